@@ -0,0 +1,68 @@
+//! Paging and aggregate queries over a single profile with 100k entries.
+
+use std::hint::black_box;
+
+use chrono::{Duration, NaiveDate};
+use criterion::{criterion_group, criterion_main, Criterion};
+use rusqlite::Connection;
+use tauri_app_lib::entries::{self, EntryFilter, EntryInput};
+use tauri_app_lib::totals::{self, GroupBy};
+use tauri_app_lib::{db, profiles};
+
+const ENTRIES: i64 = 100_000;
+const ORGANIZATIONS: i64 = 40;
+
+fn seeded() -> (Connection, i64) {
+    let mut conn = db::open_in_memory().unwrap();
+    let profile = profiles::create(&conn, "Benchmark").unwrap();
+    let first_day = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+    let tx = conn.transaction().unwrap();
+    for i in 0..ENTRIES {
+        let input = EntryInput {
+            place: format!("Organization {}", i % ORGANIZATIONS),
+            date: (first_day + Duration::days(i % 3650)).to_string(),
+            duration_minutes: Some(30 + i % 240),
+            notes: format!("Shift {i}"),
+            ..Default::default()
+        };
+        entries::create(&tx, profile.id, &input).unwrap();
+    }
+    tx.commit().unwrap();
+    (conn, profile.id)
+}
+
+fn queries(c: &mut Criterion) {
+    let (conn, profile_id) = seeded();
+    let all = EntryFilter::profile(profile_id);
+    let one_year = EntryFilter {
+        from: Some("2020-01-01".into()),
+        to: Some("2020-12-31".into()),
+        ..all.clone()
+    };
+
+    c.bench_function("first page", |b| {
+        b.iter(|| entries::page(&conn, black_box(&all), 1, 10).unwrap())
+    });
+    c.bench_function("last page", |b| {
+        let last = (ENTRIES / 10) as u32;
+        b.iter(|| entries::page(&conn, black_box(&all), last, 10).unwrap())
+    });
+    c.bench_function("first page of one year", |b| {
+        b.iter(|| entries::page(&conn, black_box(&one_year), 1, 10).unwrap())
+    });
+    c.bench_function("distinct years", |b| {
+        b.iter(|| totals::years(&conn, black_box(profile_id)).unwrap())
+    });
+    for (name, group_by) in [
+        ("totals by year", GroupBy::Year),
+        ("totals by month", GroupBy::Month),
+        ("totals by organization", GroupBy::Organization),
+    ] {
+        c.bench_function(name, |b| {
+            b.iter(|| totals::totals(&conn, black_box(&all), group_by).unwrap())
+        });
+    }
+}
+
+criterion_group!(benches, queries);
+criterion_main!(benches);
@@ -0,0 +1,118 @@
+//! The approval workflow of an entry: it starts as a draft, is submitted for approval,
+//! and is then approved or rejected with a reason. A rejected entry can be fixed and
+//! submitted again. Approved entries can no longer be edited or deleted unless they are
+//! reopened as drafts, which also needs a reason. Every move is recorded as a
+//! [revision](crate::revisions), so it is sealed in the profile's chain like any other
+//! change.
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ValueRef};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::entries::{self, VolunteerEntry};
+use crate::error::{Error, Result};
+use crate::revisions::{self, Action};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryStatus {
+    Draft,
+    Submitted,
+    Approved,
+    Rejected,
+}
+
+impl EntryStatus {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            EntryStatus::Draft => "draft",
+            EntryStatus::Submitted => "submitted",
+            EntryStatus::Approved => "approved",
+            EntryStatus::Rejected => "rejected",
+        }
+    }
+
+    /// Entries logged before the workflow existed, which were all final.
+    pub(crate) fn legacy() -> Self {
+        EntryStatus::Approved
+    }
+
+    /// Whether an entry may go from this status to `next`.
+    pub fn can_become(self, next: EntryStatus) -> bool {
+        use EntryStatus::*;
+        matches!(
+            (self, next),
+            (Draft, Submitted)
+                | (Submitted, Approved)
+                | (Submitted, Rejected)
+                | (Submitted, Draft)
+                | (Rejected, Submitted)
+                | (Rejected, Draft)
+                | (Approved, Draft)
+        )
+    }
+}
+
+impl FromSql for EntryStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Ok(match value.as_str()? {
+            "draft" => EntryStatus::Draft,
+            "submitted" => EntryStatus::Submitted,
+            "approved" => EntryStatus::Approved,
+            "rejected" => EntryStatus::Rejected,
+            other => return Err(FromSqlError::Other(format!("status {other}").into())),
+        })
+    }
+}
+
+/// Moves an entry to `status`. Rejecting needs a reason, which is kept on the entry until
+/// it moves on. Reopening an approved entry needs one too. The move is recorded as an
+/// update whose reason says where the entry went and why.
+pub fn set_status(
+    conn: &Connection,
+    id: i64,
+    status: EntryStatus,
+    reason: &str,
+) -> Result<VolunteerEntry> {
+    let entry = entries::get(conn, id)?;
+    if !entry.status.can_become(status) {
+        return Err(Error::Invalid(format!(
+            "a {} entry cannot be {}",
+            entry.status.as_str(),
+            match status {
+                EntryStatus::Draft => "made a draft again",
+                other => other.as_str(),
+            }
+        )));
+    }
+    let reason = reason.trim();
+    let reopening = entry.status == EntryStatus::Approved;
+    if status == EntryStatus::Rejected && reason.is_empty() {
+        return Err(Error::Invalid(
+            "give a reason for rejecting the entry".into(),
+        ));
+    }
+    if reopening && reason.is_empty() {
+        return Err(Error::Invalid(
+            "give a reason for reopening the approved entry".into(),
+        ));
+    }
+    let kept = if status == EntryStatus::Rejected {
+        reason
+    } else {
+        ""
+    };
+    conn.execute(
+        "UPDATE entries SET status = ?1, status_reason = ?2 WHERE id = ?3",
+        params![status.as_str(), kept, id],
+    )?;
+    let entry = entries::get(conn, id)?;
+    let recorded = match status {
+        EntryStatus::Draft if reopening => format!("reopened: {reason}"),
+        EntryStatus::Draft => "made a draft again".to_string(),
+        EntryStatus::Rejected => format!("rejected: {reason}"),
+        other => other.as_str().to_string(),
+    };
+    revisions::record(conn, &entry, Action::Update, &recorded)?;
+    Ok(entry)
+}
@@ -0,0 +1,879 @@
+//! Portable `.vlog` archives: a zip holding a versioned `manifest.json` and the profiles,
+//! organizations and entries as JSON, for moving a whole log to another machine. The
+//! entries' revisions and hash chains go along, so a log restored in place of another
+//! still verifies against heads printed before it was archived, as do their tags and
+//! sign-offs and the profiles' program enrollments and goals.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::approval::EntryStatus;
+use crate::chain::{self, Record};
+use crate::entries::{self, VolunteerEntry};
+use crate::error::{Error, Result};
+use crate::organizations::{self, Organization, OrganizationInput};
+use crate::revisions::{self, Action, EntryRevision};
+use crate::tags::{self, Tag};
+use crate::{backup, dates, migrations};
+
+pub const EXTENSION: &str = "vlog";
+
+/// Bumped whenever the archive layout changes in a way older readers can't follow.
+pub const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const PROFILES: &str = "profiles.json";
+const ORGANIZATIONS: &str = "organizations.json";
+const ENTRIES: &str = "entries.json";
+const REVISIONS: &str = "revisions.json";
+const CHAIN: &str = "chain.json";
+const TAGS: &str = "tags.json";
+const ENTRY_TAGS: &str = "entry_tags.json";
+const ATTESTATIONS: &str = "attestations.json";
+const ENROLLMENTS: &str = "enrollments.json";
+const GOALS: &str = "goals.json";
+const ATTACHMENTS_DIR: &str = "attachments/";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format_version: u32,
+    /// The database schema version the records were written from.
+    pub schema_version: u32,
+    pub app_version: String,
+    /// Local `YYYY-MM-DDTHH:MM:SS`.
+    pub exported_at: String,
+    pub profiles: usize,
+    pub organizations: usize,
+    pub entries: usize,
+    /// Archives written before entries had revisions have none.
+    #[serde(default)]
+    pub revisions: usize,
+    /// Records of the profiles' hash chains. Archives written before chains were archived
+    /// have none, and their revisions are sealed afresh when restored.
+    #[serde(default)]
+    pub chain: usize,
+    /// Archives written before tags, sign-offs, enrollments or goals were archived have
+    /// none of them.
+    #[serde(default)]
+    pub tags: usize,
+    #[serde(default)]
+    pub entry_tags: usize,
+    #[serde(default)]
+    pub attestations: usize,
+    #[serde(default)]
+    pub enrollments: usize,
+    #[serde(default)]
+    pub goals: usize,
+    /// Archive paths of files under `attachments/`. Entries have no attachments yet, so
+    /// this is always empty, but readers must accept it.
+    #[serde(default)]
+    pub attachments: Vec<String>,
+}
+
+/// A profile as archived. The PIN hash goes along so a restored profile stays locked.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedProfile {
+    id: i64,
+    name: String,
+    pin_hash: Option<String>,
+    #[serde(default)]
+    deleted_at: Option<i64>,
+    #[serde(default)]
+    birthdate: Option<String>,
+    /// The profile's [reporting periods](crate::periods) as stored.
+    #[serde(default)]
+    reporting_periods: Option<String>,
+}
+
+/// One tag on one entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedEntryTag {
+    entry_id: i64,
+    tag_id: i64,
+}
+
+/// An imported [attestation](crate::attestation) and the entries it verified.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedAttestation {
+    id: i64,
+    profile_id: i64,
+    organization: String,
+    public_key: String,
+    document: String,
+    imported_at: i64,
+    entry_ids: Vec<i64>,
+}
+
+/// A profile's enrollment in a [program](crate::programs), with the program as stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedEnrollment {
+    id: i64,
+    profile_id: i64,
+    name: String,
+    program: String,
+    enrolled_at: i64,
+}
+
+/// A [goal](crate::goals) as stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ArchivedGoal {
+    id: i64,
+    profile_id: i64,
+    kind: String,
+    minutes: i64,
+    organization_id: Option<i64>,
+    start_date: String,
+    deadline: Option<String>,
+    created_at: i64,
+}
+
+/// Everything an archive holds besides its manifest.
+struct Contents {
+    profiles: Vec<ArchivedProfile>,
+    organizations: Vec<Organization>,
+    entries: Vec<VolunteerEntry>,
+    /// Revisions by the id of their entry in the archive.
+    history: HashMap<i64, Vec<EntryRevision>>,
+    chain: Vec<Record>,
+    tags: Vec<Tag>,
+    entry_tags: Vec<ArchivedEntryTag>,
+    attestations: Vec<ArchivedAttestation>,
+    enrollments: Vec<ArchivedEnrollment>,
+    goals: Vec<ArchivedGoal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RestoreMode {
+    /// Delete the whole log and load the archive in its place.
+    Replace,
+    /// Add what the archive has and the log lacks: profiles match by name,
+    /// organizations and tags by name key, and entries already logged are skipped.
+    /// Sign-offs stay behind, because they name entries by their ids in the archive and
+    /// merged entries get new ones.
+    Merge,
+}
+
+/// What a restore added.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreSummary {
+    pub profiles: usize,
+    pub organizations: usize,
+    pub entries: usize,
+    /// Entries left out of a merge because the log already has them.
+    pub skipped_entries: usize,
+    pub tags: usize,
+    /// Sign-offs left out of a merge.
+    pub skipped_attestations: usize,
+    /// Entries whose date can't be read, each with the reason. They are restored rejected,
+    /// to be corrected. Archives from before dates were checked may have them.
+    pub unreadable_entries: Vec<String>,
+}
+
+/// Writes every profile, organization and entry as a `.vlog` archive.
+pub fn export(conn: &Connection) -> Result<Vec<u8>> {
+    let profiles = archived_profiles(conn)?;
+    let organizations = organizations::list(conn)?;
+    let entries = all_entries(conn)?;
+    let revisions = revisions::all(conn)?;
+    let chain = chain::all(conn)?;
+    let tags = tags::list(conn)?;
+    let entry_tags = archived_entry_tags(conn)?;
+    let attestations = archived_attestations(conn)?;
+    let enrollments = archived_enrollments(conn)?;
+    let goals = archived_goals(conn)?;
+    let manifest = Manifest {
+        format_version: FORMAT_VERSION,
+        schema_version: migrations::current_version(conn)?,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: backup::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+        profiles: profiles.len(),
+        organizations: organizations.len(),
+        entries: entries.len(),
+        revisions: revisions.len(),
+        chain: chain.len(),
+        tags: tags.len(),
+        entry_tags: entry_tags.len(),
+        attestations: attestations.len(),
+        enrollments: enrollments.len(),
+        goals: goals.len(),
+        attachments: Vec::new(),
+    };
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    write_json(&mut zip, MANIFEST, &manifest, options)?;
+    write_json(&mut zip, PROFILES, &profiles, options)?;
+    write_json(&mut zip, ORGANIZATIONS, &organizations, options)?;
+    write_json(&mut zip, ENTRIES, &entries, options)?;
+    write_json(&mut zip, REVISIONS, &revisions, options)?;
+    write_json(&mut zip, CHAIN, &chain, options)?;
+    write_json(&mut zip, TAGS, &tags, options)?;
+    write_json(&mut zip, ENTRY_TAGS, &entry_tags, options)?;
+    write_json(&mut zip, ATTESTATIONS, &attestations, options)?;
+    write_json(&mut zip, ENROLLMENTS, &enrollments, options)?;
+    write_json(&mut zip, GOALS, &goals, options)?;
+    zip.add_directory(ATTACHMENTS_DIR, options)?;
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Reads an archive's manifest, failing if this app can't restore it.
+pub fn manifest(data: &[u8]) -> Result<Manifest> {
+    read_manifest(&mut ZipArchive::new(Cursor::new(data))?)
+}
+
+/// Loads an archive into the log in one transaction.
+pub fn restore(conn: &mut Connection, data: &[u8], mode: RestoreMode) -> Result<RestoreSummary> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    read_manifest(&mut archive)?;
+    let revisions: Vec<EntryRevision> = read_optional(&mut archive, REVISIONS)?;
+    let mut history: HashMap<i64, Vec<EntryRevision>> = HashMap::new();
+    for revision in revisions {
+        history.entry(revision.entry_id).or_default().push(revision);
+    }
+    let contents = Contents {
+        profiles: read_json(&mut archive, PROFILES)?,
+        organizations: read_json(&mut archive, ORGANIZATIONS)?,
+        entries: read_json(&mut archive, ENTRIES)?,
+        history,
+        chain: read_optional(&mut archive, CHAIN)?,
+        tags: read_optional(&mut archive, TAGS)?,
+        entry_tags: read_optional(&mut archive, ENTRY_TAGS)?,
+        attestations: read_optional(&mut archive, ATTESTATIONS)?,
+        enrollments: read_optional(&mut archive, ENROLLMENTS)?,
+        goals: read_optional(&mut archive, GOALS)?,
+    };
+
+    let tx = conn.transaction()?;
+    let summary = match mode {
+        RestoreMode::Replace => replace(&tx, &contents)?,
+        // Merged entries and revisions get new ids, which the archive's chain doesn't
+        // seal, so they are sealed onto this log's chains instead.
+        RestoreMode::Merge => merge(&tx, &contents)?,
+    };
+    tx.commit()?;
+    Ok(summary)
+}
+
+fn read_manifest<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<Manifest> {
+    let manifest: Manifest = read_json(archive, MANIFEST)?;
+    if manifest.format_version == 0 || manifest.format_version > FORMAT_VERSION {
+        return Err(Error::Invalid(format!(
+            "archive format {} is not one this app reads (up to {FORMAT_VERSION})",
+            manifest.format_version
+        )));
+    }
+    let latest = migrations::latest_version();
+    if manifest.schema_version > latest {
+        return Err(Error::Invalid(format!(
+            "archive was written by a newer app (schema {}, this app supports {latest})",
+            manifest.schema_version
+        )));
+    }
+    Ok(manifest)
+}
+
+fn write_json<W: Write + Seek, T: Serialize>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    value: &T,
+    options: SimpleFileOptions,
+) -> Result<()> {
+    zip.start_file(name, options)?;
+    serde_json::to_writer_pretty(zip, value)?;
+    Ok(())
+}
+
+fn read_json<R: Read + Seek, T: DeserializeOwned>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<T> {
+    let file = archive
+        .by_name(name)
+        .map_err(|_| Error::Invalid(format!("archive has no {name}")))?;
+    Ok(serde_json::from_reader(file)?)
+}
+
+/// Reads a file that archives written by older versions of the app may lack.
+fn read_optional<R: Read + Seek, T: DeserializeOwned + Default>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<T> {
+    if archive.index_for_name(name).is_none() {
+        return Ok(T::default());
+    }
+    read_json(archive, name)
+}
+
+fn archived_profiles(conn: &Connection) -> Result<Vec<ArchivedProfile>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, pin_hash, deleted_at, birthdate, reporting_periods
+         FROM profiles ORDER BY id",
+    )?;
+    let profiles = stmt
+        .query_map([], |row| {
+            Ok(ArchivedProfile {
+                id: row.get("id")?,
+                name: row.get("name")?,
+                pin_hash: row.get("pin_hash")?,
+                deleted_at: row.get("deleted_at")?,
+                birthdate: row.get("birthdate")?,
+                reporting_periods: row.get("reporting_periods")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(profiles)
+}
+
+fn archived_entry_tags(conn: &Connection) -> Result<Vec<ArchivedEntryTag>> {
+    let mut stmt =
+        conn.prepare("SELECT entry_id, tag_id FROM entry_tags ORDER BY entry_id, tag_id")?;
+    let entry_tags = stmt
+        .query_map([], |row| {
+            Ok(ArchivedEntryTag {
+                entry_id: row.get("entry_id")?,
+                tag_id: row.get("tag_id")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entry_tags)
+}
+
+fn archived_attestations(conn: &Connection) -> Result<Vec<ArchivedAttestation>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, profile_id, organization, public_key, document, imported_at
+         FROM attestations ORDER BY id",
+    )?;
+    let mut attestations = stmt
+        .query_map([], |row| {
+            Ok(ArchivedAttestation {
+                id: row.get("id")?,
+                profile_id: row.get("profile_id")?,
+                organization: row.get("organization")?,
+                public_key: row.get("public_key")?,
+                document: row.get("document")?,
+                imported_at: row.get("imported_at")?,
+                entry_ids: Vec::new(),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut stmt = conn.prepare(
+        "SELECT entry_id FROM attested_entries WHERE attestation_id = ?1 ORDER BY entry_id",
+    )?;
+    for attestation in &mut attestations {
+        attestation.entry_ids = stmt
+            .query_map([attestation.id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+    }
+    Ok(attestations)
+}
+
+fn archived_enrollments(conn: &Connection) -> Result<Vec<ArchivedEnrollment>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, profile_id, name, program, enrolled_at FROM program_enrollments ORDER BY id",
+    )?;
+    let enrollments = stmt
+        .query_map([], |row| {
+            Ok(ArchivedEnrollment {
+                id: row.get("id")?,
+                profile_id: row.get("profile_id")?,
+                name: row.get("name")?,
+                program: row.get("program")?,
+                enrolled_at: row.get("enrolled_at")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(enrollments)
+}
+
+fn archived_goals(conn: &Connection) -> Result<Vec<ArchivedGoal>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, profile_id, kind, minutes, organization_id, start_date, deadline, created_at
+         FROM goals ORDER BY id",
+    )?;
+    let goals = stmt
+        .query_map([], |row| {
+            Ok(ArchivedGoal {
+                id: row.get("id")?,
+                profile_id: row.get("profile_id")?,
+                kind: row.get("kind")?,
+                minutes: row.get("minutes")?,
+                organization_id: row.get("organization_id")?,
+                start_date: row.get("start_date")?,
+                deadline: row.get("deadline")?,
+                created_at: row.get("created_at")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(goals)
+}
+
+fn all_entries(conn: &Connection) -> Result<Vec<VolunteerEntry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM entries e ORDER BY e.id",
+        entries::columns_of("e")
+    ))?;
+    let entries = stmt
+        .query_map([], VolunteerEntry::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+fn insert_entry(
+    conn: &Connection,
+    id: Option<i64>,
+    profile_id: i64,
+    organization_id: i64,
+    place: &str,
+    entry: &VolunteerEntry,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO entries (id, profile_id, organization_id, place, date, start_time,
+         end_time, duration_minutes, notes, deleted_at, status, status_reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            id,
+            profile_id,
+            organization_id,
+            place,
+            entry.date,
+            entry.start_time,
+            entry.end_time,
+            entry.duration_minutes,
+            entry.notes,
+            entry.deleted_at,
+            entry.status.as_str(),
+            entry.status_reason
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// `entry` with its date written `YYYY-MM-DD`. A date that can't be read is kept as it is
+/// and the entry rejected until it is corrected, as migration 21 does; the problem is
+/// returned with it.
+fn readable(entry: &VolunteerEntry) -> (VolunteerEntry, Option<String>) {
+    match dates::normalize(&entry.date) {
+        Ok(date) => {
+            let entry = VolunteerEntry {
+                date,
+                ..entry.clone()
+            };
+            (entry, None)
+        }
+        Err(e) => {
+            let rejected = VolunteerEntry {
+                status: EntryStatus::Rejected,
+                status_reason: format!("the date \"{}\" could not be read; correct it", entry.date),
+                ..entry.clone()
+            };
+            let problem = format!("entry {} at {}: {e}", entry.id, entry.place);
+            (rejected, Some(problem))
+        }
+    }
+}
+
+/// Records a revision for an entry whose date [`readable`] rewrote or could not read.
+fn record_repair(
+    conn: &Connection,
+    archived: &VolunteerEntry,
+    restored: &VolunteerEntry,
+    problem: Option<&str>,
+) -> Result<()> {
+    if problem.is_some() {
+        return revisions::record(conn, restored, Action::Update, "date could not be read");
+    }
+    if archived.date == restored.date {
+        return Ok(());
+    }
+    revisions::record(conn, restored, Action::Update, "date written as YYYY-MM-DD")
+}
+
+/// Carries the archived revisions of `entry` over to its copy in the log, `restored`, or
+/// starts its history afresh if the archive has none. Organization ids are mapped through
+/// `organization_ids` when merging; a replace keeps every id.
+fn restore_history(
+    conn: &Connection,
+    entry: &VolunteerEntry,
+    restored: &VolunteerEntry,
+    history: &HashMap<i64, Vec<EntryRevision>>,
+    organization_ids: Option<&HashMap<i64, Organization>>,
+) -> Result<()> {
+    let Some(revisions) = history.get(&entry.id) else {
+        return revisions::record(conn, restored, Action::Create, "restored from an archive");
+    };
+    for revision in revisions {
+        let organization_id = match organization_ids {
+            Some(ids) => ids
+                .get(&revision.organization_id)
+                .map_or(restored.organization_id, |o| o.id),
+            None => revision.organization_id,
+        };
+        let copied = EntryRevision {
+            entry_id: restored.id,
+            profile_id: restored.profile_id,
+            organization_id,
+            ..revision.clone()
+        };
+        let id = organization_ids.is_none().then_some(revision.id);
+        revisions::insert(conn, &copied, id)?;
+    }
+    Ok(())
+}
+
+fn replace(conn: &Connection, contents: &Contents) -> Result<RestoreSummary> {
+    // Archives don't carry organization keys, so keep this log's across the replace.
+    conn.execute_batch(
+        "CREATE TEMP TABLE kept_keys AS
+         SELECT o.name_key, k.public_key, k.secret_key, k.created_at
+         FROM organization_keys k JOIN organizations o ON o.id = k.organization_id;",
+    )?;
+    conn.execute_batch(
+        "DELETE FROM timer_sessions;
+         DELETE FROM attested_entries;
+         DELETE FROM attestations;
+         DELETE FROM program_enrollments;
+         DELETE FROM goals;
+         DELETE FROM entry_tags;
+         DELETE FROM tags;
+         DELETE FROM entries;
+         DELETE FROM entry_revisions;
+         DELETE FROM profiles;
+         DELETE FROM entry_chain;
+         DELETE FROM organizations;",
+    )?;
+    // Revisions keep their ids, so the archived chain still seals them.
+    chain::restore(conn, &contents.chain)?;
+    for profile in &contents.profiles {
+        conn.execute(
+            "INSERT INTO profiles (id, name, pin_hash, deleted_at, birthdate, reporting_periods)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                profile.id,
+                profile.name,
+                profile.pin_hash,
+                profile.deleted_at,
+                profile.birthdate,
+                profile.reporting_periods
+            ],
+        )?;
+    }
+    for organization in &contents.organizations {
+        conn.execute(
+            "INSERT INTO organizations
+             (id, name, name_key, contact_name, email, phone, address, notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                organization.id,
+                organization.name,
+                organizations::name_key(&organization.name),
+                organization.contact_name,
+                organization.email,
+                organization.phone,
+                organization.address,
+                organization.notes
+            ],
+        )?;
+    }
+    conn.execute_batch(
+        "INSERT INTO organization_keys (organization_id, public_key, secret_key, created_at)
+         SELECT o.id, k.public_key, k.secret_key, k.created_at
+         FROM temp.kept_keys k JOIN organizations o ON o.name_key = k.name_key;
+         DROP TABLE temp.kept_keys;",
+    )?;
+    for tag in &contents.tags {
+        conn.execute(
+            "INSERT INTO tags (id, name, name_key, sdg) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                tag.id,
+                tag.name,
+                organizations::name_key(&tag.name),
+                tag.sdg
+            ],
+        )?;
+    }
+    let mut unreadable_entries = Vec::new();
+    for archived in &contents.entries {
+        let (entry, problem) = readable(archived);
+        insert_entry(
+            conn,
+            Some(entry.id),
+            entry.profile_id,
+            entry.organization_id,
+            &entry.place,
+            &entry,
+        )?;
+        restore_history(conn, archived, &entry, &contents.history, None)?;
+        record_repair(conn, archived, &entry, problem.as_deref())?;
+        unreadable_entries.extend(problem);
+    }
+    for entry_tag in &contents.entry_tags {
+        conn.execute(
+            "INSERT INTO entry_tags (entry_id, tag_id) VALUES (?1, ?2)",
+            params![entry_tag.entry_id, entry_tag.tag_id],
+        )?;
+    }
+    for attestation in &contents.attestations {
+        conn.execute(
+            "INSERT INTO attestations
+             (id, profile_id, organization, public_key, document, imported_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                attestation.id,
+                attestation.profile_id,
+                attestation.organization,
+                attestation.public_key,
+                attestation.document,
+                attestation.imported_at
+            ],
+        )?;
+        for entry_id in &attestation.entry_ids {
+            conn.execute(
+                "INSERT INTO attested_entries (attestation_id, entry_id) VALUES (?1, ?2)",
+                params![attestation.id, entry_id],
+            )?;
+        }
+    }
+    for enrollment in &contents.enrollments {
+        insert_enrollment(conn, Some(enrollment.id), enrollment.profile_id, enrollment)?;
+    }
+    for goal in &contents.goals {
+        insert_goal(
+            conn,
+            Some(goal.id),
+            goal.profile_id,
+            goal.organization_id,
+            goal,
+        )?;
+    }
+    Ok(RestoreSummary {
+        profiles: contents.profiles.len(),
+        organizations: contents.organizations.len(),
+        entries: contents.entries.len(),
+        skipped_entries: 0,
+        tags: contents.tags.len(),
+        skipped_attestations: 0,
+        unreadable_entries,
+    })
+}
+
+fn merge(conn: &Connection, contents: &Contents) -> Result<RestoreSummary> {
+    let mut summary = RestoreSummary::default();
+
+    let mut profile_ids = HashMap::new();
+    for profile in &contents.profiles {
+        let existing: Option<i64> = conn
+            .query_row("SELECT id FROM profiles WHERE name = ?1", [&profile.name], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO profiles
+                     (name, pin_hash, deleted_at, birthdate, reporting_periods)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        profile.name,
+                        profile.pin_hash,
+                        profile.deleted_at,
+                        profile.birthdate,
+                        profile.reporting_periods
+                    ],
+                )?;
+                summary.profiles += 1;
+                conn.last_insert_rowid()
+            }
+        };
+        profile_ids.insert(profile.id, id);
+    }
+
+    let mut organization_ids = HashMap::new();
+    for organization in &contents.organizations {
+        let local = match organizations::find_by_name(conn, &organization.name)? {
+            Some(found) => found,
+            None => {
+                summary.organizations += 1;
+                organizations::create(
+                    conn,
+                    &OrganizationInput {
+                        name: organization.name.clone(),
+                        contact_name: organization.contact_name.clone(),
+                        email: organization.email.clone(),
+                        phone: organization.phone.clone(),
+                        address: organization.address.clone(),
+                        notes: organization.notes.clone(),
+                    },
+                )?
+            }
+        };
+        organization_ids.insert(organization.id, local);
+    }
+
+    // Skipped entries map to the entry already logged, so their tags still carry over.
+    let mut entry_ids = HashMap::new();
+    for archived in &contents.entries {
+        let (entry, problem) = readable(archived);
+        let (Some(&profile_id), Some(organization)) = (
+            profile_ids.get(&entry.profile_id),
+            organization_ids.get(&entry.organization_id),
+        ) else {
+            return Err(Error::Invalid(format!(
+                "entry {} in the archive refers to a missing profile or organization",
+                entry.id
+            )));
+        };
+        let logged: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM entries WHERE profile_id = ?1 AND organization_id = ?2
+                 AND date = ?3 AND start_time IS ?4 AND duration_minutes = ?5",
+                params![
+                    profile_id,
+                    organization.id,
+                    entry.date,
+                    entry.start_time,
+                    entry.duration_minutes
+                ],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(id) = logged {
+            entry_ids.insert(archived.id, id);
+            summary.skipped_entries += 1;
+            continue;
+        }
+        let id = insert_entry(conn, None, profile_id, organization.id, &organization.name, &entry)?;
+        let merged = VolunteerEntry {
+            id,
+            profile_id,
+            organization_id: organization.id,
+            place: organization.name.clone(),
+            ..entry
+        };
+        restore_history(
+            conn,
+            archived,
+            &merged,
+            &contents.history,
+            Some(&organization_ids),
+        )?;
+        record_repair(conn, archived, &merged, problem.as_deref())?;
+        summary.unreadable_entries.extend(problem);
+        entry_ids.insert(archived.id, id);
+        summary.entries += 1;
+    }
+
+    let mut tag_ids = HashMap::new();
+    for tag in &contents.tags {
+        let local = match tags::find_by_name(conn, &tag.name)? {
+            Some(found) => found,
+            None => {
+                summary.tags += 1;
+                tags::create(conn, &tag.name, tag.sdg)?
+            }
+        };
+        tag_ids.insert(tag.id, local.id);
+    }
+    for entry_tag in &contents.entry_tags {
+        if let (Some(entry_id), Some(tag_id)) = (
+            entry_ids.get(&entry_tag.entry_id),
+            tag_ids.get(&entry_tag.tag_id),
+        ) {
+            conn.execute(
+                "INSERT OR IGNORE INTO entry_tags (entry_id, tag_id) VALUES (?1, ?2)",
+                params![entry_id, tag_id],
+            )?;
+        }
+    }
+    summary.skipped_attestations = contents.attestations.len();
+
+    for enrollment in &contents.enrollments {
+        if let Some(&profile_id) = profile_ids.get(&enrollment.profile_id) {
+            insert_enrollment(conn, None, profile_id, enrollment)?;
+        }
+    }
+    for goal in &contents.goals {
+        let Some(&profile_id) = profile_ids.get(&goal.profile_id) else {
+            continue;
+        };
+        let organization_id = match goal.organization_id {
+            Some(id) => match organization_ids.get(&id) {
+                Some(organization) => Some(organization.id),
+                None => continue,
+            },
+            None => None,
+        };
+        let set: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM goals WHERE profile_id = ?1 AND kind = ?2
+             AND minutes = ?3 AND organization_id IS ?4 AND start_date = ?5
+             AND deadline IS ?6)",
+            params![
+                profile_id,
+                goal.kind,
+                goal.minutes,
+                organization_id,
+                goal.start_date,
+                goal.deadline
+            ],
+            |row| row.get(0),
+        )?;
+        if !set {
+            insert_goal(conn, None, profile_id, organization_id, goal)?;
+        }
+    }
+    Ok(summary)
+}
+
+/// Inserts an archived enrollment unless the profile is already enrolled in a program of
+/// the same name.
+fn insert_enrollment(
+    conn: &Connection,
+    id: Option<i64>,
+    profile_id: i64,
+    enrollment: &ArchivedEnrollment,
+) -> Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO program_enrollments (id, profile_id, name, program, enrolled_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            id,
+            profile_id,
+            enrollment.name,
+            enrollment.program,
+            enrollment.enrolled_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn insert_goal(
+    conn: &Connection,
+    id: Option<i64>,
+    profile_id: i64,
+    organization_id: Option<i64>,
+    goal: &ArchivedGoal,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO goals
+         (id, profile_id, kind, minutes, organization_id, start_date, deadline, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            profile_id,
+            goal.kind,
+            goal.minutes,
+            organization_id,
+            goal.start_date,
+            goal.deadline,
+            goal.created_at
+        ],
+    )?;
+    Ok(())
+}
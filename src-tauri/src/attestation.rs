@@ -0,0 +1,445 @@
+//! Supervisor sign-off. An organization holds an Ed25519 keypair; a volunteer sends its
+//! supervisor an unsigned attestation listing some of their entries there, the
+//! supervisor's instance signs it with the organization's key, and the volunteer's
+//! instance checks the signature against the key it trusts for that organization before
+//! marking those entries verified. A sign-off covers an entry's organization, date, times
+//! and duration, so editing any of them afterwards shows the sign-off as invalidated.
+//!
+//! Secret keys are stored in the database, which is only as private as the database file
+//! (see [`crate::encryption`]).
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use rand_core::OsRng;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::entries::{self, EntryFilter, VolunteerEntry};
+use crate::error::{Error, Result};
+use crate::organizations::{self, Organization};
+use crate::{backup, profiles};
+
+pub const EXTENSION: &str = "attestation";
+
+/// Bumped whenever what gets signed changes. Version 2 signs each entry's organization.
+pub const FORMAT_VERSION: u32 = 2;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizationKey {
+    pub organization_id: i64,
+    /// Hex-encoded Ed25519 public key.
+    pub public_key: String,
+    /// Whether this log holds the secret key, and so can sign for the organization.
+    pub can_sign: bool,
+}
+
+/// An entry as the supervisor is asked to confirm it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestedEntry {
+    /// The entry's id in the volunteer's log.
+    pub entry_id: i64,
+    /// The id of the entry's organization in the volunteer's log; absent from attestations
+    /// of format 1, which are matched by the organization's name instead.
+    #[serde(default)]
+    pub organization_id: Option<i64>,
+    pub date: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub duration_minutes: i64,
+    /// Shown to the supervisor but not signed, so notes can still be edited.
+    #[serde(default)]
+    pub notes: String,
+}
+
+impl AttestedEntry {
+    fn of(entry: &VolunteerEntry) -> Self {
+        Self {
+            entry_id: entry.id,
+            organization_id: Some(entry.organization_id),
+            date: entry.date.clone(),
+            start_time: entry.start_time.clone(),
+            end_time: entry.end_time.clone(),
+            duration_minutes: entry.duration_minutes,
+            notes: entry.notes.clone(),
+        }
+    }
+
+    /// Whether `entry` still has the values that were signed, at `organization`.
+    fn matches(&self, entry: &VolunteerEntry, organization: &str) -> bool {
+        let same_organization = match self.organization_id {
+            Some(id) => id == entry.organization_id,
+            None => organizations::name_key(&entry.place) == organizations::name_key(organization),
+        };
+        same_organization
+            && self.entry_id == entry.id
+            && self.date == entry.date
+            && self.start_time == entry.start_time
+            && self.end_time == entry.end_time
+            && self.duration_minutes == entry.duration_minutes
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignOff {
+    /// The supervisor's name and role, as they gave it.
+    pub signer: String,
+    /// Local `YYYY-MM-DDTHH:MM:SS` on the supervisor's machine.
+    pub signed_at: String,
+    /// Hex-encoded Ed25519 public key of the organization.
+    pub public_key: String,
+    /// Hex-encoded Ed25519 signature over everything else in the attestation.
+    pub signature: String,
+}
+
+/// The portable attestation file: unsigned as a request, signed once a supervisor
+/// has signed it off.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attestation {
+    pub format_version: u32,
+    pub organization: String,
+    pub volunteer: String,
+    pub entries: Vec<AttestedEntry>,
+    #[serde(default)]
+    pub sign_off: Option<SignOff>,
+}
+
+/// What importing a signed attestation did.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub verified: usize,
+    /// Entries that changed since the request was made, or aren't in the log, and so
+    /// were not marked verified.
+    pub mismatched: Vec<i64>,
+}
+
+/// The latest sign-off of an entry.
+#[derive(Debug, Clone, Serialize)]
+pub struct EntrySignOff {
+    pub entry_id: i64,
+    pub organization: String,
+    pub signer: String,
+    pub signed_at: String,
+    /// False once the entry has been edited since it was signed.
+    pub valid: bool,
+}
+
+fn decode<const N: usize>(text: &str, what: &str) -> Result<[u8; N]> {
+    hex::decode(text.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Invalid(format!("{what} is not valid")))
+}
+
+fn verifying_key(public_key: &str) -> Result<VerifyingKey> {
+    VerifyingKey::from_bytes(&decode(public_key, "public key")?)
+        .map_err(|_| Error::Invalid("public key is not valid".into()))
+}
+
+pub fn organization_key(
+    conn: &Connection,
+    organization_id: i64,
+) -> Result<Option<OrganizationKey>> {
+    Ok(conn
+        .query_row(
+            "SELECT organization_id, public_key, secret_key IS NOT NULL
+             FROM organization_keys WHERE organization_id = ?1",
+            [organization_id],
+            |row| {
+                Ok(OrganizationKey {
+                    organization_id: row.get(0)?,
+                    public_key: row.get(1)?,
+                    can_sign: row.get(2)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+/// Creates the organization's keypair, for the log of someone who signs for it.
+pub fn generate_key(conn: &Connection, organization_id: i64) -> Result<OrganizationKey> {
+    organizations::get(conn, organization_id)?;
+    if organization_key(conn, organization_id)?.is_some() {
+        return Err(Error::Invalid(
+            "organization already has a key; volunteers trust that one".into(),
+        ));
+    }
+    let key = SigningKey::generate(&mut OsRng);
+    conn.execute(
+        "INSERT INTO organization_keys (organization_id, public_key, secret_key)
+         VALUES (?1, ?2, ?3)",
+        params![
+            organization_id,
+            hex::encode(key.verifying_key().as_bytes()),
+            hex::encode(key.to_bytes())
+        ],
+    )?;
+    organization_key(conn, organization_id)?.ok_or(Error::NotFound("organization key"))
+}
+
+/// Records the public key a supervisor gave out, so their attestations can be checked.
+pub fn trust_key(
+    conn: &Connection,
+    organization_id: i64,
+    public_key: &str,
+) -> Result<OrganizationKey> {
+    organizations::get(conn, organization_id)?;
+    let public_key = hex::encode(verifying_key(public_key)?.as_bytes());
+    if let Some(existing) = organization_key(conn, organization_id)? {
+        if existing.can_sign && existing.public_key != public_key {
+            return Err(Error::Invalid(
+                "this log signs for the organization with its own key".into(),
+            ));
+        }
+    }
+    conn.execute(
+        "INSERT INTO organization_keys (organization_id, public_key) VALUES (?1, ?2)
+         ON CONFLICT (organization_id) DO UPDATE SET public_key = excluded.public_key
+         WHERE secret_key IS NULL",
+        params![organization_id, public_key],
+    )?;
+    organization_key(conn, organization_id)?.ok_or(Error::NotFound("organization key"))
+}
+
+/// An unsigned attestation of the profile's entries at one organization in a date range,
+/// for the volunteer to send to their supervisor.
+pub fn request(conn: &Connection, filter: &EntryFilter) -> Result<Attestation> {
+    let organization_id = filter
+        .organization_id
+        .ok_or_else(|| Error::Invalid("choose the organization to ask for sign-off".into()))?;
+    let organization = organizations::get(conn, organization_id)?;
+    let mut entries = entries::list_matching(conn, filter)?;
+    if entries.is_empty() {
+        return Err(Error::Invalid("no entries to sign off".into()));
+    }
+    entries.reverse();
+    Ok(Attestation {
+        format_version: FORMAT_VERSION,
+        organization: organization.name,
+        volunteer: profiles::get(conn, filter.profile_id)?.name,
+        entries: entries.iter().map(AttestedEntry::of).collect(),
+        sign_off: None,
+    })
+}
+
+/// The bytes a signature covers: everything but the signature itself.
+fn message(
+    attestation: &Attestation,
+    signer: &str,
+    signed_at: &str,
+    public_key: &str,
+) -> Result<Vec<u8>> {
+    let entries = attestation.entries.iter();
+    let signed = if attestation.format_version < 2 {
+        serde_json::to_value(
+            entries
+                .map(|e| {
+                    (
+                        e.entry_id,
+                        &e.date,
+                        &e.start_time,
+                        &e.end_time,
+                        e.duration_minutes,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )?
+    } else {
+        serde_json::to_value(
+            entries
+                .map(|e| {
+                    (
+                        e.entry_id,
+                        e.organization_id,
+                        &e.date,
+                        &e.start_time,
+                        &e.end_time,
+                        e.duration_minutes,
+                    )
+                })
+                .collect::<Vec<_>>(),
+        )?
+    };
+    Ok(serde_json::to_vec(&(
+        attestation.format_version,
+        &attestation.organization,
+        &attestation.volunteer,
+        signed,
+        signer,
+        signed_at,
+        public_key,
+    ))?)
+}
+
+/// Signs a request with the key of the organization it names, on the supervisor's log.
+pub fn sign(conn: &Connection, mut attestation: Attestation, signer: &str) -> Result<Attestation> {
+    check_format(&attestation)?;
+    let signer = signer.trim();
+    if signer.is_empty() {
+        return Err(Error::Invalid(
+            "enter the name of whoever is signing".into(),
+        ));
+    }
+    let organization = find_organization(conn, &attestation.organization)?;
+    let secret: Option<String> = conn
+        .query_row(
+            "SELECT secret_key FROM organization_keys WHERE organization_id = ?1",
+            [organization.id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .flatten();
+    let secret = secret.ok_or_else(|| {
+        Error::Invalid(format!(
+            "this log has no signing key for {}",
+            organization.name
+        ))
+    })?;
+    let key = SigningKey::from_bytes(&decode(&secret, "signing key")?);
+    let public_key = hex::encode(key.verifying_key().as_bytes());
+    let signed_at = backup::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+    let signature = key.sign(&message(&attestation, signer, &signed_at, &public_key)?);
+    attestation.sign_off = Some(SignOff {
+        signer: signer.to_string(),
+        signed_at,
+        public_key,
+        signature: hex::encode(signature.to_bytes()),
+    });
+    Ok(attestation)
+}
+
+fn check_format(attestation: &Attestation) -> Result<()> {
+    if attestation.format_version == 0 || attestation.format_version > FORMAT_VERSION {
+        return Err(Error::Invalid(format!(
+            "attestation format {} is not one this app reads (up to {FORMAT_VERSION})",
+            attestation.format_version
+        )));
+    }
+    Ok(())
+}
+
+fn find_organization(conn: &Connection, name: &str) -> Result<Organization> {
+    organizations::find_by_name(conn, name)?
+        .ok_or_else(|| Error::Invalid(format!("no organization named {name} in this log")))
+}
+
+/// Checks the signature of an attestation against `public_key`.
+fn check_signature(attestation: &Attestation, public_key: &str) -> Result<()> {
+    let sign_off = attestation
+        .sign_off
+        .as_ref()
+        .ok_or_else(|| Error::Invalid("the attestation has not been signed".into()))?;
+    if !sign_off.public_key.eq_ignore_ascii_case(public_key) {
+        return Err(Error::Invalid(
+            "the attestation was signed with a key this log does not trust".into(),
+        ));
+    }
+    let signature = Signature::from_bytes(&decode(&sign_off.signature, "signature")?);
+    let message = message(
+        attestation,
+        &sign_off.signer,
+        &sign_off.signed_at,
+        &sign_off.public_key,
+    )?;
+    verifying_key(public_key)?
+        .verify(&message, &signature)
+        .map_err(|_| Error::Invalid("the attestation's signature does not match".into()))
+}
+
+/// Verifies a signed attestation and marks the profile's entries it covers as verified.
+/// The organization's key must have been [trusted](trust_key) beforehand, from the
+/// supervisor rather than from the file, and must not be one this log can sign with:
+/// otherwise a volunteer could sign off their own entries.
+pub fn import(
+    conn: &mut Connection,
+    profile_id: i64,
+    attestation: &Attestation,
+) -> Result<ImportSummary> {
+    check_format(attestation)?;
+    let organization = find_organization(conn, &attestation.organization)?;
+    let known = organization_key(conn, organization.id)?.ok_or_else(|| {
+        Error::Invalid(format!(
+            "no key is trusted for {} yet; trust the key your supervisor gave you first",
+            organization.name
+        ))
+    })?;
+    if known.can_sign {
+        return Err(Error::Invalid(format!(
+            "this log holds the signing key of {}, so it cannot verify its own entries",
+            organization.name
+        )));
+    }
+    check_signature(attestation, &known.public_key)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO attestations (profile_id, organization, public_key, document)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            profile_id,
+            organization.name,
+            known.public_key,
+            serde_json::to_string(attestation)?
+        ],
+    )?;
+    let attestation_id = tx.last_insert_rowid();
+    let mut summary = ImportSummary::default();
+    for attested in &attestation.entries {
+        let current = entries::get(&tx, attested.entry_id)
+            .ok()
+            .filter(|e| e.profile_id == profile_id && e.organization_id == organization.id);
+        match current {
+            Some(entry) if attested.matches(&entry, &organization.name) => {
+                tx.execute(
+                    "INSERT INTO attested_entries (attestation_id, entry_id) VALUES (?1, ?2)",
+                    params![attestation_id, entry.id],
+                )?;
+                summary.verified += 1;
+            }
+            _ => summary.mismatched.push(attested.entry_id),
+        }
+    }
+    tx.commit()?;
+    Ok(summary)
+}
+
+/// The latest sign-off of each of the profile's signed-off entries, checked again
+/// against the entry as it is now.
+pub fn sign_offs(conn: &Connection, profile_id: i64) -> Result<Vec<EntrySignOff>> {
+    let mut stmt = conn.prepare(
+        "SELECT ae.entry_id, a.public_key, a.document FROM attested_entries ae
+         JOIN attestations a ON a.id = ae.attestation_id
+         WHERE a.profile_id = ?1
+         AND a.id = (SELECT max(attestation_id) FROM attested_entries
+                     WHERE entry_id = ae.entry_id)
+         ORDER BY ae.entry_id",
+    )?;
+    let rows = stmt
+        .query_map([profile_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut sign_offs = Vec::new();
+    for (entry_id, public_key, document) in rows {
+        let attestation: Attestation = serde_json::from_str(&document)?;
+        let Some(sign_off) = attestation.sign_off.clone() else {
+            continue;
+        };
+        let attested = attestation.entries.iter().find(|e| e.entry_id == entry_id);
+        let unchanged = match (attested, entries::get(conn, entry_id)) {
+            (Some(attested), Ok(entry)) => attested.matches(&entry, &attestation.organization),
+            _ => false,
+        };
+        sign_offs.push(EntrySignOff {
+            entry_id,
+            organization: attestation.organization.clone(),
+            signer: sign_off.signer,
+            signed_at: sign_off.signed_at,
+            valid: unchanged && check_signature(&attestation, &public_key).is_ok(),
+        });
+    }
+    Ok(sign_offs)
+}
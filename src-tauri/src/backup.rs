@@ -0,0 +1,222 @@
+//! Online backups of the database through SQLite's backup API, kept in a `backups`
+//! directory next to it and thinned out on a daily/weekly/monthly rotation.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chrono::{Datelike, Local, NaiveDateTime};
+use rusqlite::backup::Backup as SqliteBackup;
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+
+use crate::encryption;
+use crate::error::{Error, Result};
+use crate::migrations;
+
+pub const BACKUP_DIR: &str = "backups";
+
+/// Settings key of the saved [`Rotation`].
+pub const ROTATION_KEY: &str = "backup_rotation";
+
+/// How often the running app takes a backup, in addition to one at launch.
+pub const INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const FILE_FORMAT: &str = "volunteer-%Y%m%d-%H%M%S.db";
+const PAGES_PER_STEP: i32 = 256;
+const STEP_PAUSE: Duration = Duration::from_millis(10);
+
+/// How many backups to keep: the newest of each of the last `daily` days, `weekly` weeks
+/// and `monthly` months that have one. The newest backup is always kept.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rotation {
+    pub daily: usize,
+    pub weekly: usize,
+    pub monthly: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Self {
+            daily: 7,
+            weekly: 4,
+            monthly: 12,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Backup {
+    pub file: String,
+    pub path: PathBuf,
+    /// Local `YYYY-MM-DDTHH:MM:SS`.
+    pub taken_at: String,
+    pub size: u64,
+}
+
+pub fn dir_for(db_path: &Path) -> PathBuf {
+    db_path.with_file_name(BACKUP_DIR)
+}
+
+/// Local wall-clock time, which backups are named by.
+pub fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+fn taken_at(file: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(file, FILE_FORMAT).ok()
+}
+
+fn keyed(conn: Connection, passphrase: Option<&str>) -> Result<Connection> {
+    if let Some(passphrase) = passphrase {
+        encryption::apply_key(&conn, passphrase)?;
+    }
+    Ok(conn)
+}
+
+/// Copies the live database behind `conn` into `dir`, keyed with the same `passphrase`.
+pub fn create(
+    conn: &Connection,
+    dir: &Path,
+    passphrase: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<Backup> {
+    fs::create_dir_all(dir)?;
+    let file = now.format(FILE_FORMAT).to_string();
+    let path = dir.join(&file);
+    let partial = path.with_extension("db.partial");
+    let _ = fs::remove_file(&partial);
+
+    let copied = keyed(Connection::open(&partial)?, passphrase).and_then(|mut target| {
+        SqliteBackup::new(conn, &mut target)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+        Ok(())
+    });
+    if let Err(e) = copied {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, &path)?;
+    find(dir, &file)
+}
+
+/// Every backup in `dir`, newest first.
+pub fn list(dir: &Path) -> Result<Vec<Backup>> {
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut backups = Vec::new();
+    for item in read {
+        let item = item?;
+        let file = item.file_name().to_string_lossy().into_owned();
+        let Some(at) = taken_at(&file) else {
+            continue;
+        };
+        backups.push(Backup {
+            path: item.path(),
+            taken_at: at.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            size: item.metadata()?.len(),
+            file,
+        });
+    }
+    backups.sort_by(|a, b| b.file.cmp(&a.file));
+    Ok(backups)
+}
+
+/// The backup named `file`. Only names that [`list`] reports are accepted, so a caller
+/// can't point a restore outside the backup directory.
+pub fn find(dir: &Path, file: &str) -> Result<Backup> {
+    list(dir)?
+        .into_iter()
+        .find(|b| b.file == file)
+        .ok_or(Error::NotFound("backup"))
+}
+
+/// The day, week or month a backup was taken in.
+type PeriodOf = fn(&NaiveDateTime) -> (i32, u32);
+
+/// Deletes the backups `rotation` doesn't keep and returns them.
+pub fn rotate(dir: &Path, rotation: &Rotation) -> Result<Vec<Backup>> {
+    let backups = list(dir)?;
+    let mut kept: HashSet<&str> = backups.first().map(|b| b.file.as_str()).into_iter().collect();
+    let periods: [(usize, PeriodOf); 3] = [
+        (rotation.daily, |at| (at.year(), at.ordinal())),
+        (rotation.weekly, |at| (at.iso_week().year(), at.iso_week().week())),
+        (rotation.monthly, |at| (at.year(), at.month())),
+    ];
+    for (count, period) in periods {
+        let mut seen = Vec::new();
+        for backup in &backups {
+            let Some(at) = taken_at(&backup.file) else {
+                continue;
+            };
+            let key = period(&at);
+            if !seen.contains(&key) {
+                if seen.len() == count {
+                    break;
+                }
+                seen.push(key);
+                kept.insert(&backup.file);
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+    for backup in &backups {
+        if !kept.contains(backup.file.as_str()) {
+            fs::remove_file(&backup.path)?;
+            removed.push(backup.clone());
+        }
+    }
+    Ok(removed)
+}
+
+/// Fails unless the file at `path` opens with `passphrase`, passes SQLite's integrity
+/// check and has a schema this app can read.
+pub fn verify(path: &Path, passphrase: Option<&str>) -> Result<()> {
+    let conn = keyed(
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+        passphrase,
+    )?;
+    encryption::check_readable(&conn, passphrase.is_some())?;
+    let check: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+    if check != "ok" {
+        return Err(Error::Invalid(format!(
+            "{} failed the integrity check: {check}",
+            path.display()
+        )));
+    }
+    let version = migrations::current_version(&conn)?;
+    if version > migrations::latest_version() {
+        return Err(Error::Invalid(format!(
+            "{} has schema version {version}, newer than this app supports",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Overwrites the database behind `live` with the backup named `file` in `dir`. The
+/// backup is verified first and the current contents are backed up, as of `now`, before
+/// being replaced; afterwards the schema is migrated in case the backup predates it.
+pub fn restore(
+    live: &mut Connection,
+    dir: &Path,
+    file: &str,
+    passphrase: Option<&str>,
+    now: NaiveDateTime,
+) -> Result<()> {
+    let path = find(dir, file)?.path;
+    verify(&path, passphrase)?;
+    let source = keyed(
+        Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY)?,
+        passphrase,
+    )?;
+    create(live, dir, passphrase, now)?;
+    SqliteBackup::new(&source, live)?.run_to_completion(PAGES_PER_STEP, STEP_PAUSE, None)?;
+    migrations::run(live)?;
+    Ok(())
+}
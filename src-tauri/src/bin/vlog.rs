@@ -0,0 +1,925 @@
+//! `vlog`: log and report volunteering hours without the GUI. Reads and writes the same
+//! `volunteer.db` as the app and prints JSON, so it can be scripted.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::json;
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::archive::{self, RestoreMode};
+use tauri_app_lib::attestation::{self, Attestation};
+use tauri_app_lib::backup::{self, Rotation};
+use tauri_app_lib::entries::{self, EntryFilter, EntryInput};
+use tauri_app_lib::error::{Error, Result};
+use tauri_app_lib::goals::{self, GoalInput, GoalKind};
+use tauri_app_lib::organizations::{self, Organization};
+use tauri_app_lib::periods::{self, PeriodScheme, Term};
+use tauri_app_lib::profiles::{self, Profile};
+use tauri_app_lib::programs::{self, Program};
+use tauri_app_lib::pvsa::{self, Window};
+use tauri_app_lib::tags::{self, Tag};
+use tauri_app_lib::totals::{self, GroupBy};
+use tauri_app_lib::{chain, csv_io, dates, db, pins, report, revisions, settings, timer, trash};
+
+#[derive(Parser)]
+#[command(name = "vlog", version, about = "Log and report volunteering hours")]
+struct Cli {
+    /// Database file; defaults to the one the app uses.
+    #[arg(long, global = true, env = "VLOG_DB")]
+    db: Option<PathBuf>,
+    /// Passphrase of an encrypted database.
+    #[arg(long, global = true, env = "VLOG_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+    /// Profile to act on. May be left out when there is only one.
+    #[arg(long, short, global = true)]
+    profile: Option<String>,
+    /// PIN of a profile that has one.
+    #[arg(long, global = true, env = "VLOG_PIN", hide_env_values = true)]
+    pin: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Log a new entry.
+    Add(EntryArgs),
+    /// List entries, newest first.
+    List(RangeArgs),
+    /// Change some fields of an entry.
+    Edit {
+        id: i64,
+        #[command(flatten)]
+        fields: EntryArgs,
+    },
+    /// Move an entry to the trash.
+    Delete {
+        id: i64,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Submit, approve, reject or withdraw an entry, or reopen an approved one as a draft.
+    Status {
+        id: i64,
+        #[arg(value_enum)]
+        status: Status,
+        /// Why the entry is rejected or reopened; required for both.
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Show every revision of an entry, oldest first.
+    History { id: i64 },
+    /// Set an entry back to one of its revisions.
+    Revert {
+        id: i64,
+        revision: i64,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Show progress towards the Presidential Volunteer Service Award.
+    Pvsa {
+        /// Last day of the 12 months to count; defaults to today.
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        end: Option<String>,
+        /// First day of a fixed period, such as a school year.
+        #[arg(long, requires = "to")]
+        from: Option<String>,
+        /// Last day of a fixed period.
+        #[arg(long, requires = "from")]
+        to: Option<String>,
+    },
+    /// Check that the profile's log hasn't been altered outside the app.
+    Verify {
+        /// Chain head printed on an earlier report, which must still be in the chain.
+        #[arg(long)]
+        head: Option<String>,
+    },
+    /// Sum time logged, overall or grouped.
+    Total {
+        #[command(flatten)]
+        range: RangeArgs,
+        #[arg(long, value_enum)]
+        by: Option<Grouping>,
+    },
+    /// Write the log as CSV (to stdout by default) or as a PDF report.
+    Export {
+        #[command(flatten)]
+        range: RangeArgs,
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Manage profiles.
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+    /// Take, list or restore backups of the database.
+    #[command(subcommand)]
+    Backup(BackupCommand),
+    /// Move the whole log between machines as a `.vlog` archive.
+    #[command(subcommand)]
+    Archive(ArchiveCommand),
+    /// List, restore or purge deleted entries and profiles.
+    #[command(subcommand)]
+    Trash(TrashCommand),
+    /// Set hour goals and see when they will be met.
+    #[command(subcommand)]
+    Goal(GoalCommand),
+    /// Enroll in requirement programs and see how far along they are.
+    #[command(subcommand)]
+    Program(ProgramCommand),
+    /// Choose the years or terms hours are reported in.
+    #[command(subcommand)]
+    Period(PeriodCommand),
+    /// File entries under causes, and manage the tags.
+    #[command(subcommand)]
+    Tag(TagCommand),
+    /// Have a supervisor sign off entries, or sign off as one.
+    #[command(subcommand)]
+    Attest(AttestCommand),
+    /// Show or choose the timezone that decides what "today" is.
+    Timezone {
+        /// IANA name, e.g. America/Chicago.
+        zone: Option<String>,
+        /// Follow the system's timezone again.
+        #[arg(long, conflicts_with = "zone")]
+        system: bool,
+    },
+}
+
+#[derive(Args)]
+struct EntryArgs {
+    #[arg(long)]
+    place: Option<String>,
+    /// YYYY-MM-DD; defaults to today when adding.
+    #[arg(long)]
+    date: Option<String>,
+    /// HH:MM or YYYY-MM-DDTHH:MM.
+    #[arg(long)]
+    start: Option<String>,
+    /// HH:MM or YYYY-MM-DDTHH:MM; an earlier bare time means the next day.
+    #[arg(long)]
+    end: Option<String>,
+    /// Time worked; worked out from --start and --end when left out.
+    #[arg(long)]
+    hours: Option<f64>,
+    #[arg(long)]
+    notes: Option<String>,
+    /// Why the entry is being added or changed; kept in its history.
+    #[arg(long)]
+    reason: Option<String>,
+}
+
+#[derive(Args)]
+struct RangeArgs {
+    /// First day to include, YYYY-MM-DD.
+    #[arg(long)]
+    from: Option<String>,
+    /// Last day to include, YYYY-MM-DD.
+    #[arg(long)]
+    to: Option<String>,
+    /// A reporting period, by its label (e.g. 2024-25) or any date in it.
+    #[arg(long, conflicts_with_all = ["from", "to"])]
+    period: Option<String>,
+    /// Only entries with this approval status.
+    #[arg(long, value_enum)]
+    status: Option<Status>,
+    /// Only entries with this tag.
+    #[arg(long)]
+    tag: Option<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Grouping {
+    Year,
+    Month,
+    Organization,
+    Period,
+    Tag,
+    /// UN Sustainable Development Goal.
+    Sdg,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Status {
+    Draft,
+    Submitted,
+    Approved,
+    Rejected,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    Pdf,
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    List,
+    Add { name: String },
+    Rename { name: String, new_name: String },
+    /// Move a profile and its entries to the trash.
+    Delete { name: String },
+    /// Set or change a profile's PIN (the current one goes in --pin), or remove it.
+    Pin {
+        name: String,
+        #[arg(long, required_unless_present = "remove")]
+        new: Option<String>,
+        #[arg(long, conflicts_with = "new")]
+        remove: bool,
+    },
+    /// Set a profile's birthdate (YYYY-MM-DD), or clear it.
+    Birthdate {
+        name: String,
+        #[arg(required_unless_present = "clear")]
+        date: Option<String>,
+        #[arg(long, conflicts_with = "date")]
+        clear: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BackupCommand {
+    /// Back up now and prune old backups.
+    Create,
+    List,
+    /// Replace the database with a backup, after checking its integrity.
+    Restore { file: String },
+}
+
+#[derive(Subcommand)]
+enum ArchiveCommand {
+    /// Write every profile, organization and entry to an archive.
+    Export {
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Load an archive, merging it into the log or replacing the log with it.
+    Restore {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = Restore::Merge)]
+        mode: Restore,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashCommand {
+    /// Deleted profiles, and the deleted entries of the selected profile.
+    List,
+    RestoreEntry { id: i64 },
+    /// Bring back a deleted profile with its entries.
+    RestoreProfile { id: i64 },
+    /// Delete a trashed entry for good.
+    PurgeEntry { id: i64 },
+    /// Delete a trashed profile and its entries for good.
+    PurgeProfile { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum GoalCommand {
+    /// Every goal with its progress, pace and projected completion.
+    List,
+    /// Set a goal: hours by a date, hours a month, or hours at one organization.
+    Add {
+        #[arg(long)]
+        hours: f64,
+        /// Reach the hours by this date, YYYY-MM-DD.
+        #[arg(long, required_unless_present_any = ["per_month", "organization"])]
+        by: Option<String>,
+        /// Reach the hours every month.
+        #[arg(long, conflicts_with_all = ["by", "organization"])]
+        per_month: bool,
+        /// Count only time at this organization.
+        #[arg(long)]
+        organization: Option<String>,
+        /// First day that counts; defaults to today.
+        #[arg(long)]
+        from: Option<String>,
+    },
+    Delete { id: i64 },
+}
+
+#[derive(Subcommand)]
+enum ProgramCommand {
+    /// Progress in every program the profile is enrolled in.
+    List,
+    /// Enroll in the program defined in a TOML or JSON file, or update it.
+    Enroll { file: PathBuf },
+    /// Leave a program.
+    Drop { name: String },
+}
+
+#[derive(Subcommand)]
+enum PeriodCommand {
+    /// The periods to choose from, newest first.
+    List,
+    /// Show how the profile's hours are split up.
+    Show,
+    /// Report in calendar years.
+    Calendar,
+    /// Report in school years starting on the first of a month.
+    SchoolYear {
+        #[arg(long, default_value_t = 8)]
+        start_month: u32,
+    },
+    /// Report in fiscal years starting on the first of a month.
+    FiscalYear {
+        #[arg(long)]
+        start_month: u32,
+    },
+    /// Report in the terms listed in a JSON file: `[{"name", "from", "to"}, ...]`.
+    Terms { file: PathBuf },
+}
+
+#[derive(Subcommand)]
+enum TagCommand {
+    /// Every tag, with the goal its cause serves.
+    List,
+    /// Tag an entry, creating tags on first use.
+    Add {
+        id: i64,
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Take a tag off an entry.
+    Remove { id: i64, name: String },
+    Rename { name: String, new_name: String },
+    /// Fold the second tag into the first.
+    Merge { kept: String, merged: String },
+    /// Delete a tag, taking it off every entry.
+    Delete { name: String },
+    /// Set the UN Sustainable Development Goal (1 to 17) a tag's cause serves, or clear it.
+    Sdg {
+        name: String,
+        #[arg(required_unless_present = "clear")]
+        goal: Option<u8>,
+        #[arg(long, conflicts_with = "goal")]
+        clear: bool,
+    },
+    /// Add the built-in causes, mapped to the Sustainable Development Goals.
+    Taxonomy,
+}
+
+#[derive(Subcommand)]
+enum AttestCommand {
+    /// Show an organization's public key.
+    Key { organization: String },
+    /// Create the signing key of an organization you sign for.
+    GenerateKey { organization: String },
+    /// Trust the public key a supervisor gave you for their organization.
+    Trust {
+        organization: String,
+        public_key: String,
+    },
+    /// Write an unsigned attestation of your entries at an organization.
+    Request {
+        organization: String,
+        #[command(flatten)]
+        range: RangeArgs,
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Sign an attestation with the key of the organization it names.
+    Sign {
+        file: PathBuf,
+        /// Your name and role, as it should appear on the sign-off.
+        #[arg(long)]
+        signer: String,
+        #[arg(long, short)]
+        output: PathBuf,
+    },
+    /// Check a signed attestation against the trusted key and mark its entries verified.
+    Import { file: PathBuf },
+    /// List signed-off entries and whether each sign-off still holds.
+    Status,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Restore {
+    Merge,
+    Replace,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("vlog: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let path = match cli.db {
+        Some(path) => path,
+        None => db::default_path().ok_or_else(|| {
+            Error::Invalid("cannot find the app's data directory; pass --db".into())
+        })?,
+    };
+    let passphrase = cli.passphrase.as_deref();
+    let mut conn = db::open(&path, passphrase)?;
+    let profile = cli.profile.as_deref();
+    let pin = cli.pin.as_deref();
+
+    match cli.command {
+        Command::Add(fields) => {
+            let profile = select_profile(&conn, profile, pin)?;
+            let input = EntryInput {
+                place: fields
+                    .place
+                    .ok_or_else(|| Error::Invalid("--place is required".into()))?,
+                date: match fields.date {
+                    Some(date) => date,
+                    None => dates::today(&conn)?.to_string(),
+                },
+                start_time: fields.start,
+                end_time: fields.end,
+                duration_minutes: fields.hours.map(minutes),
+                notes: fields.notes.unwrap_or_default(),
+                reason: fields.reason.unwrap_or_default(),
+            };
+            print_json(&entries::create(&conn, profile.id, &input)?)
+        }
+        Command::List(range) => {
+            let filter = range.filter(&conn, select_profile(&conn, profile, pin)?.id)?;
+            print_json(&entries::list_matching(&conn, &filter)?)
+        }
+        Command::Edit { id, fields } => {
+            let entry = entries::get(&conn, id)?;
+            pins::check(&conn, entry.profile_id, pin)?;
+            let retimed = fields.start.is_some() || fields.end.is_some();
+            let input = EntryInput {
+                place: fields.place.unwrap_or(entry.place),
+                date: fields.date.unwrap_or(entry.date),
+                start_time: fields.start.or(entry.start_time),
+                end_time: fields.end.or(entry.end_time),
+                // New times without new hours mean the span is the time worked.
+                duration_minutes: fields
+                    .hours
+                    .map(minutes)
+                    .or((!retimed).then_some(entry.duration_minutes)),
+                notes: fields.notes.unwrap_or(entry.notes),
+                reason: fields.reason.unwrap_or_default(),
+            };
+            print_json(&entries::update(&conn, id, &input)?)
+        }
+        Command::Delete { id, reason } => {
+            pins::check(&conn, entries::get(&conn, id)?.profile_id, pin)?;
+            entries::delete(&conn, id, reason.as_deref().unwrap_or_default())?;
+            print_json(&json!({ "deleted": id }))
+        }
+        Command::Status { id, status, reason } => {
+            pins::check(&conn, entries::get(&conn, id)?.profile_id, pin)?;
+            let reason = reason.as_deref().unwrap_or_default();
+            print_json(&approval::set_status(&conn, id, status.into(), reason)?)
+        }
+        Command::History { id } => {
+            let history = revisions::list(&conn, id)?;
+            pins::check(&conn, history[0].profile_id, pin)?;
+            print_json(&history)
+        }
+        Command::Revert {
+            id,
+            revision,
+            reason,
+        } => {
+            pins::check(&conn, entries::get(&conn, id)?.profile_id, pin)?;
+            let reason = reason.as_deref().unwrap_or_default();
+            print_json(&revisions::revert(&conn, id, revision, reason)?)
+        }
+        Command::Pvsa { end, from, to } => {
+            let id = select_profile(&conn, profile, pin)?.id;
+            let window = match (from, to) {
+                (Some(from), Some(to)) => Window::Fixed { from, to },
+                _ => Window::Rolling {
+                    end: match end {
+                        Some(end) => end,
+                        None => dates::today(&conn)?.to_string(),
+                    },
+                },
+            };
+            print_json(&pvsa::progress(&conn, id, &window)?)
+        }
+        Command::Verify { head } => {
+            let id = select_profile(&conn, profile, pin)?.id;
+            let verification = chain::verify(&conn, id, head.as_deref())?;
+            print_json(&verification)?;
+            if !verification.intact() {
+                return Err(Error::Invalid("the log failed verification".into()));
+            }
+            Ok(())
+        }
+        Command::Total { range, by } => {
+            let filter = range.filter(&conn, select_profile(&conn, profile, pin)?.id)?;
+            match by {
+                Some(by) => print_json(&totals::totals(&conn, &filter, by.into())?),
+                None => {
+                    let page = entries::page(&conn, &filter, 1, 1)?;
+                    print_json(&json!({
+                        "entries": page.total_count,
+                        "minutes": page.total_minutes,
+                        "hours": page.total_minutes as f64 / 60.0,
+                    }))
+                }
+            }
+        }
+        Command::Export {
+            range,
+            format,
+            output,
+        } => {
+            let filter = range.filter(&conn, select_profile(&conn, profile, pin)?.id)?;
+            let (from, to) = (filter.from.as_deref(), filter.to.as_deref());
+            match (format, output) {
+                (Format::Csv, output) => {
+                    let csv = csv_io::export(&conn, &filter)?;
+                    match output {
+                        Some(path) => std::fs::write(path, csv)?,
+                        None => print!("{csv}"),
+                    }
+                    Ok(())
+                }
+                (Format::Pdf, Some(path)) => {
+                    let pdf = report::render_pdf(&conn, filter.profile_id, from, to)?;
+                    std::fs::write(&path, pdf)?;
+                    print_json(&json!({ "written": path }))
+                }
+                (Format::Pdf, None) => Err(Error::Invalid("PDF export needs --output".into())),
+            }
+        }
+        Command::Profile(command) => match command {
+            ProfileCommand::List => print_json(&profiles::list(&conn)?),
+            ProfileCommand::Add { name } => print_json(&profiles::create(&conn, &name)?),
+            ProfileCommand::Rename { name, new_name } => {
+                let id = find_profile(&conn, &name, pin)?.id;
+                print_json(&profiles::rename(&conn, id, &new_name)?)
+            }
+            ProfileCommand::Delete { name } => {
+                let id = find_profile(&conn, &name, pin)?.id;
+                profiles::delete(&mut conn, id)?;
+                print_json(&json!({ "deleted": name }))
+            }
+            ProfileCommand::Pin { name, new, .. } => {
+                let id = find_profile(&conn, &name, pin)?.id;
+                pins::set(&conn, id, pin, new.as_deref())?;
+                print_json(&profiles::get(&conn, id)?)
+            }
+            ProfileCommand::Birthdate { name, date, .. } => {
+                let id = find_profile(&conn, &name, pin)?.id;
+                print_json(&profiles::set_birthdate(&conn, id, date.as_deref())?)
+            }
+        },
+        Command::Backup(command) => {
+            let dir = backup::dir_for(&path);
+            match command {
+                BackupCommand::Create => {
+                    let rotation: Rotation = settings::get(&conn, backup::ROTATION_KEY)?;
+                    let taken = backup::create(&conn, &dir, passphrase, backup::now())?;
+                    backup::rotate(&dir, &rotation)?;
+                    print_json(&taken)
+                }
+                BackupCommand::List => print_json(&backup::list(&dir)?),
+                BackupCommand::Restore { file } => {
+                    backup::restore(&mut conn, &dir, &file, passphrase, backup::now())?;
+                    print_json(&json!({ "restored": file }))
+                }
+            }
+        }
+        Command::Archive(command) => match command {
+            ArchiveCommand::Export { output } => {
+                check_every_pin(&conn, pin)?;
+                std::fs::write(&output, archive::export(&conn)?)?;
+                print_json(&json!({ "written": output }))
+            }
+            ArchiveCommand::Restore { file, mode } => {
+                check_every_pin(&conn, pin)?;
+                let mode = match mode {
+                    Restore::Merge => RestoreMode::Merge,
+                    Restore::Replace => RestoreMode::Replace,
+                };
+                print_json(&archive::restore(&mut conn, &std::fs::read(file)?, mode)?)
+            }
+        },
+        Command::Trash(command) => match command {
+            TrashCommand::List => {
+                let id = select_profile(&conn, profile, pin)?.id;
+                print_json(&trash::list(&conn, id)?)
+            }
+            TrashCommand::RestoreEntry { id } => {
+                pins::check(&conn, trash::entry(&conn, id)?.profile_id, pin)?;
+                print_json(&trash::restore_entry(&conn, id)?)
+            }
+            TrashCommand::RestoreProfile { id } => {
+                pins::check(&conn, id, pin)?;
+                print_json(&trash::restore_profile(&conn, id)?)
+            }
+            TrashCommand::PurgeEntry { id } => {
+                pins::check(&conn, trash::entry(&conn, id)?.profile_id, pin)?;
+                trash::purge_entry(&conn, id, timer::now())?;
+                print_json(&json!({ "purged": id }))
+            }
+            TrashCommand::PurgeProfile { id } => {
+                pins::check(&conn, id, pin)?;
+                trash::purge_profile(&mut conn, id)?;
+                print_json(&json!({ "purged": id }))
+            }
+        },
+        Command::Goal(command) => {
+            let id = select_profile(&conn, profile, pin)?.id;
+            let today = dates::today(&conn)?;
+            match command {
+                GoalCommand::List => {
+                    let forecasts = goals::list(&conn, id)?
+                        .iter()
+                        .map(|goal| goals::forecast(&conn, goal.id, today))
+                        .collect::<Result<Vec<_>>>()?;
+                    print_json(&forecasts)
+                }
+                GoalCommand::Add {
+                    hours,
+                    by,
+                    per_month,
+                    organization,
+                    from,
+                } => {
+                    let organization_id = organization
+                        .map(|name| find_organization(&conn, &name).map(|o| o.id))
+                        .transpose()?;
+                    let kind = match (per_month, organization_id) {
+                        (true, _) => GoalKind::PerMonth,
+                        (false, Some(_)) => GoalKind::PerOrganization,
+                        (false, None) => GoalKind::ByDate,
+                    };
+                    let input = GoalInput {
+                        kind,
+                        minutes: minutes(hours),
+                        organization_id,
+                        start_date: from,
+                        deadline: by,
+                    };
+                    print_json(&goals::create(&conn, id, &input, today)?)
+                }
+                GoalCommand::Delete { id: goal_id } => {
+                    if goals::get(&conn, goal_id)?.profile_id != id {
+                        return Err(Error::NotFound("goal"));
+                    }
+                    goals::delete(&conn, goal_id)?;
+                    print_json(&json!({ "deleted": goal_id }))
+                }
+            }
+        }
+        Command::Program(command) => {
+            let id = select_profile(&conn, profile, pin)?.id;
+            match command {
+                ProgramCommand::List => {
+                    let today = dates::today(&conn)?;
+                    let progress = programs::list(&conn, id)?
+                        .iter()
+                        .map(|enrollment| programs::progress(&conn, enrollment.id, today))
+                        .collect::<Result<Vec<_>>>()?;
+                    print_json(&progress)
+                }
+                ProgramCommand::Enroll { file } => {
+                    print_json(&programs::enroll(&conn, id, &Program::read(&file)?)?)
+                }
+                ProgramCommand::Drop { name } => {
+                    let enrollment = programs::list(&conn, id)?
+                        .into_iter()
+                        .find(|e| e.program.name.eq_ignore_ascii_case(name.trim()))
+                        .ok_or(Error::NotFound("enrollment"))?;
+                    programs::unenroll(&conn, enrollment.id)?;
+                    print_json(&json!({ "dropped": enrollment.program.name }))
+                }
+            }
+        }
+        Command::Period(command) => {
+            let id = select_profile(&conn, profile, pin)?.id;
+            let scheme = match command {
+                PeriodCommand::List => return print_json(&periods::list(&conn, id)?),
+                PeriodCommand::Show => return print_json(&periods::scheme(&conn, id)?),
+                PeriodCommand::Calendar => PeriodScheme::Calendar,
+                PeriodCommand::SchoolYear { start_month } => {
+                    PeriodScheme::SchoolYear { start_month }
+                }
+                PeriodCommand::FiscalYear { start_month } => {
+                    PeriodScheme::FiscalYear { start_month }
+                }
+                PeriodCommand::Terms { file } => {
+                    let terms: Vec<Term> = serde_json::from_slice(&std::fs::read(file)?)?;
+                    PeriodScheme::Terms { terms }
+                }
+            };
+            print_json(&periods::set_scheme(&conn, id, &scheme)?)
+        }
+        Command::Tag(command) => match command {
+            TagCommand::List => print_json(&tags::list(&conn)?),
+            TagCommand::Add { id, names } => {
+                pins::check(&conn, entries::get(&conn, id)?.profile_id, pin)?;
+                for name in &names {
+                    tags::tag(&conn, id, name)?;
+                }
+                print_json(&tags::of_entry(&conn, id)?)
+            }
+            TagCommand::Remove { id, name } => {
+                pins::check(&conn, entries::get(&conn, id)?.profile_id, pin)?;
+                let tag = find_tag(&conn, &name)?;
+                print_json(&tags::untag(&conn, id, tag.id)?)
+            }
+            TagCommand::Rename { name, new_name } => {
+                let tag = find_tag(&conn, &name)?;
+                check_pins(&conn, tags::profiles_using(&conn, &[tag.id])?, pin)?;
+                print_json(&tags::rename(&conn, tag.id, &new_name)?)
+            }
+            TagCommand::Merge { kept, merged } => {
+                let (kept, merged) = (find_tag(&conn, &kept)?, find_tag(&conn, &merged)?);
+                check_pins(&conn, tags::profiles_using(&conn, &[kept.id, merged.id])?, pin)?;
+                print_json(&tags::merge(&mut conn, kept.id, merged.id)?)
+            }
+            TagCommand::Delete { name } => {
+                let tag = find_tag(&conn, &name)?;
+                check_pins(&conn, tags::profiles_using(&conn, &[tag.id])?, pin)?;
+                tags::delete(&conn, tag.id)?;
+                print_json(&json!({ "deleted": name }))
+            }
+            TagCommand::Sdg { name, goal, .. } => {
+                let tag = find_tag(&conn, &name)?;
+                check_pins(&conn, tags::profiles_using(&conn, &[tag.id])?, pin)?;
+                print_json(&tags::set_sdg(&conn, tag.id, goal)?)
+            }
+            TagCommand::Taxonomy => {
+                check_every_pin(&conn, pin)?;
+                print_json(&tags::install_taxonomy(&mut conn)?)
+            }
+        },
+        Command::Timezone { zone, system } => {
+            let zone = match (zone, system) {
+                (Some(zone), _) => dates::set_timezone(&conn, Some(&zone))?,
+                (None, true) => dates::set_timezone(&conn, None)?,
+                (None, false) => dates::timezone(&conn)?,
+            };
+            print_json(&json!({ "timezone": zone, "today": dates::today(&conn)?.to_string() }))
+        }
+        Command::Attest(command) => match command {
+            AttestCommand::Key { organization } => {
+                let id = find_organization(&conn, &organization)?.id;
+                print_json(&attestation::organization_key(&conn, id)?)
+            }
+            AttestCommand::GenerateKey { organization } => {
+                check_every_pin(&conn, pin)?;
+                let id = find_organization(&conn, &organization)?.id;
+                print_json(&attestation::generate_key(&conn, id)?)
+            }
+            AttestCommand::Trust {
+                organization,
+                public_key,
+            } => {
+                check_every_pin(&conn, pin)?;
+                let id = find_organization(&conn, &organization)?.id;
+                print_json(&attestation::trust_key(&conn, id, &public_key)?)
+            }
+            AttestCommand::Request {
+                organization,
+                range,
+                output,
+            } => {
+                let filter = EntryFilter {
+                    organization_id: Some(find_organization(&conn, &organization)?.id),
+                    ..range.filter(&conn, select_profile(&conn, profile, pin)?.id)?
+                };
+                let request = attestation::request(&conn, &filter)?;
+                std::fs::write(&output, serde_json::to_vec_pretty(&request)?)?;
+                print_json(&json!({ "written": output, "entries": request.entries.len() }))
+            }
+            AttestCommand::Sign {
+                file,
+                signer,
+                output,
+            } => {
+                check_every_pin(&conn, pin)?;
+                let request: Attestation = serde_json::from_slice(&std::fs::read(file)?)?;
+                let signed = attestation::sign(&conn, request, &signer)?;
+                std::fs::write(&output, serde_json::to_vec_pretty(&signed)?)?;
+                print_json(&json!({ "written": output, "entries": signed.entries.len() }))
+            }
+            AttestCommand::Import { file } => {
+                let id = select_profile(&conn, profile, pin)?.id;
+                let signed: Attestation = serde_json::from_slice(&std::fs::read(file)?)?;
+                print_json(&attestation::import(&mut conn, id, &signed)?)
+            }
+            AttestCommand::Status => {
+                let id = select_profile(&conn, profile, pin)?.id;
+                print_json(&attestation::sign_offs(&conn, id)?)
+            }
+        },
+    }
+}
+
+impl RangeArgs {
+    fn filter(self, conn: &Connection, profile_id: i64) -> Result<EntryFilter> {
+        let (from, to) = match self.period {
+            Some(period) => {
+                let period = periods::find(conn, profile_id, &period)?;
+                (Some(period.from), Some(period.to))
+            }
+            None => (self.from, self.to),
+        };
+        let tag_id = match self.tag {
+            Some(name) => Some(find_tag(conn, &name)?.id),
+            None => None,
+        };
+        Ok(EntryFilter {
+            from,
+            to,
+            status: self.status.map(Into::into),
+            tag_id,
+            ..EntryFilter::profile(profile_id)
+        })
+    }
+}
+
+impl From<Grouping> for GroupBy {
+    fn from(grouping: Grouping) -> Self {
+        match grouping {
+            Grouping::Year => GroupBy::Year,
+            Grouping::Month => GroupBy::Month,
+            Grouping::Organization => GroupBy::Organization,
+            Grouping::Period => GroupBy::Period,
+            Grouping::Tag => GroupBy::Tag,
+            Grouping::Sdg => GroupBy::Sdg,
+        }
+    }
+}
+
+impl From<Status> for EntryStatus {
+    fn from(status: Status) -> Self {
+        match status {
+            Status::Draft => EntryStatus::Draft,
+            Status::Submitted => EntryStatus::Submitted,
+            Status::Approved => EntryStatus::Approved,
+            Status::Rejected => EntryStatus::Rejected,
+        }
+    }
+}
+
+fn minutes(hours: f64) -> i64 {
+    (hours * 60.0).round() as i64
+}
+
+/// Finds a profile by name, checking `pin` if the profile has one.
+fn find_profile(conn: &Connection, name: &str, pin: Option<&str>) -> Result<Profile> {
+    let profile = profiles::list(conn)?
+        .into_iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or(Error::NotFound("profile"))?;
+    pins::check(conn, profile.id, pin)?;
+    Ok(profile)
+}
+
+fn find_organization(conn: &Connection, name: &str) -> Result<Organization> {
+    organizations::find_by_name(conn, name)?.ok_or(Error::NotFound("organization"))
+}
+
+fn find_tag(conn: &Connection, name: &str) -> Result<Tag> {
+    tags::find_by_name(conn, name)?.ok_or(Error::NotFound("tag"))
+}
+
+fn select_profile(conn: &Connection, name: Option<&str>, pin: Option<&str>) -> Result<Profile> {
+    if let Some(name) = name {
+        return find_profile(conn, name, pin);
+    }
+    let mut all = profiles::list(conn)?;
+    match all.len() {
+        1 => {
+            let profile = all.remove(0);
+            pins::check(conn, profile.id, pin)?;
+            Ok(profile)
+        }
+        0 => Err(Error::Invalid("no profiles yet; create one with `vlog profile add`".into())),
+        _ => Err(Error::Invalid("several profiles exist; choose one with --profile".into())),
+    }
+}
+
+/// For commands that read or wipe every profile at once.
+fn check_every_pin(conn: &Connection, pin: Option<&str>) -> Result<()> {
+    check_pins(conn, profiles::list(conn)?.iter().map(|p| p.id), pin)
+}
+
+fn check_pins(
+    conn: &Connection,
+    profile_ids: impl IntoIterator<Item = i64>,
+    pin: Option<&str>,
+) -> Result<()> {
+    profile_ids
+        .into_iter()
+        .try_for_each(|id| pins::check(conn, id, pin))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
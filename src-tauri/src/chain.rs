@@ -0,0 +1,481 @@
+//! A tamper-evident SHA-256 hash chain over each profile's log. Every entry revision, and
+//! every purge of an entry, appends a record holding a digest of the entry and the hash of
+//! the record before it, so rewriting or removing any earlier record breaks every hash
+//! after it. The head (the latest hash) is printed on reports; a log that later verifies
+//! against that head has not been altered since.
+//!
+//! The digest covers the entry's organization, date, times, duration, notes and approval
+//! status. Renaming or merging an organization records a revision for every entry it
+//! moves, so those moves are sealed like any other change.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::approval::EntryStatus;
+use crate::entries::{self, VolunteerEntry};
+use crate::error::{Error, Result};
+use crate::revisions::{self, EntryRevision};
+
+/// The `prev_hash` of a profile's first record.
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+const PURGE: &str = "purge";
+
+/// Which fields a record's digest covers. Version 1 left out the organization and version
+/// 2 the status; records keep the version they were sealed with, and migrations 22 and 23
+/// sealed every entry again.
+const DIGEST_VERSION: i64 = 3;
+
+fn first_digest_version() -> i64 {
+    1
+}
+
+/// The latest record of a profile's chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChainHead {
+    pub seq: i64,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Verification {
+    pub profile_id: i64,
+    pub records: i64,
+    pub head: Option<ChainHead>,
+    /// What doesn't match, in chain order; empty when the log is intact.
+    pub problems: Vec<String>,
+}
+
+impl Verification {
+    pub fn intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// One link of a chain, as stored and as carried in archives.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Record {
+    profile_id: i64,
+    seq: i64,
+    entry_id: i64,
+    revision_id: Option<i64>,
+    action: String,
+    recorded_at: i64,
+    digest: String,
+    /// Not part of the hash: checking a digest under another version than the one it was
+    /// made with can only make it fail to match.
+    #[serde(default = "first_digest_version")]
+    digest_version: i64,
+    prev_hash: String,
+    hash: String,
+}
+
+/// The fields of an entry that a record seals.
+struct Sealed<'a> {
+    entry_id: i64,
+    profile_id: i64,
+    organization_id: i64,
+    place: &'a str,
+    date: &'a str,
+    start_time: Option<&'a str>,
+    end_time: Option<&'a str>,
+    duration_minutes: i64,
+    notes: &'a str,
+    status: Option<&'a str>,
+    status_reason: &'a str,
+}
+
+impl Sealed<'_> {
+    fn digest(&self, version: i64) -> Result<String> {
+        let fields = (
+            self.entry_id,
+            self.profile_id,
+            self.date,
+            self.start_time,
+            self.end_time,
+            self.duration_minutes,
+            self.notes,
+        );
+        let organization = (self.organization_id, self.place);
+        let bytes = match version {
+            1 => serde_json::to_vec(&fields)?,
+            2 => serde_json::to_vec(&(fields, organization))?,
+            _ => serde_json::to_vec(&(fields, organization, self.status, self.status_reason))?,
+        };
+        Ok(hex::encode(Sha256::digest(bytes)))
+    }
+}
+
+fn revision_digest(revision: &EntryRevision, version: i64) -> Result<String> {
+    Sealed {
+        entry_id: revision.entry_id,
+        profile_id: revision.profile_id,
+        organization_id: revision.organization_id,
+        place: &revision.place,
+        date: &revision.date,
+        start_time: revision.start_time.as_deref(),
+        end_time: revision.end_time.as_deref(),
+        duration_minutes: revision.duration_minutes,
+        notes: &revision.notes,
+        status: revision.status.map(EntryStatus::as_str),
+        status_reason: &revision.status_reason,
+    }
+    .digest(version)
+}
+
+fn entry_digest(entry: &VolunteerEntry, version: i64) -> Result<String> {
+    Sealed {
+        entry_id: entry.id,
+        profile_id: entry.profile_id,
+        organization_id: entry.organization_id,
+        place: &entry.place,
+        date: &entry.date,
+        start_time: entry.start_time.as_deref(),
+        end_time: entry.end_time.as_deref(),
+        duration_minutes: entry.duration_minutes,
+        notes: &entry.notes,
+        status: Some(entry.status.as_str()),
+        status_reason: &entry.status_reason,
+    }
+    .digest(version)
+}
+
+fn link_hash(
+    prev_hash: &str,
+    seq: i64,
+    entry_id: i64,
+    action: &str,
+    recorded_at: i64,
+    digest: &str,
+) -> String {
+    let line = format!("{prev_hash}\n{seq}\n{entry_id}\n{action}\n{recorded_at}\n{digest}");
+    hex::encode(Sha256::digest(line.as_bytes()))
+}
+
+pub fn head(conn: &Connection, profile_id: i64) -> Result<Option<ChainHead>> {
+    Ok(conn
+        .query_row(
+            "SELECT seq, hash FROM entry_chain WHERE profile_id = ?1 ORDER BY seq DESC LIMIT 1",
+            [profile_id],
+            |row| {
+                Ok(ChainHead {
+                    seq: row.get(0)?,
+                    hash: row.get(1)?,
+                })
+            },
+        )
+        .optional()?)
+}
+
+fn append(
+    conn: &Connection,
+    profile_id: i64,
+    entry_id: i64,
+    revision_id: Option<i64>,
+    action: &str,
+    recorded_at: i64,
+    digest: &str,
+) -> Result<()> {
+    let (seq, prev_hash) = match head(conn, profile_id)? {
+        Some(head) => (head.seq + 1, head.hash),
+        None => (1, GENESIS.to_string()),
+    };
+    let hash = link_hash(&prev_hash, seq, entry_id, action, recorded_at, digest);
+    conn.execute(
+        "INSERT INTO entry_chain (profile_id, seq, entry_id, revision_id, action, recorded_at,
+         digest, digest_version, prev_hash, hash)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            profile_id,
+            seq,
+            entry_id,
+            revision_id,
+            action,
+            recorded_at,
+            digest,
+            DIGEST_VERSION,
+            prev_hash,
+            hash
+        ],
+    )?;
+    Ok(())
+}
+
+/// Seals a revision that was just recorded.
+pub(crate) fn seal(conn: &Connection, revision: &EntryRevision) -> Result<()> {
+    append(
+        conn,
+        revision.profile_id,
+        revision.entry_id,
+        Some(revision.id),
+        revision.action.as_str(),
+        revision.recorded_at,
+        &revision_digest(revision, DIGEST_VERSION)?,
+    )
+}
+
+/// Whether a revision is in a chain.
+pub(crate) fn is_sealed(conn: &Connection, revision_id: i64) -> Result<bool> {
+    Ok(conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM entry_chain WHERE revision_id = ?1)",
+        [revision_id],
+        |row| row.get(0),
+    )?)
+}
+
+/// Seals the revisions after `after` that aren't in a chain yet, oldest first. Only
+/// [migrations](crate::migrations) call this, for the revisions they record and, when the
+/// chain is created, those recorded before it. Any other revision outside the chain was
+/// slipped in, and is left for [`verify`] to report.
+pub(crate) fn seal_unsealed(conn: &Connection, after: i64) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id FROM entry_revisions r
+         WHERE id > ?1 AND NOT EXISTS (SELECT 1 FROM entry_chain c WHERE c.revision_id = r.id)
+         ORDER BY id",
+    )?;
+    let ids = stmt
+        .query_map([after], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for id in ids {
+        seal(conn, &revisions::get(conn, id)?)?;
+    }
+    Ok(())
+}
+
+/// Notes that an entry is about to be purged, so its revisions going missing is expected.
+pub(crate) fn seal_purge(conn: &Connection, entry_id: i64, now: i64) -> Result<()> {
+    let profile_id: Option<i64> = conn
+        .query_row(
+            "SELECT profile_id FROM entries WHERE id = ?1",
+            [entry_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match profile_id {
+        Some(profile_id) => append(conn, profile_id, entry_id, None, PURGE, now, ""),
+        None => Ok(()),
+    }
+}
+
+const RECORD_COLUMNS: &str = "profile_id, seq, entry_id, revision_id, action, recorded_at, \
+    digest, digest_version, prev_hash, hash";
+
+impl Record {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Record {
+            profile_id: row.get("profile_id")?,
+            seq: row.get("seq")?,
+            entry_id: row.get("entry_id")?,
+            revision_id: row.get("revision_id")?,
+            action: row.get("action")?,
+            recorded_at: row.get("recorded_at")?,
+            digest: row.get("digest")?,
+            digest_version: row.get("digest_version")?,
+            prev_hash: row.get("prev_hash")?,
+            hash: row.get("hash")?,
+        })
+    }
+}
+
+fn records(conn: &Connection, profile_id: i64) -> Result<Vec<Record>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {RECORD_COLUMNS} FROM entry_chain WHERE profile_id = ?1 ORDER BY seq"
+    ))?;
+    let records = stmt
+        .query_map([profile_id], Record::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(records)
+}
+
+/// Every profile's chain, for archives.
+pub(crate) fn all(conn: &Connection) -> Result<Vec<Record>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {RECORD_COLUMNS} FROM entry_chain ORDER BY profile_id, seq"
+    ))?;
+    let records = stmt
+        .query_map([], Record::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(records)
+}
+
+/// Puts archived chains back as they were, so heads printed before the archive was
+/// written still verify. Entries and revisions that were purged keep their ids reserved,
+/// so nothing logged later can take the id a record still names.
+pub(crate) fn restore(conn: &Connection, records: &[Record]) -> Result<()> {
+    for record in records {
+        conn.execute(
+            &format!(
+                "INSERT INTO entry_chain ({RECORD_COLUMNS})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
+            ),
+            params![
+                record.profile_id,
+                record.seq,
+                record.entry_id,
+                record.revision_id,
+                record.action,
+                record.recorded_at,
+                record.digest,
+                record.digest_version,
+                record.prev_hash,
+                record.hash
+            ],
+        )?;
+    }
+    for (table, column) in [("entries", "entry_id"), ("entry_revisions", "revision_id")] {
+        conn.execute(
+            "INSERT INTO sqlite_sequence (name, seq) SELECT ?1, 0
+             WHERE NOT EXISTS (SELECT 1 FROM sqlite_sequence WHERE name = ?1)",
+            [table],
+        )?;
+        conn.execute(
+            &format!(
+                "UPDATE sqlite_sequence
+                 SET seq = max(seq, (SELECT COALESCE(max({column}), 0) FROM entry_chain))
+                 WHERE name = ?1"
+            ),
+            [table],
+        )?;
+    }
+    Ok(())
+}
+
+/// Recomputes a profile's chain and checks it against the revisions and entries it seals.
+/// Reports every record that was changed, removed or slipped in outside the chain, and
+/// every entry whose current values no longer match their last sealed revision. Records
+/// cut from the end of the chain can only be caught against a head printed earlier, so
+/// `printed_head` (from a report) must be found in the chain if given.
+pub fn verify(
+    conn: &Connection,
+    profile_id: i64,
+    printed_head: Option<&str>,
+) -> Result<Verification> {
+    let records = records(conn, profile_id)?;
+    let mut problems = Vec::new();
+
+    let mut next_seq = 1;
+    let mut prev_hash = GENESIS.to_string();
+    for (i, record) in records.iter().enumerate() {
+        if record.seq != next_seq {
+            problems.push(format!("record #{next_seq} is missing"));
+        }
+        next_seq = record.seq + 1;
+        if record.prev_hash != prev_hash {
+            problems.push(format!(
+                "record #{} does not follow the one before it",
+                record.seq
+            ));
+        }
+        let hash = link_hash(
+            &record.prev_hash,
+            record.seq,
+            record.entry_id,
+            &record.action,
+            record.recorded_at,
+            &record.digest,
+        );
+        if hash != record.hash {
+            problems.push(format!("record #{} was altered", record.seq));
+        }
+        prev_hash = record.hash.clone();
+
+        let Some(revision_id) = record.revision_id else {
+            continue;
+        };
+        let purged = records[i..]
+            .iter()
+            .any(|later| later.entry_id == record.entry_id && later.action == PURGE);
+        let revision = match revisions::get(conn, revision_id) {
+            Ok(revision) => Some(revision),
+            Err(Error::NotFound(_)) => None,
+            Err(e) => return Err(e),
+        };
+        match revision {
+            Some(revision) if revision.entry_id != record.entry_id => problems.push(format!(
+                "revision {revision_id} no longer belongs to entry {}",
+                record.entry_id
+            )),
+            Some(revision)
+                if revision_digest(&revision, record.digest_version)? != record.digest =>
+            {
+                problems.push(format!(
+                    "revision {revision_id} of entry {} was altered",
+                    record.entry_id
+                ))
+            }
+            Some(_) => {}
+            None if purged => {}
+            None => problems.push(format!(
+                "revision {revision_id} of entry {} was removed",
+                record.entry_id
+            )),
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.entry_id FROM entry_revisions r
+         WHERE r.profile_id = ?1
+         AND NOT EXISTS (SELECT 1 FROM entry_chain c WHERE c.revision_id = r.id)
+         ORDER BY r.id",
+    )?;
+    let unsealed = stmt
+        .query_map([profile_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for (revision_id, entry_id) in unsealed {
+        problems.push(format!(
+            "revision {revision_id} of entry {entry_id} was added outside the chain"
+        ));
+    }
+
+    // Entries in the trash are sealed too.
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM entries e WHERE e.profile_id = ?1 ORDER BY e.id",
+        entries::columns_of("e")
+    ))?;
+    let entries = stmt
+        .query_map([profile_id], VolunteerEntry::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for entry in entries {
+        let entry_id = entry.id;
+        let deleted = entry.deleted_at.is_some();
+        let mut sealed = records.iter().rev().filter(|r| r.entry_id == entry_id);
+        // Entries in the trash can still be moved to another organization.
+        let trashed = sealed.clone().find_map(|r| match r.action.as_str() {
+            "delete" => Some(true),
+            "create" | "restore" => Some(false),
+            _ => None,
+        });
+        match sealed.next() {
+            None => problems.push(format!("entry {entry_id} was added outside the chain")),
+            Some(last) if last.action == PURGE => {
+                problems.push(format!("entry {entry_id} was purged but is still there"))
+            }
+            Some(last) if last.digest != entry_digest(&entry, last.digest_version)? => {
+                problems.push(format!("entry {entry_id} was changed outside the app"))
+            }
+            Some(_) if deleted != trashed.unwrap_or(false) => problems.push(format!(
+                "entry {entry_id} was moved in or out of the trash outside the app"
+            )),
+            Some(_) => {}
+        }
+    }
+
+    if let Some(printed) = printed_head.map(str::trim) {
+        if !records.iter().any(|r| r.hash.eq_ignore_ascii_case(printed)) {
+            problems.push(format!("head {printed} is not in the chain"));
+        }
+    }
+
+    Ok(Verification {
+        profile_id,
+        records: records.len() as i64,
+        head: records.last().map(|r| ChainHead {
+            seq: r.seq,
+            hash: r.hash.clone(),
+        }),
+        problems,
+    })
+}
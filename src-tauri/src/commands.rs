@@ -0,0 +1,1085 @@
+use std::collections::BTreeMap;
+
+use tauri::State;
+
+use crate::approval::{self, EntryStatus};
+use crate::archive::{self, Manifest, RestoreMode, RestoreSummary};
+use crate::attestation::{self, Attestation, EntrySignOff, ImportSummary, OrganizationKey};
+use crate::backup::{self, Backup, Rotation};
+use crate::chain::{self, ChainHead, Verification};
+use crate::csv_io::{self, ColumnMapping, ImportReport};
+use crate::dates;
+use crate::db::{Db, DbStatus};
+use crate::entries::{self, EntryFilter, EntryInput, EntryPage, VolunteerEntry, DATE_FORMAT};
+use crate::error::Result;
+use crate::goals::{self, Forecast, Goal, GoalInput};
+use crate::history::{Change, History, HistoryStatus};
+use crate::organizations::{self, Organization, OrganizationInput};
+use crate::periods::{self, Period, PeriodScheme};
+use crate::pins::{self, Sessions};
+use crate::profiles::{self, Profile};
+use crate::programs::{self, Enrollment, Program, ProgramProgress};
+use crate::pvsa::{self, Progress, Window};
+use crate::report;
+use crate::revisions::{self, EntryRevision};
+use crate::search::{self, SearchHit, SearchQuery};
+use crate::settings;
+use crate::tags::{self, Tag};
+use crate::timer::{self, TimerSession};
+use crate::totals::{self, GroupBy, Total};
+use crate::trash::{self, Trash, TrashSettings};
+
+#[tauri::command]
+pub fn database_status(db: State<'_, Db>) -> DbStatus {
+    db.status()
+}
+
+/// Today's date, `YYYY-MM-DD`, in the chosen timezone.
+#[tauri::command]
+pub fn today(db: State<'_, Db>) -> Result<String> {
+    db.with(|conn| Ok(dates::today(conn)?.format(DATE_FORMAT).to_string()))
+}
+
+#[tauri::command]
+pub fn timezone(db: State<'_, Db>) -> Result<Option<String>> {
+    db.with(|conn| dates::timezone(conn))
+}
+
+/// Leave `zone` out to follow the system's timezone.
+#[tauri::command]
+pub fn set_timezone(db: State<'_, Db>, zone: Option<String>) -> Result<Option<String>> {
+    db.with(|conn| dates::set_timezone(conn, zone.as_deref()))
+}
+
+#[tauri::command]
+pub fn unlock_database(db: State<'_, Db>, passphrase: String) -> Result<DbStatus> {
+    db.unlock(&passphrase)
+}
+
+#[tauri::command]
+pub fn lock_database(db: State<'_, Db>) -> DbStatus {
+    db.lock()
+}
+
+/// Leave `new` out to remove encryption.
+#[tauri::command]
+pub fn set_database_passphrase(
+    db: State<'_, Db>,
+    current: Option<String>,
+    new: Option<String>,
+) -> Result<DbStatus> {
+    db.set_passphrase(current.as_deref(), new.as_deref())
+}
+
+#[tauri::command]
+pub fn list_backups(db: State<'_, Db>) -> Result<Vec<Backup>> {
+    backup::list(&db.backup_dir())
+}
+
+#[tauri::command]
+pub fn create_backup(db: State<'_, Db>) -> Result<Backup> {
+    db.backup()
+}
+
+#[tauri::command]
+pub fn restore_backup(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    file: String,
+) -> Result<()> {
+    db.restore(&file)?;
+    // Profile ids in the restored copy need not match the ones that were unlocked.
+    sessions.lock_all();
+    history.clear();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn backup_rotation(db: State<'_, Db>) -> Result<Rotation> {
+    db.with(|conn| settings::get(conn, backup::ROTATION_KEY))
+}
+
+#[tauri::command]
+pub fn set_backup_rotation(db: State<'_, Db>, rotation: Rotation) -> Result<Rotation> {
+    db.with(|conn| settings::set(conn, backup::ROTATION_KEY, &rotation))?;
+    backup::rotate(&db.backup_dir(), &rotation)?;
+    Ok(rotation)
+}
+
+/// Archives hold every profile, so all of them must be unlocked.
+#[tauri::command]
+pub fn export_archive(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    path: String,
+) -> Result<()> {
+    let data = db.with(|conn| {
+        sessions.require_all(conn)?;
+        archive::export(conn)
+    })?;
+    std::fs::write(path, data)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn read_archive_manifest(path: String) -> Result<Manifest> {
+    archive::manifest(&std::fs::read(path)?)
+}
+
+#[tauri::command]
+pub fn restore_archive(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    path: String,
+    mode: RestoreMode,
+) -> Result<RestoreSummary> {
+    let data = std::fs::read(path)?;
+    // A merge adds entries to existing profiles, matched by name, so it needs them
+    // unlocked just as a replace does.
+    let summary = db.with(|conn| {
+        sessions.require_all(conn)?;
+        archive::restore(conn, &data, mode)
+    })?;
+    sessions.lock_all();
+    history.clear();
+    Ok(summary)
+}
+
+#[tauri::command]
+pub fn list_profiles(db: State<'_, Db>) -> Result<Vec<Profile>> {
+    db.with(|conn| profiles::list(conn))
+}
+
+#[tauri::command]
+pub fn create_profile(db: State<'_, Db>, name: String) -> Result<Profile> {
+    db.with(|conn| profiles::create(conn, &name))
+}
+
+#[tauri::command]
+pub fn rename_profile(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+    name: String,
+) -> Result<Profile> {
+    db.with(|conn| {
+        sessions.require(conn, id)?;
+        profiles::rename(conn, id, &name)
+    })
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    id: i64,
+) -> Result<()> {
+    let profile = db.with(|conn| {
+        sessions.require(conn, id)?;
+        let profile = profiles::get(conn, id)?;
+        profiles::delete(conn, id)?;
+        Ok(profile)
+    })?;
+    history.record(Change::DeleteProfile { profile });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn profile_locked(db: State<'_, Db>, sessions: State<'_, Sessions>, id: i64) -> Result<bool> {
+    db.with(|conn| sessions.is_locked(conn, id))
+}
+
+#[tauri::command]
+pub fn unlock_profile(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+    pin: String,
+) -> Result<()> {
+    db.with(|conn| sessions.unlock(conn, id, &pin))
+}
+
+#[tauri::command]
+pub fn lock_profile(sessions: State<'_, Sessions>, id: i64) {
+    sessions.lock(id)
+}
+
+/// Leave `new` out to remove the PIN.
+#[tauri::command]
+pub fn set_profile_pin(
+    db: State<'_, Db>,
+    id: i64,
+    current: Option<String>,
+    new: Option<String>,
+) -> Result<Profile> {
+    db.with(|conn| {
+        pins::set(conn, id, current.as_deref(), new.as_deref())?;
+        profiles::get(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn set_profile_birthdate(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+    birthdate: Option<String>,
+) -> Result<Profile> {
+    db.with(|conn| {
+        sessions.require(conn, id)?;
+        profiles::set_birthdate(conn, id, birthdate.as_deref())
+    })
+}
+
+/// Hours towards the Presidential Volunteer Service Award in `window`.
+#[tauri::command]
+pub fn pvsa_progress(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    window: Window,
+) -> Result<Progress> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        pvsa::progress(conn, profile_id, &window)
+    })
+}
+
+/// Progress in every program the profile is enrolled in.
+#[tauri::command]
+pub fn program_progress(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Vec<ProgramProgress>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        let today = dates::today(conn)?;
+        programs::list(conn, profile_id)?
+            .iter()
+            .map(|enrollment| programs::progress(conn, enrollment.id, today))
+            .collect()
+    })
+}
+
+/// Enrolls the profile in the program defined in the TOML or JSON file at `path`.
+#[tauri::command]
+pub fn enroll_program(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    path: String,
+) -> Result<Enrollment> {
+    let program = Program::read(path.as_ref())?;
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        programs::enroll(conn, profile_id, &program)
+    })
+}
+
+/// Every goal of the profile with how it is coming along.
+#[tauri::command]
+pub fn goal_forecasts(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Vec<Forecast>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        let today = dates::today(conn)?;
+        goals::list(conn, profile_id)?
+            .iter()
+            .map(|goal| goals::forecast(conn, goal.id, today))
+            .collect()
+    })
+}
+
+#[tauri::command]
+pub fn create_goal(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    goal: GoalInput,
+) -> Result<Goal> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        goals::create(conn, profile_id, &goal, dates::today(conn)?)
+    })
+}
+
+#[tauri::command]
+pub fn delete_goal(db: State<'_, Db>, sessions: State<'_, Sessions>, id: i64) -> Result<()> {
+    db.with(|conn| {
+        sessions.require(conn, goals::get(conn, id)?.profile_id)?;
+        goals::delete(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn unenroll_program(db: State<'_, Db>, sessions: State<'_, Sessions>, id: i64) -> Result<()> {
+    db.with(|conn| {
+        sessions.require(conn, programs::get(conn, id)?.profile_id)?;
+        programs::unenroll(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn list_entries(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Vec<VolunteerEntry>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        entries::list(conn, profile_id)
+    })
+}
+
+#[tauri::command]
+pub fn list_entries_page(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    filter: EntryFilter,
+    page: u32,
+    per_page: u32,
+) -> Result<EntryPage> {
+    db.with(|conn| {
+        sessions.require(conn, filter.profile_id)?;
+        entries::page(conn, &filter, page, per_page)
+    })
+}
+
+#[tauri::command]
+pub fn entry_years(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Vec<i32>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        totals::years(conn, profile_id)
+    })
+}
+
+#[tauri::command]
+pub fn reporting_periods(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<PeriodScheme> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        periods::scheme(conn, profile_id)
+    })
+}
+
+#[tauri::command]
+pub fn set_reporting_periods(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    scheme: PeriodScheme,
+) -> Result<PeriodScheme> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        periods::set_scheme(conn, profile_id, &scheme)
+    })
+}
+
+/// The reporting periods the year filter offers, newest first.
+#[tauri::command]
+pub fn entry_periods(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Vec<Period>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        periods::list(conn, profile_id)
+    })
+}
+
+#[tauri::command]
+pub fn entry_totals(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    filter: EntryFilter,
+    group_by: GroupBy,
+) -> Result<Vec<Total>> {
+    db.with(|conn| {
+        sessions.require(conn, filter.profile_id)?;
+        totals::totals(conn, &filter, group_by)
+    })
+}
+
+#[tauri::command]
+pub fn create_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    profile_id: i64,
+    entry: EntryInput,
+) -> Result<VolunteerEntry> {
+    let created = db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        entries::create(conn, profile_id, &entry)
+    })?;
+    history.record(Change::CreateEntry {
+        entry: created.clone(),
+    });
+    Ok(created)
+}
+
+#[tauri::command]
+pub fn update_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    id: i64,
+    entry: EntryInput,
+) -> Result<VolunteerEntry> {
+    let (before, after) = db.with(|conn| {
+        let before = entries::get(conn, id)?;
+        sessions.require(conn, before.profile_id)?;
+        let after = entries::update(conn, id, &entry)?;
+        Ok((before, after))
+    })?;
+    history.record(Change::UpdateEntry {
+        before,
+        after: after.clone(),
+    });
+    Ok(after)
+}
+
+#[tauri::command]
+pub fn delete_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    id: i64,
+    reason: Option<String>,
+) -> Result<()> {
+    let entry = db.with(|conn| {
+        let entry = entries::get(conn, id)?;
+        sessions.require(conn, entry.profile_id)?;
+        entries::delete(conn, id, reason.as_deref().unwrap_or_default())?;
+        Ok(entry)
+    })?;
+    history.record(Change::DeleteEntry { entry });
+    Ok(())
+}
+
+/// Moves an entry through the approval workflow; rejecting needs a `reason`.
+#[tauri::command]
+pub fn set_entry_status(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+    status: EntryStatus,
+    reason: Option<String>,
+) -> Result<VolunteerEntry> {
+    db.with(|conn| {
+        sessions.require(conn, entries::get(conn, id)?.profile_id)?;
+        approval::set_status(conn, id, status, reason.as_deref().unwrap_or_default())
+    })
+}
+
+/// Every revision of an entry, oldest first.
+#[tauri::command]
+pub fn entry_revisions(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    entry_id: i64,
+) -> Result<Vec<EntryRevision>> {
+    db.with(|conn| {
+        let revisions = revisions::list(conn, entry_id)?;
+        sessions.require(conn, revisions[0].profile_id)?;
+        Ok(revisions)
+    })
+}
+
+#[tauri::command]
+pub fn revert_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    entry_id: i64,
+    revision_id: i64,
+    reason: Option<String>,
+) -> Result<VolunteerEntry> {
+    let (before, after) = db.with(|conn| {
+        let before = entries::get(conn, entry_id)?;
+        sessions.require(conn, before.profile_id)?;
+        let reason = reason.as_deref().unwrap_or_default();
+        let after = revisions::revert(conn, entry_id, revision_id, reason)?;
+        Ok((before, after))
+    })?;
+    history.record(Change::UpdateEntry {
+        before,
+        after: after.clone(),
+    });
+    Ok(after)
+}
+
+#[tauri::command]
+pub fn chain_head(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Option<ChainHead>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        chain::head(conn, profile_id)
+    })
+}
+
+/// Checks the profile's hash chain, and that `head` (copied from a report) is in it.
+#[tauri::command]
+pub fn verify_chain(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    head: Option<String>,
+) -> Result<Verification> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        chain::verify(conn, profile_id, head.as_deref())
+    })
+}
+
+#[tauri::command]
+pub fn organization_key(
+    db: State<'_, Db>,
+    organization_id: i64,
+) -> Result<Option<OrganizationKey>> {
+    db.with(|conn| attestation::organization_key(conn, organization_id))
+}
+
+/// Organization keys decide which sign-offs count for every profile, so changing them
+/// needs every profile unlocked.
+#[tauri::command]
+pub fn generate_organization_key(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    organization_id: i64,
+) -> Result<OrganizationKey> {
+    db.with(|conn| {
+        sessions.require_all(conn)?;
+        attestation::generate_key(conn, organization_id)
+    })
+}
+
+#[tauri::command]
+pub fn trust_organization_key(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    organization_id: i64,
+    public_key: String,
+) -> Result<OrganizationKey> {
+    db.with(|conn| {
+        sessions.require_all(conn)?;
+        attestation::trust_key(conn, organization_id, &public_key)
+    })
+}
+
+/// Writes an unsigned attestation of the filtered entries for a supervisor to sign.
+#[tauri::command]
+pub fn request_attestation(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    filter: EntryFilter,
+    path: String,
+) -> Result<Attestation> {
+    let request = db.with(|conn| {
+        sessions.require(conn, filter.profile_id)?;
+        attestation::request(conn, &filter)
+    })?;
+    std::fs::write(path, serde_json::to_vec_pretty(&request)?)?;
+    Ok(request)
+}
+
+#[tauri::command]
+pub fn read_attestation(path: String) -> Result<Attestation> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+/// Signs the attestation at `path` and writes the signed copy to `signed_path`.
+#[tauri::command]
+pub fn sign_attestation(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    path: String,
+    signer: String,
+    signed_path: String,
+) -> Result<Attestation> {
+    let request: Attestation = serde_json::from_slice(&std::fs::read(path)?)?;
+    let signed = db.with(|conn| {
+        sessions.require_all(conn)?;
+        attestation::sign(conn, request, &signer)
+    })?;
+    std::fs::write(signed_path, serde_json::to_vec_pretty(&signed)?)?;
+    Ok(signed)
+}
+
+#[tauri::command]
+pub fn import_attestation(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    path: String,
+) -> Result<ImportSummary> {
+    let signed: Attestation = serde_json::from_slice(&std::fs::read(path)?)?;
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        attestation::import(conn, profile_id, &signed)
+    })
+}
+
+#[tauri::command]
+pub fn entry_sign_offs(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Vec<EntrySignOff>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        attestation::sign_offs(conn, profile_id)
+    })
+}
+
+/// Deleted profiles, and the deleted entries of `profile_id`.
+#[tauri::command]
+pub fn list_trash(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Trash> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        trash::list(conn, profile_id)
+    })
+}
+
+#[tauri::command]
+pub fn restore_trashed_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+) -> Result<VolunteerEntry> {
+    db.with(|conn| {
+        sessions.require(conn, trash::entry(conn, id)?.profile_id)?;
+        trash::restore_entry(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn restore_trashed_profile(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+) -> Result<Profile> {
+    db.with(|conn| {
+        sessions.require(conn, id)?;
+        trash::restore_profile(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn purge_trashed_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+) -> Result<()> {
+    db.with(|conn| {
+        sessions.require(conn, trash::entry(conn, id)?.profile_id)?;
+        trash::purge_entry(conn, id, timer::now())
+    })
+}
+
+#[tauri::command]
+pub fn purge_trashed_profile(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+) -> Result<()> {
+    db.with(|conn| {
+        sessions.require(conn, id)?;
+        trash::purge_profile(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn trash_settings(db: State<'_, Db>) -> Result<TrashSettings> {
+    db.with(|conn| settings::get(conn, trash::SETTINGS_KEY))
+}
+
+#[tauri::command]
+pub fn set_trash_settings(db: State<'_, Db>, settings: TrashSettings) -> Result<TrashSettings> {
+    db.with(|conn| {
+        settings::set(conn, trash::SETTINGS_KEY, &settings)?;
+        trash::purge_expired(conn, timer::now())?;
+        Ok(settings)
+    })
+}
+
+#[tauri::command]
+pub fn history_status(history: State<'_, History>) -> HistoryStatus {
+    history.status()
+}
+
+/// Returns the change that was undone, or nothing when there was none.
+#[tauri::command]
+pub fn undo(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+) -> Result<Option<Change>> {
+    db.with(|conn| {
+        history.undo(conn, |conn, change| {
+            sessions.require_each(conn, change.profile_ids(conn)?)
+        })
+    })
+}
+
+#[tauri::command]
+pub fn redo(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+) -> Result<Option<Change>> {
+    db.with(|conn| {
+        history.redo(conn, |conn, change| {
+            sessions.require_each(conn, change.profile_ids(conn)?)
+        })
+    })
+}
+
+#[tauri::command]
+pub fn list_organizations(db: State<'_, Db>) -> Result<Vec<Organization>> {
+    db.with(|conn| organizations::list(conn))
+}
+
+#[tauri::command]
+pub fn create_organization(
+    db: State<'_, Db>,
+    organization: OrganizationInput,
+) -> Result<Organization> {
+    db.with(|conn| organizations::create(conn, &organization))
+}
+
+/// Renaming or merging an organization moves the entries logged there, so it needs a
+/// session for every profile with hours at either organization.
+#[tauri::command]
+pub fn update_organization(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    id: i64,
+    organization: OrganizationInput,
+) -> Result<Organization> {
+    let (before, after) = db.with(|conn| {
+        sessions.require_each(conn, organizations::profiles_using(conn, &[id])?)?;
+        let before = organizations::get(conn, id)?;
+        let after = organizations::update(conn, id, &organization)?;
+        Ok((before, after))
+    })?;
+    history.record(Change::UpdateOrganization {
+        before,
+        after: after.clone(),
+    });
+    Ok(after)
+}
+
+/// Deleting an organization purges its entries in the trash, whoever's they are.
+#[tauri::command]
+pub fn delete_organization(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+) -> Result<()> {
+    db.with(|conn| {
+        sessions.require_all(conn)?;
+        organizations::delete(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn merge_organizations(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    kept_id: i64,
+    merged_id: i64,
+) -> Result<Organization> {
+    let merge = db.with(|conn| {
+        let used = organizations::profiles_using(conn, &[kept_id, merged_id])?;
+        sessions.require_each(conn, used)?;
+        organizations::merge(conn, kept_id, merged_id)
+    })?;
+    let organization = merge.organization.clone();
+    history.record(Change::MergeOrganizations { merge });
+    Ok(organization)
+}
+
+#[tauri::command]
+pub fn list_tags(db: State<'_, Db>) -> Result<Vec<Tag>> {
+    db.with(|conn| tags::list(conn))
+}
+
+/// The tags of the entries matching `filter`, by entry id.
+#[tauri::command]
+pub fn entry_tags(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    filter: EntryFilter,
+) -> Result<BTreeMap<i64, Vec<Tag>>> {
+    db.with(|conn| {
+        sessions.require(conn, filter.profile_id)?;
+        tags::by_entry(conn, &filter)
+    })
+}
+
+#[tauri::command]
+pub fn tag_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    entry_id: i64,
+    name: String,
+) -> Result<Vec<Tag>> {
+    db.with(|conn| {
+        sessions.require(conn, entries::get(conn, entry_id)?.profile_id)?;
+        tags::tag(conn, entry_id, &name)
+    })
+}
+
+#[tauri::command]
+pub fn untag_entry(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    entry_id: i64,
+    tag_id: i64,
+) -> Result<Vec<Tag>> {
+    db.with(|conn| {
+        sessions.require(conn, entries::get(conn, entry_id)?.profile_id)?;
+        tags::untag(conn, entry_id, tag_id)
+    })
+}
+
+/// Renaming, merging or deleting a tag, or setting its goal, needs a session for every
+/// profile whose entries carry it.
+#[tauri::command]
+pub fn rename_tag(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+    name: String,
+) -> Result<Tag> {
+    db.with(|conn| {
+        sessions.require_each(conn, tags::profiles_using(conn, &[id])?)?;
+        tags::rename(conn, id, &name)
+    })
+}
+
+#[tauri::command]
+pub fn set_tag_sdg(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    id: i64,
+    sdg: Option<u8>,
+) -> Result<Tag> {
+    db.with(|conn| {
+        sessions.require_each(conn, tags::profiles_using(conn, &[id])?)?;
+        tags::set_sdg(conn, id, sdg)
+    })
+}
+
+#[tauri::command]
+pub fn delete_tag(db: State<'_, Db>, sessions: State<'_, Sessions>, id: i64) -> Result<()> {
+    db.with(|conn| {
+        sessions.require_each(conn, tags::profiles_using(conn, &[id])?)?;
+        tags::delete(conn, id)
+    })
+}
+
+#[tauri::command]
+pub fn merge_tags(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    kept_id: i64,
+    merged_id: i64,
+) -> Result<Tag> {
+    db.with(|conn| {
+        sessions.require_each(conn, tags::profiles_using(conn, &[kept_id, merged_id])?)?;
+        tags::merge(conn, kept_id, merged_id)
+    })
+}
+
+/// Adds the built-in causes, mapped to the UN Sustainable Development Goals. Goals it
+/// gives to tags already in use can move anyone's hours, so every profile must be unlocked.
+#[tauri::command]
+pub fn install_tag_taxonomy(db: State<'_, Db>, sessions: State<'_, Sessions>) -> Result<Vec<Tag>> {
+    db.with(|conn| {
+        sessions.require_all(conn)?;
+        tags::install_taxonomy(conn)
+    })
+}
+
+#[tauri::command]
+pub fn export_csv(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    filter: EntryFilter,
+    path: String,
+) -> Result<()> {
+    let csv = db.with(|conn| {
+        sessions.require(conn, filter.profile_id)?;
+        csv_io::export(conn, &filter)
+    })?;
+    std::fs::write(path, csv)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn import_csv(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    path: String,
+    mapping: Option<ColumnMapping>,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    let data = std::fs::read_to_string(path)?;
+    let mapping = mapping.unwrap_or_default();
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        csv_io::import(conn, profile_id, &data, &mapping, dry_run)
+    })
+}
+
+#[tauri::command]
+pub fn export_report_pdf(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    from: Option<String>,
+    to: Option<String>,
+    path: String,
+) -> Result<()> {
+    let pdf = db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        report::render_pdf(conn, profile_id, from.as_deref(), to.as_deref())
+    })?;
+    std::fs::write(path, pdf)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn search_entries(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    query: SearchQuery,
+) -> Result<Vec<SearchHit>> {
+    db.with(|conn| {
+        match query.profile_id {
+            Some(profile_id) => sessions.require(conn, profile_id)?,
+            None => sessions.require_all(conn)?,
+        }
+        search::search(conn, &query)
+    })
+}
+
+#[tauri::command]
+pub fn timer_status(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<Option<TimerSession>> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        timer::active(conn, profile_id)
+    })
+}
+
+#[tauri::command]
+pub fn start_timer(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+    place: Option<String>,
+    notes: Option<String>,
+) -> Result<TimerSession> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        timer::start(
+            conn,
+            profile_id,
+            place.as_deref().unwrap_or_default(),
+            notes.as_deref().unwrap_or_default(),
+            timer::now(),
+        )
+    })
+}
+
+#[tauri::command]
+pub fn pause_timer(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<TimerSession> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        timer::pause(conn, profile_id, timer::now())
+    })
+}
+
+#[tauri::command]
+pub fn resume_timer(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<TimerSession> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        timer::resume(conn, profile_id, timer::now())
+    })
+}
+
+#[tauri::command]
+pub fn stop_timer(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    history: State<'_, History>,
+    profile_id: i64,
+    place: Option<String>,
+    notes: Option<String>,
+) -> Result<VolunteerEntry> {
+    let entry = db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        timer::stop(
+            conn,
+            profile_id,
+            place.as_deref(),
+            notes.as_deref(),
+            timer::now(),
+        )
+    })?;
+    history.record(Change::CreateEntry {
+        entry: entry.clone(),
+    });
+    Ok(entry)
+}
+
+#[tauri::command]
+pub fn cancel_timer(
+    db: State<'_, Db>,
+    sessions: State<'_, Sessions>,
+    profile_id: i64,
+) -> Result<()> {
+    db.with(|conn| {
+        sessions.require(conn, profile_id)?;
+        timer::cancel(conn, profile_id)
+    })
+}
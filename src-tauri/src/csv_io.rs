@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::dates;
+use crate::entries::{self, EntryFilter, EntryInput};
+use crate::error::{Error, Result};
+use crate::organizations;
+
+pub const HEADERS: [&str; 6] = ["place", "date", "start_time", "end_time", "hours", "notes"];
+
+/// Writes the matching entries, oldest first, as RFC 4180 CSV.
+pub fn export(conn: &Connection, filter: &EntryFilter) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(Vec::new());
+    writer.write_record(HEADERS)?;
+    for entry in entries::list_matching(conn, filter)?.iter().rev() {
+        writer.write_record([
+            entry.place.as_str(),
+            &entry.date,
+            entry.start_time.as_deref().unwrap_or_default(),
+            entry.end_time.as_deref().unwrap_or_default(),
+            &format!("{:.2}", entry.hours()),
+            &entry.notes,
+        ])?;
+    }
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Which CSV header feeds each entry field. Headers match case-insensitively; optional
+/// columns missing from the file are skipped. The hours cell may be left blank on rows
+/// that give a start and end time.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub place: String,
+    pub date: String,
+    pub hours: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            place: "place".into(),
+            date: "date".into(),
+            hours: "hours".into(),
+            start_time: Some("start_time".into()),
+            end_time: Some("end_time".into()),
+            notes: Some("notes".into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowStatus {
+    New,
+    Duplicate,
+    Invalid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportRow {
+    pub line: u64,
+    pub status: RowStatus,
+    pub entry: Option<EntryInput>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: usize,
+    pub rows: Vec<ImportRow>,
+}
+
+/// Parses `data` and, unless `dry_run` is set, inserts every row that is valid and not
+/// already logged. Rows repeating an existing entry or an earlier row are duplicates.
+pub fn import(
+    conn: &mut Connection,
+    profile_id: i64,
+    data: &str,
+    mapping: &ColumnMapping,
+    dry_run: bool,
+) -> Result<ImportReport> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(data.as_bytes());
+    let headers = reader.headers()?.clone();
+    let find = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+    };
+    let column = |name: &str| -> Result<usize> {
+        find(name).ok_or_else(|| Error::Invalid(format!("CSV has no \"{name}\" column")))
+    };
+    let place = column(&mapping.place)?;
+    let date = column(&mapping.date)?;
+    let hours = column(&mapping.hours)?;
+    let start_time = mapping.start_time.as_deref().and_then(find);
+    let end_time = mapping.end_time.as_deref().and_then(find);
+    let notes = mapping.notes.as_deref().and_then(find);
+
+    let zone = dates::timezone(conn)?;
+    let mut seen: HashSet<_> = entries::list(conn, profile_id)?
+        .iter()
+        .map(|e| duplicate_key(&e.place, &e.date, e.duration_minutes))
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                rows.push(ImportRow {
+                    line: e.position().map_or(0, |p| p.line()),
+                    status: RowStatus::Invalid,
+                    entry: None,
+                    error: Some(e.to_string()),
+                });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |p| p.line());
+        let field = |i: usize| record.get(i).unwrap_or("").trim().to_string();
+        let parsed = parse_minutes(&field(hours)).and_then(|duration_minutes| {
+            let mut input = EntryInput {
+                place: field(place),
+                date: field(date),
+                start_time: start_time.map(field),
+                end_time: end_time.map(field),
+                duration_minutes,
+                notes: notes.map(field).unwrap_or_default(),
+                reason: "imported from CSV".into(),
+            };
+            let resolved = entries::resolve(&input, zone.as_deref())?;
+            input.duration_minutes = Some(resolved.duration_minutes);
+            Ok((input, resolved))
+        });
+        rows.push(match parsed {
+            Ok((input, resolved)) => {
+                let fresh = seen.insert(duplicate_key(
+                    &resolved.place,
+                    &resolved.date,
+                    resolved.duration_minutes,
+                ));
+                ImportRow {
+                    line,
+                    status: if fresh { RowStatus::New } else { RowStatus::Duplicate },
+                    entry: Some(input),
+                    error: None,
+                }
+            }
+            Err(e) => ImportRow {
+                line,
+                status: RowStatus::Invalid,
+                entry: None,
+                error: Some(e.to_string()),
+            },
+        });
+    }
+
+    let mut imported = 0;
+    if !dry_run {
+        let tx = conn.transaction()?;
+        for row in rows.iter().filter(|r| r.status == RowStatus::New) {
+            if let Some(input) = &row.entry {
+                entries::create(&tx, profile_id, input)?;
+                imported += 1;
+            }
+        }
+        tx.commit()?;
+    }
+    Ok(ImportReport {
+        dry_run,
+        imported,
+        rows,
+    })
+}
+
+/// Reads an hours cell as whole minutes; blank means "work it out from the times".
+fn parse_minutes(hours: &str) -> Result<Option<i64>> {
+    if hours.is_empty() {
+        return Ok(None);
+    }
+    match hours.parse::<f64>() {
+        Ok(h) if h.is_finite() => Ok(Some((h * 60.0).round() as i64)),
+        _ => Err(Error::Invalid(format!("\"{hours}\" is not a number of hours"))),
+    }
+}
+
+fn duplicate_key(place: &str, date: &str, minutes: i64) -> (String, String, i64) {
+    (organizations::name_key(place), date.to_string(), minutes)
+}
@@ -0,0 +1,105 @@
+//! Calendar dates as the volunteer sees them. "Today" is taken in the timezone chosen in
+//! the settings, or the system's when none is chosen, and every entry date is read
+//! through [`parse`], so `entries.date` only ever holds real `YYYY-MM-DD` dates that
+//! sort and compare as text.
+
+use chrono::{
+    DateTime, Duration, Local, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use rusqlite::Connection;
+
+use crate::entries::DATE_FORMAT;
+use crate::error::{Error, Result};
+use crate::settings;
+
+/// Settings key of the IANA timezone name, e.g. `America/Chicago`.
+pub const TIMEZONE_KEY: &str = "timezone";
+
+/// Reads a calendar date written `YYYY-MM-DD`. Months and days may have one digit, and
+/// `/` may separate the parts; the year must have four digits.
+pub fn parse(value: &str) -> Result<NaiveDate> {
+    let invalid = || Error::Invalid(format!("\"{value}\" is not a date (expected YYYY-MM-DD)"));
+    let parts: Vec<&str> = value.trim().split(['-', '/']).collect();
+    let [year, month, day] = parts[..] else {
+        return Err(invalid());
+    };
+    let number = |part: &str, digits: std::ops::RangeInclusive<usize>| {
+        (digits.contains(&part.len()) && part.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| part.parse::<u32>().ok())
+            .flatten()
+    };
+    match (
+        number(year, 4..=4),
+        number(month, 1..=2),
+        number(day, 1..=2),
+    ) {
+        (Some(year), Some(month), Some(day)) => {
+            NaiveDate::from_ymd_opt(year as i32, month, day).ok_or_else(invalid)
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// `value` as a `YYYY-MM-DD` date.
+pub fn normalize(value: &str) -> Result<String> {
+    Ok(parse(value)?.format(DATE_FORMAT).to_string())
+}
+
+/// The chosen timezone; `None` means the system's.
+pub fn timezone(conn: &Connection) -> Result<Option<String>> {
+    settings::get(conn, TIMEZONE_KEY)
+}
+
+/// Chooses a timezone by its IANA name, or goes back to the system's.
+pub fn set_timezone(conn: &Connection, zone: Option<&str>) -> Result<Option<String>> {
+    let zone = match zone.map(str::trim).filter(|z| !z.is_empty()) {
+        Some(name) => Some(
+            name.parse::<Tz>()
+                .map_err(|_| Error::Invalid(format!("\"{name}\" is not a timezone")))?
+                .name()
+                .to_string(),
+        ),
+        None => None,
+    };
+    settings::set(conn, TIMEZONE_KEY, &zone)?;
+    Ok(zone)
+}
+
+/// The wall-clock time at `at` in `zone`, or in the system's timezone when `zone` is
+/// `None` or unknown.
+pub fn local_time(zone: Option<&str>, at: DateTime<Utc>) -> NaiveDateTime {
+    match zone.and_then(|name| name.parse::<Tz>().ok()) {
+        Some(tz) => at.with_timezone(&tz).naive_local(),
+        None => at.with_timezone(&Local).naive_local(),
+    }
+}
+
+/// The earliest and latest instant the wall-clock time `local` names in `zone`, or in the
+/// system's timezone when `zone` is `None` or unknown. They differ only for a time that
+/// happens twice as clocks go back. A time skipped as clocks go forward is read with the
+/// offset from before the change.
+pub fn instants(zone: Option<&str>, local: NaiveDateTime) -> (DateTime<Utc>, DateTime<Utc>) {
+    match zone.and_then(|name| name.parse::<Tz>().ok()) {
+        Some(tz) => instants_in(&tz, local),
+        None => instants_in(&Local, local),
+    }
+}
+
+fn instants_in<Z: TimeZone>(zone: &Z, local: NaiveDateTime) -> (DateTime<Utc>, DateTime<Utc>) {
+    match zone.from_local_datetime(&local) {
+        LocalResult::Single(at) => (at.to_utc(), at.to_utc()),
+        LocalResult::Ambiguous(first, second) => (first.to_utc(), second.to_utc()),
+        LocalResult::None => {
+            let before = zone.offset_from_utc_datetime(&(local - Duration::days(1)));
+            let offset = Duration::seconds(before.fix().local_minus_utc().into());
+            let at = Utc.from_utc_datetime(&(local - offset));
+            (at, at)
+        }
+    }
+}
+
+/// Today's date in the chosen timezone.
+pub fn today(conn: &Connection) -> Result<NaiveDate> {
+    Ok(local_time(timezone(conn)?.as_deref(), Utc::now()).date())
+}
@@ -0,0 +1,217 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::backup::{self, Backup, Rotation};
+use crate::encryption;
+use crate::error::{Error, Result};
+use crate::{migrations, settings};
+
+pub const DB_FILE: &str = "volunteer.db";
+
+/// The bundle identifier from `tauri.conf.json`, which names the app's config directory.
+pub const APP_IDENTIFIER: &str = "com.ajayn.volunteerlog";
+
+/// Where the desktop app keeps its database, for tools running outside Tauri.
+pub fn default_path() -> Option<PathBuf> {
+    Some(dirs::config_dir()?.join(APP_IDENTIFIER).join(DB_FILE))
+}
+
+/// Opens (creating if needed) the database at `path` and migrates it to the latest schema.
+/// An encrypted database needs its `passphrase`; without one it fails with
+/// [`Error::Locked`], and with the wrong one with [`Error::WrongPassphrase`].
+pub fn open(path: &Path, passphrase: Option<&str>) -> Result<Connection> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let conn = Connection::open(path)?;
+    if let Some(passphrase) = passphrase {
+        encryption::apply_key(&conn, passphrase)?;
+    }
+    encryption::check_readable(&conn, passphrase.is_some())?;
+    prepare(conn)
+}
+
+/// A migrated private database, used by tests.
+pub fn open_in_memory() -> Result<Connection> {
+    prepare(Connection::open_in_memory()?)
+}
+
+fn prepare(mut conn: Connection) -> Result<Connection> {
+    conn.pragma_update(None, "foreign_keys", true)?;
+    migrations::run(&mut conn)?;
+    Ok(conn)
+}
+
+/// Runs `write` in a transaction of its own, so it lands whole or not at all. Inside a
+/// caller's transaction it joins that one instead, which commits or rolls back for both.
+pub(crate) fn atomically<T>(
+    conn: &Connection,
+    write: impl FnOnce(&Connection) -> Result<T>,
+) -> Result<T> {
+    if !conn.is_autocommit() {
+        return write(conn);
+    }
+    let tx = conn.unchecked_transaction()?;
+    let value = write(&tx)?;
+    tx.commit()?;
+    Ok(value)
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct DbStatus {
+    pub encrypted: bool,
+    /// No queries run until an encrypted database is unlocked.
+    pub locked: bool,
+}
+
+struct State {
+    conn: Option<Connection>,
+    encrypted: bool,
+    /// Kept while unlocked so backups can be keyed the same way as the database.
+    passphrase: Option<String>,
+}
+
+/// The shared connection, managed as Tauri state. An encrypted database starts out
+/// locked and every [`Db::with`] call fails until [`Db::unlock`] succeeds.
+pub struct Db {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl Db {
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let encrypted = encryption::is_encrypted(&path)?;
+        let conn = if encrypted { None } else { Some(open(&path, None)?) };
+        Ok(Self {
+            path,
+            state: Mutex::new(State {
+                conn,
+                encrypted,
+                passphrase: None,
+            }),
+        })
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn with<T>(&self, f: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        let mut state = self.state();
+        f(state.conn.as_mut().ok_or(Error::Locked)?)
+    }
+
+    pub fn status(&self) -> DbStatus {
+        let state = self.state();
+        DbStatus {
+            encrypted: state.encrypted,
+            locked: state.conn.is_none(),
+        }
+    }
+
+    pub fn unlock(&self, passphrase: &str) -> Result<DbStatus> {
+        {
+            let mut state = self.state();
+            if state.conn.is_none() {
+                state.conn = Some(open(&self.path, Some(passphrase))?);
+                state.passphrase = Some(passphrase.to_string());
+            }
+        }
+        Ok(self.status())
+    }
+
+    /// Closes an encrypted database until it is unlocked again. Plain ones stay open.
+    pub fn lock(&self) -> DbStatus {
+        {
+            let mut state = self.state();
+            if state.encrypted {
+                state.conn = None;
+                state.passphrase = None;
+            }
+        }
+        self.status()
+    }
+
+    /// Encrypts the database with `new`, changes its passphrase, or decrypts it when `new`
+    /// is `None`. An encrypted database needs its `current` passphrase even when unlocked.
+    pub fn set_passphrase(&self, current: Option<&str>, new: Option<&str>) -> Result<DbStatus> {
+        {
+            let mut state = self.state();
+            let State {
+                conn,
+                encrypted,
+                passphrase,
+            } = &mut *state;
+            let open_conn = conn.take().ok_or(Error::Locked)?;
+            match change_key(open_conn, &self.path, *encrypted, current, new) {
+                Ok(changed) => {
+                    *conn = Some(changed);
+                    *encrypted = new.is_some();
+                    *passphrase = new.map(str::to_string);
+                }
+                Err(e) => {
+                    // Whatever failed, the file still holds the old key, which may not be
+                    // the `current` that was given.
+                    *conn = open(&self.path, passphrase.as_deref()).ok();
+                    return Err(e);
+                }
+            }
+        }
+        Ok(self.status())
+    }
+
+    pub fn backup_dir(&self) -> PathBuf {
+        backup::dir_for(&self.path)
+    }
+
+    /// Takes a backup now and prunes old ones according to the saved rotation.
+    pub fn backup(&self) -> Result<Backup> {
+        let state = self.state();
+        let conn = state.conn.as_ref().ok_or(Error::Locked)?;
+        let rotation: Rotation = settings::get(conn, backup::ROTATION_KEY)?;
+        let dir = self.backup_dir();
+        let taken = backup::create(conn, &dir, state.passphrase.as_deref(), backup::now())?;
+        backup::rotate(&dir, &rotation)?;
+        Ok(taken)
+    }
+
+    /// Replaces the live database with the backup named `file`; see [`backup::restore`].
+    /// Backups taken under an earlier passphrase can't be restored.
+    pub fn restore(&self, file: &str) -> Result<()> {
+        let mut state = self.state();
+        let State {
+            conn, passphrase, ..
+        } = &mut *state;
+        let conn = conn.as_mut().ok_or(Error::Locked)?;
+        backup::restore(
+            conn,
+            &self.backup_dir(),
+            file,
+            passphrase.as_deref(),
+            backup::now(),
+        )
+    }
+}
+
+fn change_key(
+    conn: Connection,
+    path: &Path,
+    encrypted: bool,
+    current: Option<&str>,
+    new: Option<&str>,
+) -> Result<Connection> {
+    if encrypted {
+        open(path, Some(current.ok_or(Error::WrongPassphrase)?))?;
+    }
+    match (encrypted, new) {
+        (false, None) => Ok(conn),
+        (true, Some(new)) => {
+            encryption::rekey(&conn, new)?;
+            Ok(conn)
+        }
+        (_, new) => encryption::rewrite(conn, path, new),
+    }
+}
@@ -0,0 +1,111 @@
+//! Encryption at rest through SQLCipher, compiled in with the `encryption` feature. A
+//! database file is either plain SQLite or keyed with a passphrase; moving between the
+//! two rewrites the file, while changing the passphrase re-keys it in place.
+
+use std::fs;
+use std::path::Path;
+
+use rusqlite::{params, Connection, DatabaseName, ErrorCode};
+
+use crate::db;
+use crate::error::{Error, Result};
+use crate::migrations;
+
+fn ensure_supported() -> Result<()> {
+    if cfg!(feature = "encryption") {
+        Ok(())
+    } else {
+        Err(Error::Invalid("this build does not support encrypted databases".into()))
+    }
+}
+
+fn ensure_usable(passphrase: &str) -> Result<()> {
+    ensure_supported()?;
+    if passphrase.is_empty() {
+        return Err(Error::Invalid("passphrase must not be empty".into()));
+    }
+    Ok(())
+}
+
+/// Keys a freshly opened connection. Must run before anything else touches the file.
+pub(crate) fn apply_key(conn: &Connection, passphrase: &str) -> Result<()> {
+    ensure_usable(passphrase)?;
+    conn.pragma_update(None, "key", passphrase)?;
+    Ok(())
+}
+
+pub(crate) fn rekey(conn: &Connection, passphrase: &str) -> Result<()> {
+    ensure_usable(passphrase)?;
+    conn.pragma_update(None, "rekey", passphrase)?;
+    Ok(())
+}
+
+fn readable(conn: &Connection) -> rusqlite::Result<()> {
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+}
+
+fn is_not_a_database(e: &rusqlite::Error) -> bool {
+    e.sqlite_error_code() == Some(ErrorCode::NotADatabase)
+}
+
+/// Fails with [`Error::Locked`] or [`Error::WrongPassphrase`] when the file can't be read
+/// with the key (if any) applied to `conn`.
+pub(crate) fn check_readable(conn: &Connection, keyed: bool) -> Result<()> {
+    match readable(conn) {
+        Ok(()) => Ok(()),
+        Err(e) if is_not_a_database(&e) => Err(if keyed {
+            Error::WrongPassphrase
+        } else {
+            Error::Locked
+        }),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether the file at `path` needs a passphrase to be read.
+pub fn is_encrypted(path: &Path) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    match check_readable(&Connection::open(path)?, false) {
+        Ok(()) => Ok(false),
+        Err(Error::Locked) => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Copies the database behind `conn` into a new file keyed with `passphrase` (plain
+/// when `None`), swaps it in for `path` and opens it. The original file is untouched
+/// until the copy is complete.
+pub(crate) fn rewrite(
+    conn: Connection,
+    path: &Path,
+    passphrase: Option<&str>,
+) -> Result<Connection> {
+    match passphrase {
+        Some(passphrase) => ensure_usable(passphrase)?,
+        None => ensure_supported()?,
+    }
+    let copy = path.with_extension("db.rewrite");
+    let _ = fs::remove_file(&copy);
+
+    let version = migrations::current_version(&conn)?;
+    conn.execute(
+        "ATTACH DATABASE ?1 AS rewritten KEY ?2",
+        params![copy.to_string_lossy(), passphrase.unwrap_or_default()],
+    )?;
+    let exported = conn
+        .query_row("SELECT sqlcipher_export('rewritten')", [], |_| Ok(()))
+        .and_then(|()| {
+            conn.pragma_update(Some(DatabaseName::Attached("rewritten")), "user_version", version)
+        });
+    conn.execute("DETACH DATABASE rewritten", [])?;
+    if let Err(e) = exported {
+        let _ = fs::remove_file(&copy);
+        return Err(e.into());
+    }
+
+    conn.close().map_err(|(_, e)| e)?;
+    fs::rename(&copy, path)?;
+    db::open(path, passphrase)
+}
@@ -0,0 +1,430 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::EntryStatus;
+use crate::dates;
+use crate::db;
+use crate::error::{Error, Result};
+use crate::organizations;
+use crate::revisions::{self, Action};
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+pub const DATETIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+const TIME_FORMAT: &str = "%H:%M";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolunteerEntry {
+    pub id: i64,
+    pub profile_id: i64,
+    pub organization_id: i64,
+    /// The organization's name, kept in step with `organizations.name`.
+    pub place: String,
+    /// The day the shift started.
+    pub date: String,
+    /// Local `YYYY-MM-DDTHH:MM`; the end may fall on the day after `date`.
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// Time actually worked, which may be less than the span between start and end.
+    pub duration_minutes: i64,
+    pub notes: String,
+    /// Unix seconds when the entry went to the trash.
+    #[serde(default)]
+    pub deleted_at: Option<i64>,
+    #[serde(default = "EntryStatus::legacy")]
+    pub status: EntryStatus,
+    /// Why the entry was rejected, while it is.
+    #[serde(default)]
+    pub status_reason: String,
+}
+
+/// The user-editable fields of an entry.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EntryInput {
+    pub place: String,
+    pub date: String,
+    /// `HH:MM` on `date`, or a full `YYYY-MM-DDTHH:MM`.
+    pub start_time: Option<String>,
+    /// `HH:MM` or a full `YYYY-MM-DDTHH:MM`. A bare time earlier than the start is read
+    /// as the next day, so overnight shifts can be entered as e.g. 22:00-02:00, unless it
+    /// falls in the hour repeated as clocks go back.
+    pub end_time: Option<String>,
+    /// Defaults to the span between start and end when both are given, counted in the
+    /// [chosen timezone](crate::dates::timezone) so clock changes are allowed for.
+    pub duration_minutes: Option<i64>,
+    #[serde(default)]
+    pub notes: String,
+    /// Why the entry is being added or changed. Kept in its [revisions](crate::revisions),
+    /// not on the entry.
+    #[serde(default)]
+    pub reason: String,
+}
+
+/// Narrows a profile's entries. Dates are inclusive `YYYY-MM-DD` bounds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryFilter {
+    pub profile_id: i64,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub organization_id: Option<i64>,
+    pub status: Option<EntryStatus>,
+    /// Leaves out rejected entries, whose hours don't count until they are corrected.
+    pub exclude_rejected: bool,
+    /// Only entries carrying this [tag](crate::tags).
+    pub tag_id: Option<i64>,
+}
+
+impl EntryFilter {
+    pub fn profile(profile_id: i64) -> Self {
+        Self {
+            profile_id,
+            ..Default::default()
+        }
+    }
+
+    /// A `WHERE` clause over the entries table aliased as `alias`, with its parameters.
+    /// Only the bounds that are set appear, so range queries can use the date index.
+    /// Entries in the trash never match.
+    pub(crate) fn where_sql(&self, alias: &str) -> (String, Vec<Value>) {
+        let mut clauses = vec![
+            format!("{alias}.deleted_at IS NULL"),
+            format!("{alias}.profile_id = ?"),
+        ];
+        let mut values = vec![Value::Integer(self.profile_id)];
+        if let Some(from) = &self.from {
+            clauses.push(format!("{alias}.date >= ?"));
+            values.push(Value::Text(from.clone()));
+        }
+        if let Some(to) = &self.to {
+            clauses.push(format!("{alias}.date <= ?"));
+            values.push(Value::Text(to.clone()));
+        }
+        if let Some(organization_id) = self.organization_id {
+            clauses.push(format!("{alias}.organization_id = ?"));
+            values.push(Value::Integer(organization_id));
+        }
+        if let Some(status) = self.status {
+            clauses.push(format!("{alias}.status = ?"));
+            values.push(Value::Text(status.as_str().into()));
+        }
+        if self.exclude_rejected {
+            clauses.push(format!("{alias}.status != ?"));
+            values.push(Value::Text(EntryStatus::Rejected.as_str().into()));
+        }
+        if let Some(tag_id) = self.tag_id {
+            clauses.push(format!(
+                "EXISTS(SELECT 1 FROM entry_tags et
+                 WHERE et.entry_id = {alias}.id AND et.tag_id = ?)"
+            ));
+            values.push(Value::Integer(tag_id));
+        }
+        (clauses.join(" AND "), values)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPage {
+    pub entries: Vec<VolunteerEntry>,
+    pub page: u32,
+    pub per_page: u32,
+    /// Count and sum over every page matching the filter.
+    pub total_count: i64,
+    pub total_minutes: i64,
+}
+
+pub const MAX_PER_PAGE: u32 = 500;
+
+/// An [`EntryInput`] that passed validation, in the form it is stored.
+pub(crate) struct Resolved {
+    pub place: String,
+    pub date: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub duration_minutes: i64,
+    pub notes: String,
+}
+
+const COLUMNS: &str = "id, profile_id, organization_id, place, date, start_time, end_time, \
+    duration_minutes, notes, deleted_at, status, status_reason";
+
+/// The entry columns qualified with a table alias, for queries that join `entries`.
+pub(crate) fn columns_of(alias: &str) -> String {
+    COLUMNS
+        .split(", ")
+        .map(|column| format!("{alias}.{column} AS {column}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl From<&VolunteerEntry> for EntryInput {
+    fn from(entry: &VolunteerEntry) -> Self {
+        Self {
+            place: entry.place.clone(),
+            date: entry.date.clone(),
+            start_time: entry.start_time.clone(),
+            end_time: entry.end_time.clone(),
+            duration_minutes: Some(entry.duration_minutes),
+            notes: entry.notes.clone(),
+            reason: String::new(),
+        }
+    }
+}
+
+impl VolunteerEntry {
+    pub(crate) fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            profile_id: row.get("profile_id")?,
+            organization_id: row.get("organization_id")?,
+            place: row.get("place")?,
+            date: row.get("date")?,
+            start_time: row.get("start_time")?,
+            end_time: row.get("end_time")?,
+            duration_minutes: row.get("duration_minutes")?,
+            notes: row.get("notes")?,
+            deleted_at: row.get("deleted_at")?,
+            status: row.get("status")?,
+            status_reason: row.get("status_reason")?,
+        })
+    }
+
+    pub fn hours(&self) -> f64 {
+        self.duration_minutes as f64 / 60.0
+    }
+}
+
+pub fn list(conn: &Connection, profile_id: i64) -> Result<Vec<VolunteerEntry>> {
+    list_matching(conn, &EntryFilter::profile(profile_id))
+}
+
+/// Every entry matching `filter`, newest first.
+pub fn list_matching(conn: &Connection, filter: &EntryFilter) -> Result<Vec<VolunteerEntry>> {
+    let (condition, values) = filter.where_sql("e");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM entries e WHERE {condition}
+         ORDER BY e.date DESC, e.start_time DESC, e.id DESC",
+        columns_of("e")
+    ))?;
+    let entries = stmt
+        .query_map(params_from_iter(&values), VolunteerEntry::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+/// One page of matching entries, newest first; pages are numbered from 1.
+pub fn page(
+    conn: &Connection,
+    filter: &EntryFilter,
+    page: u32,
+    per_page: u32,
+) -> Result<EntryPage> {
+    let page = page.max(1);
+    let per_page = per_page.clamp(1, MAX_PER_PAGE);
+    let (condition, mut values) = filter.where_sql("e");
+    let (total_count, total_minutes) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM(e.duration_minutes), 0) FROM entries e WHERE {condition}"
+        ),
+        params_from_iter(&values),
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    values.push(Value::Integer(per_page.into()));
+    values.push(Value::Integer(i64::from(page - 1) * i64::from(per_page)));
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM entries e WHERE {condition}
+         ORDER BY e.date DESC, e.start_time DESC, e.id DESC LIMIT ? OFFSET ?",
+        columns_of("e")
+    ))?;
+    let entries = stmt
+        .query_map(params_from_iter(&values), VolunteerEntry::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(EntryPage {
+        entries,
+        page,
+        per_page,
+        total_count,
+        total_minutes,
+    })
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<VolunteerEntry> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM entries WHERE id = ?1 AND deleted_at IS NULL"),
+        [id],
+        VolunteerEntry::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("entry"))
+}
+
+/// An entry whether or not it is in the trash.
+fn get_any(conn: &Connection, id: i64) -> Result<VolunteerEntry> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM entries WHERE id = ?1"),
+        [id],
+        VolunteerEntry::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("entry"))
+}
+
+pub fn create(conn: &Connection, profile_id: i64, input: &EntryInput) -> Result<VolunteerEntry> {
+    let entry = resolve(input, dates::timezone(conn)?.as_deref())?;
+    db::atomically(conn, |tx| {
+        let organization = organizations::find_or_create(tx, &entry.place)?;
+        tx.execute(
+            "INSERT INTO entries
+             (profile_id, organization_id, place, date, start_time, end_time,
+             duration_minutes, notes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                profile_id,
+                organization.id,
+                organization.name,
+                entry.date,
+                entry.start_time,
+                entry.end_time,
+                entry.duration_minutes,
+                entry.notes
+            ],
+        )?;
+        let created = get(tx, tx.last_insert_rowid())?;
+        revisions::record(tx, &created, Action::Create, &input.reason)?;
+        Ok(created)
+    })
+}
+
+pub fn update(conn: &Connection, id: i64, input: &EntryInput) -> Result<VolunteerEntry> {
+    db::atomically(conn, |tx| {
+        let updated = store(tx, id, input)?;
+        revisions::record(tx, &updated, Action::Update, &input.reason)?;
+        Ok(updated)
+    })
+}
+
+/// Writes new values over an entry without recording a revision. Approved entries are
+/// [locked](crate::approval).
+pub(crate) fn store(conn: &Connection, id: i64, input: &EntryInput) -> Result<VolunteerEntry> {
+    if get(conn, id)?.status == EntryStatus::Approved {
+        return Err(Error::Invalid("approved entries cannot be edited".into()));
+    }
+    let entry = resolve(input, dates::timezone(conn)?.as_deref())?;
+    let organization = organizations::find_or_create(conn, &entry.place)?;
+    let changed = conn.execute(
+        "UPDATE entries SET organization_id = ?1, place = ?2, date = ?3, start_time = ?4,
+         end_time = ?5, duration_minutes = ?6, notes = ?7 WHERE id = ?8 AND deleted_at IS NULL",
+        params![
+            organization.id,
+            organization.name,
+            entry.date,
+            entry.start_time,
+            entry.end_time,
+            entry.duration_minutes,
+            entry.notes,
+            id
+        ],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("entry"));
+    }
+    get(conn, id)
+}
+
+/// Moves the entry to the [trash](crate::trash). Approved entries stay.
+pub fn delete(conn: &Connection, id: i64, reason: &str) -> Result<()> {
+    if get(conn, id)?.status == EntryStatus::Approved {
+        return Err(Error::Invalid("approved entries cannot be deleted".into()));
+    }
+    db::atomically(conn, |tx| {
+        let changed = tx.execute(
+            "UPDATE entries SET deleted_at = unixepoch() WHERE id = ?1 AND deleted_at IS NULL",
+            [id],
+        )?;
+        if changed == 0 {
+            return Err(Error::NotFound("entry"));
+        }
+        revisions::record(tx, &get_any(tx, id)?, Action::Delete, reason)
+    })
+}
+
+pub(crate) fn resolve(input: &EntryInput, zone: Option<&str>) -> Result<Resolved> {
+    let place = input.place.trim();
+    if place.is_empty() {
+        return Err(Error::Invalid("place is required".into()));
+    }
+    let date = dates::parse(&input.date)?;
+
+    let start = parse_time(input.start_time.as_deref(), date)?;
+    let end = parse_time(input.end_time.as_deref(), date)?;
+    let span = match (start, end) {
+        (Some((start, _)), Some((mut end, bare_end))) => {
+            if start.date() != date {
+                return Err(Error::Invalid("start time must fall on the entry date".into()));
+            }
+            // Minutes are counted between instants in `zone`, so a shift across a clock
+            // change counts the time actually worked. An end that reads as earlier than
+            // the start may be the repeat of an hour as clocks go back.
+            let (began, _) = dates::instants(zone, start);
+            let (first, second) = dates::instants(zone, end);
+            let mut finished = if first > began { first } else { second };
+            if bare_end && end < start && finished <= began {
+                end += Duration::days(1);
+                finished = dates::instants(zone, end).0;
+            }
+            if finished <= began {
+                return Err(Error::Invalid("end time must be after start time".into()));
+            }
+            Some((start, end, (finished - began).num_minutes()))
+        }
+        (None, None) => None,
+        _ => {
+            return Err(Error::Invalid(
+                "give both a start and an end time, or neither".into(),
+            ))
+        }
+    };
+    let span_minutes = span.map(|(_, _, minutes)| minutes);
+
+    let duration_minutes = match (input.duration_minutes, span_minutes) {
+        (Some(minutes), _) if minutes <= 0 => {
+            return Err(Error::Invalid("duration must be greater than zero".into()))
+        }
+        (Some(minutes), Some(span)) if minutes > span => {
+            return Err(Error::Invalid(
+                "duration is longer than the time between start and end".into(),
+            ))
+        }
+        (Some(minutes), _) => minutes,
+        (None, Some(span)) => span,
+        (None, None) => {
+            return Err(Error::Invalid(
+                "duration is required without a start and end time".into(),
+            ))
+        }
+    };
+
+    Ok(Resolved {
+        place: place.to_string(),
+        date: date.format(DATE_FORMAT).to_string(),
+        start_time: span.map(|(start, _, _)| start.format(DATETIME_FORMAT).to_string()),
+        end_time: span.map(|(_, end, _)| end.format(DATETIME_FORMAT).to_string()),
+        duration_minutes,
+        notes: input.notes.clone(),
+    })
+}
+
+/// Parses a full datetime or a bare time on `date`; the flag reports a bare time.
+fn parse_time(value: Option<&str>, date: NaiveDate) -> Result<Option<(NaiveDateTime, bool)>> {
+    let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, DATETIME_FORMAT) {
+        return Ok(Some((datetime, false)));
+    }
+    NaiveTime::parse_from_str(value, TIME_FORMAT)
+        .map(|time| Some((date.and_time(time), true)))
+        .map_err(|_| Error::Invalid(format!("\"{value}\" is not a time (expected HH:MM)")))
+}
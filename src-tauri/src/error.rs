@@ -0,0 +1,41 @@
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error(transparent)]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("{0} not found")]
+    NotFound(&'static str),
+    #[error("{0}")]
+    Invalid(String),
+    #[error("the database is locked; enter its passphrase to unlock it")]
+    Locked,
+    #[error("wrong passphrase")]
+    WrongPassphrase,
+    #[error("this profile is locked; enter its PIN to unlock it")]
+    ProfileLocked,
+    #[error("wrong PIN")]
+    WrongPin,
+    /// Seconds until another PIN may be tried.
+    #[error("too many wrong PINs; try again in {0} seconds")]
+    PinBackoff(i64),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+// Commands hand errors back to the webview as plain strings.
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
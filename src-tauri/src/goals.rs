@@ -0,0 +1,274 @@
+//! Personal hour goals and how they are coming along. A forecast compares the pace needed
+//! to reach a goal in time with the pace of the last few weeks, and projects the day the
+//! goal will be met if that recent pace keeps up.
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::dates;
+use crate::entries::{EntryFilter, DATE_FORMAT};
+use crate::error::{Error, Result};
+use crate::{organizations, totals};
+
+/// How far back the recent pace looks.
+pub const RECENT_DAYS: i64 = 28;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalKind {
+    /// A number of hours between the start date and a deadline.
+    ByDate,
+    /// A number of hours every calendar month.
+    PerMonth,
+    /// A number of hours at one organization, by a deadline or open-ended.
+    PerOrganization,
+}
+
+impl GoalKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            GoalKind::ByDate => "by_date",
+            GoalKind::PerMonth => "per_month",
+            GoalKind::PerOrganization => "per_organization",
+        }
+    }
+
+    fn parse(kind: &str) -> rusqlite::Result<Self> {
+        Ok(match kind {
+            "by_date" => GoalKind::ByDate,
+            "per_month" => GoalKind::PerMonth,
+            "per_organization" => GoalKind::PerOrganization,
+            other => {
+                return Err(rusqlite::Error::InvalidColumnType(
+                    0,
+                    format!("goal kind {other}"),
+                    rusqlite::types::Type::Text,
+                ))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Goal {
+    pub id: i64,
+    pub profile_id: i64,
+    pub kind: GoalKind,
+    pub minutes: i64,
+    pub organization_id: Option<i64>,
+    /// First day that counts, `YYYY-MM-DD`.
+    pub start_date: String,
+    pub deadline: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalInput {
+    pub kind: GoalKind,
+    pub minutes: i64,
+    #[serde(default)]
+    pub organization_id: Option<i64>,
+    /// Defaults to the day the goal is set.
+    #[serde(default)]
+    pub start_date: Option<String>,
+    #[serde(default)]
+    pub deadline: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Forecast {
+    pub goal: Goal,
+    /// The period being counted; for monthly goals, the current month.
+    pub from: String,
+    pub to: Option<String>,
+    pub minutes_done: i64,
+    pub minutes_remaining: i64,
+    /// Minutes a week needed from today to meet the goal by `to`.
+    pub required_minutes_per_week: Option<i64>,
+    /// Minutes a week over the last [`RECENT_DAYS`] days.
+    pub recent_minutes_per_week: i64,
+    /// When the goal will be met at the recent pace; `None` if it has no recent pace.
+    pub projected_completion: Option<String>,
+    /// Whether the projection lands by `to`.
+    pub on_track: Option<bool>,
+    pub complete: bool,
+}
+
+const COLUMNS: &str = "id, profile_id, kind, minutes, organization_id, start_date, deadline";
+
+impl Goal {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            profile_id: row.get("profile_id")?,
+            kind: GoalKind::parse(&row.get::<_, String>("kind")?)?,
+            minutes: row.get("minutes")?,
+            organization_id: row.get("organization_id")?,
+            start_date: row.get("start_date")?,
+            deadline: row.get("deadline")?,
+        })
+    }
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+pub fn list(conn: &Connection, profile_id: i64) -> Result<Vec<Goal>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM goals WHERE profile_id = ?1 ORDER BY deadline IS NULL, deadline, id"
+    ))?;
+    let goals = stmt
+        .query_map([profile_id], Goal::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(goals)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Goal> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM goals WHERE id = ?1"),
+        [id],
+        Goal::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("goal"))
+}
+
+pub fn create(
+    conn: &Connection,
+    profile_id: i64,
+    input: &GoalInput,
+    today: NaiveDate,
+) -> Result<Goal> {
+    if input.minutes <= 0 {
+        return Err(Error::Invalid("a goal needs some hours to reach".into()));
+    }
+    let start = match input.start_date.as_deref() {
+        Some(start) => dates::parse(start)?,
+        None => today,
+    };
+    let deadline = input.deadline.as_deref().map(dates::parse).transpose()?;
+    match (input.kind, deadline, input.organization_id) {
+        (GoalKind::ByDate, None, _) => {
+            return Err(Error::Invalid(
+                "choose the date to reach the goal by".into(),
+            ))
+        }
+        (GoalKind::PerMonth, Some(_), _) => {
+            return Err(Error::Invalid("a monthly goal has no deadline".into()))
+        }
+        (GoalKind::PerOrganization, _, None) => {
+            return Err(Error::Invalid("choose the organization".into()))
+        }
+        (_, Some(deadline), _) if deadline < start => {
+            return Err(Error::Invalid(
+                "the deadline is before the start date".into(),
+            ))
+        }
+        _ => {}
+    }
+    let organization_id = match input.kind {
+        GoalKind::PerOrganization => input.organization_id,
+        _ => None,
+    };
+    if let Some(id) = organization_id {
+        organizations::get(conn, id)?;
+    }
+    conn.execute(
+        "INSERT INTO goals (profile_id, kind, minutes, organization_id, start_date, deadline)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            profile_id,
+            input.kind.as_str(),
+            input.minutes,
+            organization_id,
+            format_date(start),
+            deadline.map(format_date)
+        ],
+    )?;
+    get(conn, conn.last_insert_rowid())
+}
+
+pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+    let changed = conn.execute("DELETE FROM goals WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(Error::NotFound("goal"));
+    }
+    Ok(())
+}
+
+fn logged(conn: &Connection, goal: &Goal, from: NaiveDate, to: Option<NaiveDate>) -> Result<i64> {
+    let filter = EntryFilter {
+        from: Some(format_date(from)),
+        to: to.map(format_date),
+        organization_id: goal.organization_id,
+        exclude_rejected: true,
+        ..EntryFilter::profile(goal.profile_id)
+    };
+    totals::minutes(conn, &filter)
+}
+
+/// Progress towards a goal as of `today`, with the pace needed and the recent pace.
+pub fn forecast(conn: &Connection, id: i64, today: NaiveDate) -> Result<Forecast> {
+    let goal = get(conn, id)?;
+    let (from, to) = match goal.kind {
+        GoalKind::PerMonth => {
+            let first = today.with_day(1).unwrap_or(today);
+            let last = first
+                .checked_add_months(Months::new(1))
+                .and_then(|next| next.pred_opt())
+                .unwrap_or(today);
+            (first, Some(last))
+        }
+        _ => (
+            dates::parse(&goal.start_date)?,
+            goal.deadline.as_deref().map(dates::parse).transpose()?,
+        ),
+    };
+    let minutes_done = logged(conn, &goal, from, to)?;
+    let minutes_remaining = (goal.minutes - minutes_done).max(0);
+
+    let recent_from = (today - Duration::days(RECENT_DAYS - 1)).max(from);
+    let recent_days = (today - recent_from).num_days() + 1;
+    let recent = if recent_days > 0 {
+        logged(conn, &goal, recent_from, Some(today))?
+    } else {
+        0
+    };
+    let recent_minutes_per_week = if recent_days > 0 {
+        recent * 7 / recent_days
+    } else {
+        0
+    };
+
+    // Today counts as a day left, since it can still be logged.
+    let required_minutes_per_week = to.map(|to| {
+        let days_left = (to - today).num_days() + 1;
+        match days_left {
+            _ if minutes_remaining == 0 => 0,
+            days if days > 0 => (minutes_remaining * 7 + days - 1) / days,
+            _ => minutes_remaining,
+        }
+    });
+    let projected = if minutes_remaining == 0 {
+        Some(today)
+    } else if recent > 0 {
+        let days = (minutes_remaining * recent_days + recent - 1) / recent;
+        Some(today + Duration::days(days))
+    } else {
+        None
+    };
+
+    Ok(Forecast {
+        from: format_date(from),
+        to: to.map(format_date),
+        minutes_done,
+        minutes_remaining,
+        required_minutes_per_week,
+        recent_minutes_per_week,
+        projected_completion: projected.map(format_date),
+        on_track: to.map(|to| projected.is_some_and(|p| p <= to)),
+        complete: minutes_remaining == 0,
+        goal,
+    })
+}
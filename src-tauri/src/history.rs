@@ -0,0 +1,208 @@
+//! Multi-step undo and redo of edits made in the app. The history is process state, like
+//! the unlocked profiles in [`crate::pins`], so it starts empty on every launch.
+
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::entries::{self, EntryInput, VolunteerEntry};
+use crate::error::Result;
+use crate::organizations::{self, Merge, Organization};
+use crate::profiles::{self, Profile};
+use crate::trash;
+
+/// How many changes can be undone.
+pub const DEPTH: usize = 50;
+
+/// One edit, with what is needed to reverse and repeat it. Deletions go through the
+/// trash, so undoing one is a restore.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    CreateEntry {
+        entry: VolunteerEntry,
+    },
+    UpdateEntry {
+        before: VolunteerEntry,
+        after: VolunteerEntry,
+    },
+    DeleteEntry {
+        entry: VolunteerEntry,
+    },
+    DeleteProfile {
+        profile: Profile,
+    },
+    UpdateOrganization {
+        before: Organization,
+        after: Organization,
+    },
+    MergeOrganizations {
+        merge: Merge,
+    },
+}
+
+impl Change {
+    /// The profiles whose entries undoing or redoing the change would touch, for the PIN
+    /// check.
+    pub fn profile_ids(&self, conn: &Connection) -> Result<Vec<i64>> {
+        Ok(match self {
+            Change::CreateEntry { entry } | Change::DeleteEntry { entry } => {
+                vec![entry.profile_id]
+            }
+            Change::UpdateEntry { after, .. } => vec![after.profile_id],
+            Change::DeleteProfile { profile } => vec![profile.id],
+            Change::UpdateOrganization { after, .. } => {
+                organizations::profiles_using(conn, &[after.id])?
+            }
+            Change::MergeOrganizations { merge } => {
+                organizations::profiles_using(conn, &[merge.kept.id, merge.merged.id])?
+            }
+        })
+    }
+
+    fn undo(&self, conn: &mut Connection) -> Result<()> {
+        match self {
+            Change::CreateEntry { entry } => entries::delete(conn, entry.id, "undo")?,
+            Change::UpdateEntry { before, .. } => {
+                entries::update(conn, before.id, &edit(before, "undo"))?;
+            }
+            Change::DeleteEntry { entry } => {
+                trash::restore_entry(conn, entry.id)?;
+            }
+            Change::DeleteProfile { profile } => {
+                trash::restore_profile(conn, profile.id)?;
+            }
+            Change::UpdateOrganization { before, .. } => {
+                organizations::update(conn, before.id, &before.into())?;
+            }
+            Change::MergeOrganizations { merge } => {
+                organizations::unmerge(conn, merge)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn redo(&self, conn: &mut Connection) -> Result<()> {
+        match self {
+            Change::CreateEntry { entry } => {
+                trash::restore_entry(conn, entry.id)?;
+            }
+            Change::UpdateEntry { after, .. } => {
+                entries::update(conn, after.id, &edit(after, "redo"))?;
+            }
+            Change::DeleteEntry { entry } => entries::delete(conn, entry.id, "redo")?,
+            Change::DeleteProfile { profile } => profiles::delete(conn, profile.id)?,
+            Change::UpdateOrganization { after, .. } => {
+                organizations::update(conn, after.id, &after.into())?;
+            }
+            Change::MergeOrganizations { merge } => {
+                organizations::merge(conn, merge.kept.id, merge.merged.id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The edit that puts `entry` back as it was, noting why in its revision.
+fn edit(entry: &VolunteerEntry, reason: &str) -> EntryInput {
+    EntryInput {
+        reason: reason.into(),
+        ..entry.into()
+    }
+}
+
+/// What the next undo and redo would do.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryStatus {
+    pub undo: Option<Change>,
+    pub redo: Option<Change>,
+}
+
+#[derive(Default)]
+struct Stacks {
+    undo: Vec<Change>,
+    redo: Vec<Change>,
+}
+
+/// Managed as Tauri state next to [`crate::db::Db`].
+#[derive(Default)]
+pub struct History(Mutex<Stacks>);
+
+impl History {
+    fn stacks(&self) -> MutexGuard<'_, Stacks> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Records a change that was just made. Anything that could be redone is forgotten.
+    pub fn record(&self, change: Change) {
+        let mut stacks = self.stacks();
+        stacks.redo.clear();
+        stacks.undo.push(change);
+        if stacks.undo.len() > DEPTH {
+            stacks.undo.remove(0);
+        }
+    }
+
+    pub fn clear(&self) {
+        let mut stacks = self.stacks();
+        stacks.undo.clear();
+        stacks.redo.clear();
+    }
+
+    pub fn status(&self) -> HistoryStatus {
+        let stacks = self.stacks();
+        HistoryStatus {
+            undo: stacks.undo.last().cloned(),
+            redo: stacks.redo.last().cloned(),
+        }
+    }
+
+    /// Reverses the latest change, returning it, or `None` when there is nothing to undo.
+    /// `allow` can refuse the change first, which leaves it in place; a change that
+    /// fails to apply, say because its entry has since been purged, is dropped.
+    pub fn undo(
+        &self,
+        conn: &mut Connection,
+        allow: impl FnOnce(&Connection, &Change) -> Result<()>,
+    ) -> Result<Option<Change>> {
+        self.step(conn, allow, true)
+    }
+
+    /// Repeats the latest undone change; see [`History::undo`].
+    pub fn redo(
+        &self,
+        conn: &mut Connection,
+        allow: impl FnOnce(&Connection, &Change) -> Result<()>,
+    ) -> Result<Option<Change>> {
+        self.step(conn, allow, false)
+    }
+
+    fn step(
+        &self,
+        conn: &mut Connection,
+        allow: impl FnOnce(&Connection, &Change) -> Result<()>,
+        undo: bool,
+    ) -> Result<Option<Change>> {
+        let mut stacks = self.stacks();
+        let Stacks {
+            undo: undos,
+            redo: redos,
+        } = &mut *stacks;
+        let (from, to) = if undo { (undos, redos) } else { (redos, undos) };
+        let Some(change) = from.pop() else {
+            return Ok(None);
+        };
+        if let Err(e) = allow(conn, &change) {
+            from.push(change);
+            return Err(e);
+        }
+        if undo {
+            change.undo(conn)?;
+        } else {
+            change.redo(conn)?;
+        }
+        to.push(change.clone());
+        Ok(Some(change))
+    }
+}
@@ -1,8 +1,172 @@
+pub mod approval;
+pub mod archive;
+pub mod attestation;
+pub mod backup;
+pub mod chain;
+mod commands;
+pub mod csv_io;
+pub mod dates;
+pub mod db;
+pub mod encryption;
+pub mod entries;
+pub mod error;
+pub mod goals;
+pub mod history;
+pub mod migrations;
+pub mod organizations;
+pub mod pdf;
+pub mod periods;
+pub mod pins;
+pub mod profiles;
+pub mod programs;
+pub mod pvsa;
+pub mod report;
+pub mod revisions;
+pub mod search;
+pub mod settings;
+pub mod tags;
+pub mod timer;
+pub mod totals;
+pub mod trash;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Event sent to the window with the error when scheduled maintenance fails.
+const MAINTENANCE_FAILED: &str = "maintenance-failed";
+
+/// Backs the database up and then purges expired trash, so purged items are still in a
+/// backup. Runs once the database is open after launch (an encrypted one may still be
+/// locked), then every [`backup::INTERVAL`] while the app runs. Failures are sent to the
+/// window as [`MAINTENANCE_FAILED`] events.
+fn schedule_maintenance(app: AppHandle) {
+    thread::spawn(move || {
+        let mut last: Option<Instant> = None;
+        loop {
+            if last.is_none_or(|at| at.elapsed() >= backup::INTERVAL) {
+                let db = app.state::<db::Db>();
+                let done = db
+                    .backup()
+                    .and_then(|_| db.with(|conn| trash::purge_expired(conn, timer::now())));
+                match done {
+                    Err(error::Error::Locked) => {}
+                    Err(e) => {
+                        let _ = app.emit(MAINTENANCE_FAILED, e.to_string());
+                        last = Some(Instant::now());
+                    }
+                    Ok(_) => last = Some(Instant::now()),
+                }
+            }
+            thread::sleep(Duration::from_secs(60));
+        }
+    });
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_sql::Builder::new().build())
+        .setup(|app| {
+            // Same location the SQL plugin used, so existing logs carry over.
+            let path = app.path().app_config_dir()?.join(db::DB_FILE);
+            app.manage(db::Db::open(path)?);
+            app.manage(pins::Sessions::default());
+            app.manage(history::History::default());
+            schedule_maintenance(app.handle().clone());
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            commands::database_status,
+            commands::today,
+            commands::timezone,
+            commands::set_timezone,
+            commands::unlock_database,
+            commands::lock_database,
+            commands::set_database_passphrase,
+            commands::list_backups,
+            commands::create_backup,
+            commands::restore_backup,
+            commands::backup_rotation,
+            commands::set_backup_rotation,
+            commands::export_archive,
+            commands::read_archive_manifest,
+            commands::restore_archive,
+            commands::list_trash,
+            commands::restore_trashed_entry,
+            commands::restore_trashed_profile,
+            commands::purge_trashed_entry,
+            commands::purge_trashed_profile,
+            commands::trash_settings,
+            commands::set_trash_settings,
+            commands::history_status,
+            commands::undo,
+            commands::redo,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::rename_profile,
+            commands::delete_profile,
+            commands::profile_locked,
+            commands::unlock_profile,
+            commands::lock_profile,
+            commands::set_profile_pin,
+            commands::set_profile_birthdate,
+            commands::pvsa_progress,
+            commands::program_progress,
+            commands::enroll_program,
+            commands::unenroll_program,
+            commands::goal_forecasts,
+            commands::create_goal,
+            commands::delete_goal,
+            commands::list_entries,
+            commands::list_entries_page,
+            commands::entry_years,
+            commands::entry_periods,
+            commands::reporting_periods,
+            commands::set_reporting_periods,
+            commands::entry_totals,
+            commands::create_entry,
+            commands::update_entry,
+            commands::delete_entry,
+            commands::set_entry_status,
+            commands::entry_revisions,
+            commands::revert_entry,
+            commands::chain_head,
+            commands::verify_chain,
+            commands::organization_key,
+            commands::generate_organization_key,
+            commands::trust_organization_key,
+            commands::request_attestation,
+            commands::read_attestation,
+            commands::sign_attestation,
+            commands::import_attestation,
+            commands::entry_sign_offs,
+            commands::list_organizations,
+            commands::create_organization,
+            commands::update_organization,
+            commands::delete_organization,
+            commands::merge_organizations,
+            commands::list_tags,
+            commands::entry_tags,
+            commands::tag_entry,
+            commands::untag_entry,
+            commands::rename_tag,
+            commands::set_tag_sdg,
+            commands::delete_tag,
+            commands::merge_tags,
+            commands::install_tag_taxonomy,
+            commands::export_csv,
+            commands::import_csv,
+            commands::export_report_pdf,
+            commands::search_entries,
+            commands::timer_status,
+            commands::start_timer,
+            commands::pause_timer,
+            commands::resume_timer,
+            commands::stop_timer,
+            commands::cancel_timer,
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -0,0 +1,516 @@
+use rusqlite::functions::FunctionFlags;
+use rusqlite::Connection;
+
+use crate::error::{Error, Result};
+use crate::{chain, dates, organizations};
+
+/// One step of the schema history. Applied in order, each in its own transaction,
+/// and recorded in SQLite's `user_version`. Foreign keys are not enforced while
+/// migrations run, so a step may rebuild a table that others refer to.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// The migration that creates the hash chain.
+const CHAIN_VERSION: u32 = 11;
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create profiles and entries",
+        // `IF NOT EXISTS` adopts databases created by the webview before migrations existed.
+        sql: "CREATE TABLE IF NOT EXISTS profiles (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE
+        );
+
+        CREATE TABLE IF NOT EXISTS entries (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL,
+            place TEXT NOT NULL,
+            date TEXT NOT NULL,
+            hours REAL NOT NULL,
+            notes TEXT DEFAULT '',
+            FOREIGN KEY (profile_id) REFERENCES profiles(id)
+        );",
+    },
+    Migration {
+        version: 2,
+        description: "fold free-text places into organizations",
+        sql: "CREATE TABLE organizations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            name_key TEXT NOT NULL UNIQUE,
+            contact_name TEXT NOT NULL DEFAULT '',
+            email TEXT NOT NULL DEFAULT '',
+            phone TEXT NOT NULL DEFAULT '',
+            address TEXT NOT NULL DEFAULT '',
+            notes TEXT NOT NULL DEFAULT ''
+        );
+
+        INSERT INTO organizations (name, name_key)
+        SELECT spelling, folded FROM (
+            SELECT trim(place) AS spelling, name_key(place) AS folded, min(id) AS first_id
+            FROM entries GROUP BY folded
+        ) ORDER BY first_id;
+
+        ALTER TABLE entries ADD COLUMN organization_id INTEGER REFERENCES organizations(id);
+
+        UPDATE entries SET
+            organization_id = (SELECT id FROM organizations o WHERE o.name_key = name_key(entries.place)),
+            place = (SELECT name FROM organizations o WHERE o.name_key = name_key(entries.place));
+
+        CREATE INDEX entries_organization_id ON entries(organization_id);",
+    },
+    Migration {
+        version: 3,
+        description: "add clock-in timer sessions",
+        sql: "CREATE TABLE timer_sessions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+            place TEXT NOT NULL DEFAULT '',
+            notes TEXT NOT NULL DEFAULT '',
+            started_at INTEGER NOT NULL,
+            ended_at INTEGER,
+            paused_at INTEGER,
+            break_seconds INTEGER NOT NULL DEFAULT 0,
+            entry_id INTEGER REFERENCES entries(id) ON DELETE SET NULL
+        );
+
+        CREATE UNIQUE INDEX timer_sessions_running ON timer_sessions(profile_id)
+            WHERE ended_at IS NULL;",
+    },
+    Migration {
+        version: 4,
+        description: "store shift times and whole minutes instead of fractional hours",
+        // Hours that make no whole minute are stored as one, to satisfy the check, and
+        // noted so that migration 25 can reject them once entries have a status.
+        sql: "CREATE TABLE entries_new (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL REFERENCES profiles(id),
+            organization_id INTEGER NOT NULL REFERENCES organizations(id),
+            place TEXT NOT NULL,
+            date TEXT NOT NULL,
+            start_time TEXT,
+            end_time TEXT,
+            duration_minutes INTEGER NOT NULL CHECK (duration_minutes > 0),
+            notes TEXT NOT NULL DEFAULT ''
+        );
+
+        INSERT INTO entries_new (id, profile_id, organization_id, place, date, duration_minutes, notes)
+        SELECT id, profile_id, organization_id, place, date,
+               max(1, CAST(round(hours * 60) AS INTEGER)), coalesce(notes, '')
+        FROM entries;
+
+        CREATE TABLE legacy_rejections (entry_id INTEGER PRIMARY KEY, reason TEXT NOT NULL);
+        INSERT INTO legacy_rejections (entry_id, reason)
+        SELECT id, 'the hours \"' || hours || '\" were not a positive duration; correct them'
+        FROM entries WHERE NOT round(hours * 60) >= 1;
+
+        DROP TABLE entries;
+        ALTER TABLE entries_new RENAME TO entries;
+
+        CREATE INDEX entries_organization_id ON entries(organization_id);
+        CREATE INDEX entries_profile_date ON entries(profile_id, date);",
+    },
+    Migration {
+        version: 5,
+        description: "index places and notes for full-text search",
+        sql: "CREATE VIRTUAL TABLE entries_fts USING fts5(
+            place, notes,
+            content = 'entries', content_rowid = 'id',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        INSERT INTO entries_fts (entries_fts) VALUES ('rebuild');
+
+        CREATE TRIGGER entries_fts_insert AFTER INSERT ON entries BEGIN
+            INSERT INTO entries_fts (rowid, place, notes) VALUES (new.id, new.place, new.notes);
+        END;
+
+        CREATE TRIGGER entries_fts_delete AFTER DELETE ON entries BEGIN
+            INSERT INTO entries_fts (entries_fts, rowid, place, notes)
+            VALUES ('delete', old.id, old.place, old.notes);
+        END;
+
+        CREATE TRIGGER entries_fts_update AFTER UPDATE OF place, notes ON entries BEGIN
+            INSERT INTO entries_fts (entries_fts, rowid, place, notes)
+            VALUES ('delete', old.id, old.place, old.notes);
+            INSERT INTO entries_fts (rowid, place, notes) VALUES (new.id, new.place, new.notes);
+        END;",
+    },
+    Migration {
+        version: 6,
+        description: "index entries by profile and date, carrying the columns totals sum",
+        sql: "DROP INDEX entries_profile_date;
+        CREATE INDEX entries_profile_totals
+            ON entries(profile_id, date, organization_id, duration_minutes);",
+    },
+    Migration {
+        version: 7,
+        description: "add optional profile PINs",
+        sql: "ALTER TABLE profiles ADD COLUMN pin_hash TEXT;",
+    },
+    Migration {
+        version: 8,
+        description: "add a settings table",
+        sql: "CREATE TABLE settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );",
+    },
+    Migration {
+        version: 9,
+        description: "keep deleted profiles and entries in a trash",
+        sql: "ALTER TABLE profiles ADD COLUMN deleted_at INTEGER;
+        ALTER TABLE entries ADD COLUMN deleted_at INTEGER;
+        DROP INDEX entries_profile_totals;
+        CREATE INDEX entries_profile_totals
+            ON entries(profile_id, date, organization_id, duration_minutes)
+            WHERE deleted_at IS NULL;",
+    },
+    Migration {
+        version: 10,
+        description: "keep an append-only revision history of entries",
+        sql: "CREATE TABLE entry_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entry_id INTEGER NOT NULL,
+            action TEXT NOT NULL
+                CHECK (action IN ('create', 'update', 'delete', 'restore', 'revert')),
+            reason TEXT NOT NULL DEFAULT '',
+            recorded_at INTEGER NOT NULL DEFAULT (unixepoch()),
+            profile_id INTEGER NOT NULL,
+            organization_id INTEGER NOT NULL,
+            place TEXT NOT NULL,
+            date TEXT NOT NULL,
+            start_time TEXT,
+            end_time TEXT,
+            duration_minutes INTEGER NOT NULL,
+            notes TEXT NOT NULL DEFAULT ''
+        );
+        CREATE INDEX entry_revisions_entry ON entry_revisions(entry_id, id);
+
+        CREATE TRIGGER entry_revisions_no_update BEFORE UPDATE ON entry_revisions BEGIN
+            SELECT RAISE(ABORT, 'entry revisions cannot be changed');
+        END;
+
+        -- History goes only when its entry is purged for good.
+        CREATE TRIGGER entry_revisions_no_delete BEFORE DELETE ON entry_revisions
+        WHEN EXISTS (SELECT 1 FROM entries WHERE id = old.entry_id) BEGIN
+            SELECT RAISE(ABORT, 'entry revisions cannot be deleted');
+        END;
+
+        INSERT INTO entry_revisions (entry_id, action, reason, profile_id, organization_id,
+            place, date, start_time, end_time, duration_minutes, notes)
+        SELECT id, 'create', 'logged before revisions were kept', profile_id,
+            organization_id, place, date, start_time, end_time, duration_minutes, notes
+        FROM entries ORDER BY id;",
+    },
+    Migration {
+        version: 11,
+        description: "seal each profile's revisions in a hash chain",
+        // Existing revisions are sealed from Rust once the table exists; see `apply`.
+        sql: "CREATE TABLE entry_chain (
+            profile_id INTEGER NOT NULL,
+            seq INTEGER NOT NULL,
+            entry_id INTEGER NOT NULL,
+            revision_id INTEGER,
+            action TEXT NOT NULL,
+            recorded_at INTEGER NOT NULL,
+            digest TEXT NOT NULL,
+            prev_hash TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            PRIMARY KEY (profile_id, seq)
+        );
+        CREATE INDEX entry_chain_revision ON entry_chain(revision_id);
+
+        CREATE TRIGGER entry_chain_no_update BEFORE UPDATE ON entry_chain BEGIN
+            SELECT RAISE(ABORT, 'the entry chain cannot be changed');
+        END;
+
+        CREATE TRIGGER entry_chain_no_delete BEFORE DELETE ON entry_chain
+        WHEN EXISTS (SELECT 1 FROM profiles WHERE id = old.profile_id) BEGIN
+            SELECT RAISE(ABORT, 'the entry chain cannot be changed');
+        END;",
+    },
+    Migration {
+        version: 12,
+        description: "add organization keys and signed attestations",
+        sql: "CREATE TABLE organization_keys (
+            organization_id INTEGER PRIMARY KEY
+                REFERENCES organizations(id) ON DELETE CASCADE,
+            public_key TEXT NOT NULL,
+            -- NULL for keys trusted from a supervisor's log.
+            secret_key TEXT,
+            created_at INTEGER NOT NULL DEFAULT (unixepoch())
+        );
+
+        CREATE TABLE attestations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+            organization TEXT NOT NULL,
+            public_key TEXT NOT NULL,
+            document TEXT NOT NULL,
+            imported_at INTEGER NOT NULL DEFAULT (unixepoch())
+        );
+
+        CREATE TABLE attested_entries (
+            attestation_id INTEGER NOT NULL REFERENCES attestations(id) ON DELETE CASCADE,
+            entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+            PRIMARY KEY (attestation_id, entry_id)
+        );
+        CREATE INDEX attested_entries_entry ON attested_entries(entry_id);",
+    },
+    Migration {
+        version: 13,
+        description: "add an approval status to entries",
+        // Entries logged so far were final, so they start out approved. Any that need
+        // correcting can be reopened with a reason.
+        sql: "ALTER TABLE entries ADD COLUMN status TEXT NOT NULL DEFAULT 'draft'
+            CHECK (status IN ('draft', 'submitted', 'approved', 'rejected'));
+        ALTER TABLE entries ADD COLUMN status_reason TEXT NOT NULL DEFAULT '';
+        UPDATE entries SET status = 'approved';",
+    },
+    Migration {
+        version: 14,
+        description: "add profile birthdates",
+        sql: "ALTER TABLE profiles ADD COLUMN birthdate TEXT;",
+    },
+    Migration {
+        version: 15,
+        description: "enroll profiles in requirement programs",
+        sql: "CREATE TABLE program_enrollments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            -- The program as JSON, so later edits to its file don't change it.
+            program TEXT NOT NULL,
+            enrolled_at INTEGER NOT NULL DEFAULT (unixepoch()),
+            UNIQUE (profile_id, name)
+        );",
+    },
+    Migration {
+        version: 16,
+        description: "add personal hour goals",
+        sql: "CREATE TABLE goals (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            profile_id INTEGER NOT NULL REFERENCES profiles(id) ON DELETE CASCADE,
+            kind TEXT NOT NULL CHECK (kind IN ('by_date', 'per_month', 'per_organization')),
+            minutes INTEGER NOT NULL CHECK (minutes > 0),
+            organization_id INTEGER REFERENCES organizations(id) ON DELETE CASCADE,
+            start_date TEXT NOT NULL,
+            deadline TEXT,
+            created_at INTEGER NOT NULL DEFAULT (unixepoch())
+        );
+        CREATE INDEX goals_profile ON goals(profile_id);",
+    },
+    Migration {
+        version: 17,
+        description: "add per-profile reporting periods",
+        // JSON; calendar years when NULL.
+        sql: "ALTER TABLE profiles ADD COLUMN reporting_periods TEXT;",
+    },
+    Migration {
+        version: 18,
+        description: "reject entry dates that are not YYYY-MM-DD",
+        // `date()` rolls impossible days over and gives NULL for anything unreadable, so
+        // only a real, zero-padded date comes back unchanged. Rows already stored are
+        // repaired by migration 21.
+        sql: "CREATE TRIGGER entries_date_insert BEFORE INSERT ON entries
+        WHEN new.date IS NOT date(new.date) BEGIN
+            SELECT RAISE(ABORT, 'entry date must be YYYY-MM-DD');
+        END;
+
+        CREATE TRIGGER entries_date_update BEFORE UPDATE OF date ON entries
+        WHEN new.date IS NOT date(new.date) BEGIN
+            SELECT RAISE(ABORT, 'entry date must be YYYY-MM-DD');
+        END;",
+    },
+    Migration {
+        version: 19,
+        description: "tag entries with causes",
+        sql: "CREATE TABLE tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            name_key TEXT NOT NULL UNIQUE,
+            -- UN Sustainable Development Goal the cause serves, if any.
+            sdg INTEGER CHECK (sdg BETWEEN 1 AND 17)
+        );
+
+        CREATE TABLE entry_tags (
+            entry_id INTEGER NOT NULL REFERENCES entries(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (entry_id, tag_id)
+        );
+
+        CREATE INDEX entry_tags_tag ON entry_tags(tag_id);",
+    },
+    Migration {
+        version: 20,
+        description: "count wrong PINs",
+        sql: "ALTER TABLE profiles ADD COLUMN pin_failures INTEGER NOT NULL DEFAULT 0;
+        ALTER TABLE profiles ADD COLUMN pin_failed_at INTEGER;",
+    },
+    Migration {
+        version: 21,
+        description: "repair entry dates stored before they were checked",
+        // Dates that only lack zero-padding or use slashes are rewritten. The rest can't be
+        // read, so their entries are rejected with a reason asking for the date. Either
+        // way the change gets a revision, which `apply` seals.
+        sql: "CREATE TEMP TABLE repaired AS
+            SELECT id, normalize_date(date) AS date FROM entries WHERE date IS NOT date(date);
+
+        UPDATE entries SET date = (SELECT r.date FROM temp.repaired r WHERE r.id = entries.id)
+        WHERE id IN (SELECT id FROM temp.repaired WHERE date IS NOT NULL);
+
+        UPDATE entries SET status = 'rejected',
+            status_reason = 'the date \"' || date || '\" could not be read; correct it'
+        WHERE id IN (SELECT id FROM temp.repaired WHERE date IS NULL);
+
+        INSERT INTO entry_revisions (entry_id, action, reason, profile_id, organization_id,
+            place, date, start_time, end_time, duration_minutes, notes)
+        SELECT e.id, 'update',
+            CASE WHEN r.date IS NULL THEN 'date could not be read'
+                ELSE 'date written as YYYY-MM-DD' END,
+            e.profile_id, e.organization_id, e.place, e.date, e.start_time, e.end_time,
+            e.duration_minutes, e.notes
+        FROM entries e JOIN temp.repaired r ON r.id = e.id ORDER BY e.id;
+
+        DROP TABLE temp.repaired;",
+    },
+    Migration {
+        version: 22,
+        description: "seal the organization of each entry",
+        // Records sealed so far keep their first digest, which left the organization out.
+        // Every entry gets a revision, which `apply` seals with the organization in it.
+        sql: "ALTER TABLE entry_chain ADD COLUMN digest_version INTEGER NOT NULL DEFAULT 1;
+
+        INSERT INTO entry_revisions (entry_id, action, reason, profile_id, organization_id,
+            place, date, start_time, end_time, duration_minutes, notes)
+        SELECT id, 'update', 'sealed with its organization', profile_id, organization_id,
+            place, date, start_time, end_time, duration_minutes, notes
+        FROM entries ORDER BY id;",
+    },
+    Migration {
+        version: 23,
+        description: "keep and seal the status of each entry revision",
+        // Revisions recorded so far can't be changed, so their status stays unknown.
+        // Every entry gets a revision holding its status, which `apply` seals.
+        sql: "ALTER TABLE entry_revisions ADD COLUMN status TEXT;
+        ALTER TABLE entry_revisions ADD COLUMN status_reason TEXT NOT NULL DEFAULT '';
+
+        INSERT INTO entry_revisions (entry_id, action, reason, profile_id, organization_id,
+            place, date, start_time, end_time, duration_minutes, notes, status, status_reason)
+        SELECT id, 'update', 'sealed with its status', profile_id, organization_id, place,
+            date, start_time, end_time, duration_minutes, notes, status, status_reason
+        FROM entries ORDER BY id;",
+    },
+    Migration {
+        version: 24,
+        description: "let rejected entries keep a date that could not be read",
+        // Archives from before dates were checked can hold such entries. A restore keeps
+        // them rejected, the way migration 21 kept the rows already stored.
+        sql: "DROP TRIGGER entries_date_insert;
+        CREATE TRIGGER entries_date_insert BEFORE INSERT ON entries
+        WHEN new.date IS NOT date(new.date) AND new.status IS NOT 'rejected' BEGIN
+            SELECT RAISE(ABORT, 'entry date must be YYYY-MM-DD');
+        END;",
+    },
+    Migration {
+        version: 25,
+        description: "reject legacy entries whose hours were not positive",
+        // Databases that passed migration 4 before it noted such entries have none noted.
+        // Each entry rejected gets a revision, which `apply` seals.
+        sql: "CREATE TABLE IF NOT EXISTS legacy_rejections (
+            entry_id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL
+        );
+
+        UPDATE entries SET status = 'rejected',
+            status_reason = (SELECT reason FROM legacy_rejections WHERE entry_id = entries.id)
+        WHERE id IN (SELECT entry_id FROM legacy_rejections);
+
+        INSERT INTO entry_revisions (entry_id, action, reason, profile_id, organization_id,
+            place, date, start_time, end_time, duration_minutes, notes, status, status_reason)
+        SELECT e.id, 'update', 'hours were not positive', e.profile_id, e.organization_id,
+            e.place, e.date, e.start_time, e.end_time, e.duration_minutes, e.notes, e.status,
+            e.status_reason
+        FROM entries e JOIN legacy_rejections r ON r.entry_id = e.id ORDER BY e.id;
+
+        DROP TABLE legacy_rejections;",
+    },
+];
+
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+pub fn current_version(conn: &Connection) -> Result<u32> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// Brings the schema up to [`latest_version`], returning the version it started from.
+pub fn run(conn: &mut Connection) -> Result<u32> {
+    let from = current_version(conn)?;
+    let latest = latest_version();
+    if from > latest {
+        return Err(Error::Invalid(format!(
+            "database schema version {from} is newer than this app supports ({latest})"
+        )));
+    }
+    conn.create_scalar_function(
+        "name_key",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(organizations::name_key(&ctx.get::<String>(0)?)),
+    )?;
+    conn.create_scalar_function(
+        "normalize_date",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| Ok(dates::normalize(&ctx.get::<String>(0)?).ok()),
+    )?;
+    let foreign_keys: bool = conn.pragma_query_value(None, "foreign_keys", |row| row.get(0))?;
+    conn.pragma_update(None, "foreign_keys", false)?;
+    let applied = apply(conn, from);
+    conn.pragma_update(None, "foreign_keys", foreign_keys)?;
+    applied?;
+    Ok(from)
+}
+
+fn apply(conn: &mut Connection, from: u32) -> Result<()> {
+    // Revisions after this one are recorded by the migrations below, or predate the hash
+    // chain, and are sealed with the last migration. Any others outside the chain were
+    // slipped in and must stay unsealed for `chain::verify` to report.
+    let recorded: i64 = if from >= CHAIN_VERSION {
+        conn.query_row(
+            "SELECT COALESCE(max(id), 0) FROM entry_revisions",
+            [],
+            |row| row.get(0),
+        )?
+    } else {
+        0
+    };
+    for migration in MIGRATIONS.iter().filter(|m| m.version > from) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        if migration.version == latest_version() {
+            chain::seal_unsealed(&tx, recorded)?;
+        }
+        let broken: bool =
+            tx.query_row("SELECT EXISTS(SELECT 1 FROM pragma_foreign_key_check)", [], |row| {
+                row.get(0)
+            })?;
+        if broken {
+            return Err(Error::Invalid(format!(
+                "migration {} left dangling foreign keys",
+                migration.version
+            )));
+        }
+        tx.pragma_update(None, "user_version", migration.version)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
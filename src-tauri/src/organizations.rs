@@ -0,0 +1,379 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::EntryStatus;
+use crate::entries::{self, VolunteerEntry};
+use crate::error::{Error, Result};
+use crate::revisions::{self, Action};
+use crate::{timer, trash};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Organization {
+    pub id: i64,
+    pub name: String,
+    pub contact_name: String,
+    pub email: String,
+    pub phone: String,
+    pub address: String,
+    pub notes: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrganizationInput {
+    pub name: String,
+    pub contact_name: String,
+    pub email: String,
+    pub phone: String,
+    pub address: String,
+    pub notes: String,
+}
+
+/// What a [`merge`] changed, so [`unmerge`] can put it back.
+#[derive(Debug, Clone, Serialize)]
+pub struct Merge {
+    /// The kept organization after the merge.
+    pub organization: Organization,
+    /// The kept organization before the merge.
+    pub kept: Organization,
+    pub merged: Organization,
+    pub entry_ids: Vec<i64>,
+    pub goal_ids: Vec<i64>,
+}
+
+const COLUMNS: &str = "id, name, contact_name, email, phone, address, notes";
+
+impl Organization {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            contact_name: row.get("contact_name")?,
+            email: row.get("email")?,
+            phone: row.get("phone")?,
+            address: row.get("address")?,
+            notes: row.get("notes")?,
+        })
+    }
+}
+
+impl From<&Organization> for OrganizationInput {
+    fn from(organization: &Organization) -> Self {
+        Self {
+            name: organization.name.clone(),
+            contact_name: organization.contact_name.clone(),
+            email: organization.email.clone(),
+            phone: organization.phone.clone(),
+            address: organization.address.clone(),
+            notes: organization.notes.clone(),
+        }
+    }
+}
+
+/// The identity of an organization name: case-folded with whitespace collapsed, so
+/// "Food Bank", "food bank" and " Food  Bank " are the same place.
+pub fn name_key(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Organization>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM organizations ORDER BY name_key"
+    ))?;
+    let organizations = stmt
+        .query_map([], Organization::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(organizations)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Organization> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM organizations WHERE id = ?1"),
+        [id],
+        Organization::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("organization"))
+}
+
+pub fn find_by_name(conn: &Connection, name: &str) -> Result<Option<Organization>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {COLUMNS} FROM organizations WHERE name_key = ?1"),
+            [name_key(name)],
+            Organization::from_row,
+        )
+        .optional()?)
+}
+
+/// Resolves a free-text place to its organization, creating one on first use.
+pub fn find_or_create(conn: &Connection, name: &str) -> Result<Organization> {
+    match find_by_name(conn, name)? {
+        Some(organization) => Ok(organization),
+        None => create(
+            conn,
+            &OrganizationInput {
+                name: name.to_string(),
+                ..Default::default()
+            },
+        ),
+    }
+}
+
+pub fn create(conn: &Connection, input: &OrganizationInput) -> Result<Organization> {
+    let name = validate_name(conn, &input.name, None)?;
+    conn.execute(
+        "INSERT INTO organizations (name, name_key, contact_name, email, phone, address, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            name,
+            name_key(&name),
+            input.contact_name.trim(),
+            input.email.trim(),
+            input.phone.trim(),
+            input.address.trim(),
+            input.notes,
+        ],
+    )?;
+    get(conn, conn.last_insert_rowid())
+}
+
+/// Updates the details; a rename is carried through to the entries' `place`, with a
+/// revision for each. Approved entries are [locked](crate::approval), so an organization
+/// with any can't be renamed.
+pub fn update(conn: &mut Connection, id: i64, input: &OrganizationInput) -> Result<Organization> {
+    let name = validate_name(conn, &input.name, Some(id))?;
+    let tx = conn.transaction()?;
+    let changed = tx.execute(
+        "UPDATE organizations SET name = ?1, name_key = ?2, contact_name = ?3, email = ?4,
+         phone = ?5, address = ?6, notes = ?7 WHERE id = ?8",
+        params![
+            name,
+            name_key(&name),
+            input.contact_name.trim(),
+            input.email.trim(),
+            input.phone.trim(),
+            input.address.trim(),
+            input.notes,
+            id,
+        ],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("organization"));
+    }
+    let reason = format!("organization renamed to {name}");
+    for entry in entries_of(&tx, id)? {
+        if entry.place != name {
+            move_entry(&tx, entry, id, &name, &reason)?;
+        }
+    }
+    tx.commit()?;
+    get(conn, id)
+}
+
+/// Deletes an organization that no entry outside the trash uses. Its entries in the trash
+/// are purged with it, since they could no longer be restored.
+pub fn delete(conn: &mut Connection, id: i64) -> Result<()> {
+    let in_use: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM entries WHERE organization_id = ?1 AND deleted_at IS NULL)",
+        [id],
+        |row| row.get(0),
+    )?;
+    if in_use {
+        return Err(Error::Invalid(
+            "organization still has entries; merge it into another instead".into(),
+        ));
+    }
+    let tx = conn.transaction()?;
+    let trashed = tx
+        .prepare("SELECT id FROM entries WHERE organization_id = ?1")?
+        .query_map([id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    for entry_id in trashed {
+        trash::purge_entry(&tx, entry_id, timer::now())?;
+    }
+    let changed = tx.execute("DELETE FROM organizations WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(Error::NotFound("organization"));
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Moves every entry of `merged_id` onto `kept_id`, with a revision for each, and removes
+/// `merged_id`. Contact details missing on the kept organization are taken from the
+/// merged one. Fails if `merged_id` has approved entries, which are locked.
+pub fn merge(conn: &mut Connection, kept_id: i64, merged_id: i64) -> Result<Merge> {
+    if kept_id == merged_id {
+        return Err(Error::Invalid("cannot merge an organization into itself".into()));
+    }
+    let kept = get(conn, kept_id)?;
+    let merged = get(conn, merged_id)?;
+    let pick = |a: &str, b: &str| if a.is_empty() { b.to_string() } else { a.to_string() };
+    let notes = match (kept.notes.is_empty(), merged.notes.is_empty()) {
+        (_, true) => kept.notes.clone(),
+        (true, false) => merged.notes.clone(),
+        (false, false) => format!("{}\n{}", kept.notes, merged.notes),
+    };
+
+    let tx = conn.transaction()?;
+    let reason = format!("organization {} merged into {}", merged.name, kept.name);
+    let mut entry_ids = Vec::new();
+    for entry in entries_of(&tx, merged_id)? {
+        entry_ids.push(entry.id);
+        move_entry(&tx, entry, kept_id, &kept.name, &reason)?;
+    }
+    let goal_ids = tx
+        .prepare("SELECT id FROM goals WHERE organization_id = ?1 ORDER BY id")?
+        .query_map([merged_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<i64>>>()?;
+    tx.execute(
+        "UPDATE organizations SET contact_name = ?1, email = ?2, phone = ?3, address = ?4,
+         notes = ?5 WHERE id = ?6",
+        params![
+            pick(&kept.contact_name, &merged.contact_name),
+            pick(&kept.email, &merged.email),
+            pick(&kept.phone, &merged.phone),
+            pick(&kept.address, &merged.address),
+            notes,
+            kept_id,
+        ],
+    )?;
+    tx.execute(
+        "UPDATE goals SET organization_id = ?1 WHERE organization_id = ?2",
+        params![kept_id, merged_id],
+    )?;
+    tx.execute("DELETE FROM organizations WHERE id = ?1", [merged_id])?;
+    tx.commit()?;
+    Ok(Merge {
+        organization: get(conn, kept_id)?,
+        kept,
+        merged,
+        entry_ids,
+        goal_ids,
+    })
+}
+
+/// Undoes a [`merge`]: the merged organization comes back under its id with its entries
+/// and goals, and the kept one gets its details from before. A signing key the merged
+/// organization had is not brought back.
+pub fn unmerge(conn: &mut Connection, merge: &Merge) -> Result<Organization> {
+    let Merge { kept, merged, .. } = merge;
+    let name = validate_name(conn, &merged.name, None)?;
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO organizations (id, name, name_key, contact_name, email, phone, address,
+         notes) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            merged.id,
+            name,
+            name_key(&name),
+            merged.contact_name,
+            merged.email,
+            merged.phone,
+            merged.address,
+            merged.notes,
+        ],
+    )?;
+    tx.execute(
+        "UPDATE organizations SET contact_name = ?1, email = ?2, phone = ?3, address = ?4,
+         notes = ?5 WHERE id = ?6",
+        params![
+            kept.contact_name,
+            kept.email,
+            kept.phone,
+            kept.address,
+            kept.notes,
+            kept.id,
+        ],
+    )?;
+    for entry in entries_of(&tx, kept.id)? {
+        if merge.entry_ids.contains(&entry.id) {
+            move_entry(&tx, entry, merged.id, &name, "undo")?;
+        }
+    }
+    for goal_id in &merge.goal_ids {
+        tx.execute(
+            "UPDATE goals SET organization_id = ?1 WHERE id = ?2 AND organization_id = ?3",
+            params![merged.id, goal_id, kept.id],
+        )?;
+    }
+    tx.commit()?;
+    get(conn, merged.id)
+}
+
+/// The profiles with entries at any of `ids`, trashed ones included, which renaming or
+/// merging those organizations changes.
+pub fn profiles_using(conn: &Connection, ids: &[i64]) -> Result<Vec<i64>> {
+    let mut stmt =
+        conn.prepare("SELECT DISTINCT profile_id FROM entries WHERE organization_id = ?1")?;
+    let mut profile_ids = Vec::new();
+    for id in ids {
+        for profile_id in stmt.query_map([id], |row| row.get(0))? {
+            let profile_id = profile_id?;
+            if !profile_ids.contains(&profile_id) {
+                profile_ids.push(profile_id);
+            }
+        }
+    }
+    Ok(profile_ids)
+}
+
+/// Every entry of an organization, the trashed ones included.
+fn entries_of(conn: &Connection, id: i64) -> Result<Vec<VolunteerEntry>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM entries e WHERE e.organization_id = ?1 ORDER BY e.id",
+        entries::columns_of("e")
+    ))?;
+    let entries = stmt
+        .query_map([id], VolunteerEntry::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(entries)
+}
+
+/// Files `entry` under another organization and records why. Approved entries stay.
+fn move_entry(
+    conn: &Connection,
+    entry: VolunteerEntry,
+    organization_id: i64,
+    name: &str,
+    reason: &str,
+) -> Result<()> {
+    if entry.status == EntryStatus::Approved {
+        return Err(Error::Invalid(format!(
+            "{} has approved entries, which cannot be moved; reopen them first",
+            entry.place
+        )));
+    }
+    conn.execute(
+        "UPDATE entries SET organization_id = ?1, place = ?2 WHERE id = ?3",
+        params![organization_id, name, entry.id],
+    )?;
+    let moved = VolunteerEntry {
+        organization_id,
+        place: name.to_string(),
+        ..entry
+    };
+    revisions::record(conn, &moved, Action::Update, reason)
+}
+
+fn validate_name(conn: &Connection, name: &str, id: Option<i64>) -> Result<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err(Error::Invalid("organization name is required".into()));
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM organizations WHERE name_key = ?1 AND id IS NOT ?2)",
+        params![name_key(&name), id],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(Error::Invalid(format!(
+            "an organization named \"{name}\" already exists"
+        )));
+    }
+    Ok(name)
+}
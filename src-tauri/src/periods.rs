@@ -0,0 +1,225 @@
+//! Reporting periods: how a profile's hours are split up for totals, filters and reports.
+//! Calendar years are the default; a profile can count in school years, fiscal years, or
+//! terms of its own instead.
+
+use std::collections::HashSet;
+
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::dates;
+use crate::entries::DATE_FORMAT;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PeriodScheme {
+    #[default]
+    Calendar,
+    /// Years from the first of `start_month`, labelled by the years they span, "2024-25".
+    SchoolYear { start_month: u32 },
+    /// Years from the first of `start_month`, labelled by the year they end in, "FY2025".
+    FiscalYear { start_month: u32 },
+    /// Named periods such as semesters. They may leave gaps but may not overlap.
+    Terms { terms: Vec<Term> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Term {
+    pub name: String,
+    /// First and last day, inclusive, `YYYY-MM-DD`.
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Period {
+    pub label: String,
+    /// First and last day, inclusive, `YYYY-MM-DD`. `from` also identifies the period.
+    pub from: String,
+    pub to: String,
+}
+
+fn format_date(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+impl PeriodScheme {
+    /// The period `day` falls in; `None` for a day outside every term.
+    pub fn period_of(&self, day: NaiveDate) -> Option<Period> {
+        let start_month = match self {
+            PeriodScheme::Calendar => 1,
+            PeriodScheme::SchoolYear { start_month } | PeriodScheme::FiscalYear { start_month } => {
+                *start_month
+            }
+            PeriodScheme::Terms { terms } => {
+                let day = format_date(day);
+                return terms
+                    .iter()
+                    .find(|term| term.from <= day && day <= term.to)
+                    .map(|term| Period {
+                        label: term.name.clone(),
+                        from: term.from.clone(),
+                        to: term.to.clone(),
+                    });
+            }
+        };
+        let year = if day.month() >= start_month {
+            day.year()
+        } else {
+            day.year() - 1
+        };
+        let from = NaiveDate::from_ymd_opt(year, start_month, 1)?;
+        let to = from.checked_add_months(Months::new(12))?.pred_opt()?;
+        let label = match self {
+            PeriodScheme::SchoolYear { .. } if from.year() != to.year() => {
+                format!("{}-{:02}", from.year(), to.year() % 100)
+            }
+            PeriodScheme::FiscalYear { .. } => format!("FY{}", to.year()),
+            _ => from.year().to_string(),
+        };
+        Some(Period {
+            label,
+            from: format_date(from),
+            to: format_date(to),
+        })
+    }
+
+    /// Checks the scheme and puts it in its stored form: trimmed names, and terms in order.
+    fn normalized(&self) -> Result<Self> {
+        match self {
+            PeriodScheme::Calendar => Ok(PeriodScheme::Calendar),
+            PeriodScheme::SchoolYear { start_month } | PeriodScheme::FiscalYear { start_month }
+                if !(1..=12).contains(start_month) =>
+            {
+                Err(Error::Invalid(format!(
+                    "{start_month} is not a month (expected 1 to 12)"
+                )))
+            }
+            PeriodScheme::SchoolYear { .. } | PeriodScheme::FiscalYear { .. } => Ok(self.clone()),
+            PeriodScheme::Terms { terms } => {
+                if terms.is_empty() {
+                    return Err(Error::Invalid("add at least one term".into()));
+                }
+                let mut names = HashSet::new();
+                let mut normalized = Vec::with_capacity(terms.len());
+                for term in terms {
+                    let name = term.name.trim();
+                    if name.is_empty() {
+                        return Err(Error::Invalid("every term needs a name".into()));
+                    }
+                    if !names.insert(name.to_lowercase()) {
+                        return Err(Error::Invalid(format!(
+                            "there are two terms named \"{name}\""
+                        )));
+                    }
+                    let (from, to) = (dates::parse(&term.from)?, dates::parse(&term.to)?);
+                    if to < from {
+                        return Err(Error::Invalid(format!("{name} ends before it starts")));
+                    }
+                    normalized.push(Term {
+                        name: name.to_string(),
+                        from: format_date(from),
+                        to: format_date(to),
+                    });
+                }
+                normalized.sort_by(|a, b| a.from.cmp(&b.from));
+                if let Some(pair) = normalized
+                    .windows(2)
+                    .find(|pair| pair[1].from <= pair[0].to)
+                {
+                    return Err(Error::Invalid(format!(
+                        "{} and {} overlap",
+                        pair[0].name, pair[1].name
+                    )));
+                }
+                Ok(PeriodScheme::Terms { terms: normalized })
+            }
+        }
+    }
+}
+
+/// The profile's reporting periods.
+pub fn scheme(conn: &Connection, profile_id: i64) -> Result<PeriodScheme> {
+    let stored: Option<String> = conn
+        .query_row(
+            "SELECT reporting_periods FROM profiles WHERE id = ?1 AND deleted_at IS NULL",
+            [profile_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or(Error::NotFound("profile"))?;
+    match stored {
+        Some(json) => Ok(serde_json::from_str(&json)?),
+        None => Ok(PeriodScheme::Calendar),
+    }
+}
+
+pub fn set_scheme(
+    conn: &Connection,
+    profile_id: i64,
+    scheme: &PeriodScheme,
+) -> Result<PeriodScheme> {
+    let scheme = scheme.normalized()?;
+    let stored = match scheme {
+        PeriodScheme::Calendar => None,
+        _ => Some(serde_json::to_string(&scheme)?),
+    };
+    let changed = conn.execute(
+        "UPDATE profiles SET reporting_periods = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+        params![stored, profile_id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("profile"));
+    }
+    Ok(scheme)
+}
+
+/// The periods to choose from, newest first: every term, or the years that have entries.
+pub fn list(conn: &Connection, profile_id: i64) -> Result<Vec<Period>> {
+    let scheme = scheme(conn, profile_id)?;
+    if let PeriodScheme::Terms { terms } = &scheme {
+        return Ok(terms
+            .iter()
+            .rev()
+            .map(|term| Period {
+                label: term.name.clone(),
+                from: term.from.clone(),
+                to: term.to.clone(),
+            })
+            .collect());
+    }
+    // Every period starts on the first of a month, so months are enough to find them.
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT substr(date, 1, 7) || '-01' AS month
+         FROM entries WHERE profile_id = ?1 AND deleted_at IS NULL ORDER BY month DESC",
+    )?;
+    let months = stmt
+        .query_map([profile_id], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut periods: Vec<Period> = Vec::new();
+    for month in months {
+        let Some(period) = scheme.period_of(dates::parse(&month)?) else {
+            continue;
+        };
+        if periods.last().is_none_or(|last| last.from != period.from) {
+            periods.push(period);
+        }
+    }
+    Ok(periods)
+}
+
+/// The period named `query`, or the one containing `query` when it is a date.
+pub fn find(conn: &Connection, profile_id: i64, query: &str) -> Result<Period> {
+    let query = query.trim();
+    if let Ok(day) = dates::parse(query) {
+        return scheme(conn, profile_id)?
+            .period_of(day)
+            .ok_or(Error::NotFound("period"));
+    }
+    list(conn, profile_id)?
+        .into_iter()
+        .find(|period| period.label.eq_ignore_ascii_case(query))
+        .ok_or(Error::NotFound("period"))
+}
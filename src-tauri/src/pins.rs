@@ -0,0 +1,211 @@
+//! Optional per-profile PINs, so one family member can't read or change another's log on
+//! a shared device. PINs are stored as Argon2 hashes. Which profiles are unlocked is
+//! process state, so every profile with a PIN starts out locked. Wrong PINs are counted
+//! in the database, and after a few of them each further try has to wait twice as long
+//! as the one before, whether it comes from the app or the command line.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand_core::OsRng;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use crate::error::{Error, Result};
+use crate::timer;
+
+/// How long an unlocked profile stays unlocked without being used.
+pub const IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// Wrong PINs allowed in a row before tries are slowed down.
+pub const FREE_ATTEMPTS: u32 = 3;
+
+/// Seconds to wait after the first wrong PIN past [`FREE_ATTEMPTS`]; each one after
+/// doubles it, up to an hour.
+const FIRST_DELAY: i64 = 30;
+const MAX_DELAY: i64 = 60 * 60;
+
+/// Seconds to wait after the last of `failures` wrong PINs in a row.
+fn delay(failures: u32) -> i64 {
+    match failures.checked_sub(FREE_ATTEMPTS) {
+        None => 0,
+        Some(over) => (FIRST_DELAY << over.min(10)).min(MAX_DELAY),
+    }
+}
+
+fn stored_hash(conn: &Connection, profile_id: i64) -> Result<Option<String>> {
+    conn.query_row(
+        "SELECT pin_hash FROM profiles WHERE id = ?1",
+        [profile_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or(Error::NotFound("profile"))
+}
+
+fn validate(pin: &str) -> Result<()> {
+    if !(4..=12).contains(&pin.len()) || !pin.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(Error::Invalid("a PIN is 4 to 12 digits".into()));
+    }
+    Ok(())
+}
+
+fn hash(pin: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| Error::Invalid(format!("cannot hash PIN: {e}")))
+}
+
+fn matches(hash: &str, pin: &str) -> Result<bool> {
+    let hash = PasswordHash::new(hash)
+        .map_err(|e| Error::Invalid(format!("stored PIN hash is unreadable: {e}")))?;
+    Ok(Argon2::default().verify_password(pin.as_bytes(), &hash).is_ok())
+}
+
+/// Succeeds when the profile has no PIN or `pin` is its PIN. Fails with
+/// [`Error::PinBackoff`] without trying `pin` while the profile is waiting out earlier
+/// wrong PINs.
+pub fn check(conn: &Connection, profile_id: i64, pin: Option<&str>) -> Result<()> {
+    let (hash, pin) = match (stored_hash(conn, profile_id)?, pin) {
+        (None, _) => return Ok(()),
+        (Some(_), None) => return Err(Error::ProfileLocked),
+        (Some(hash), Some(pin)) => (hash, pin),
+    };
+    let (failures, failed_at): (u32, Option<i64>) = conn.query_row(
+        "SELECT pin_failures, pin_failed_at FROM profiles WHERE id = ?1",
+        [profile_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let now = timer::now();
+    let wait = failed_at.map_or(0, |at| at + delay(failures) - now);
+    if wait > 0 {
+        return Err(Error::PinBackoff(wait));
+    }
+    if matches(&hash, pin)? {
+        conn.execute(
+            "UPDATE profiles SET pin_failures = 0, pin_failed_at = NULL WHERE id = ?1",
+            [profile_id],
+        )?;
+        return Ok(());
+    }
+    conn.execute(
+        "UPDATE profiles SET pin_failures = pin_failures + 1, pin_failed_at = ?1
+         WHERE id = ?2",
+        params![now, profile_id],
+    )?;
+    Err(Error::WrongPin)
+}
+
+/// Sets, changes or (with `new` as `None`) removes a profile's PIN. Changing or removing
+/// one needs the `current` PIN.
+pub fn set(
+    conn: &Connection,
+    profile_id: i64,
+    current: Option<&str>,
+    new: Option<&str>,
+) -> Result<()> {
+    check(conn, profile_id, current)?;
+    let hash = match new {
+        Some(pin) => {
+            validate(pin)?;
+            Some(hash(pin)?)
+        }
+        None => None,
+    };
+    conn.execute(
+        "UPDATE profiles SET pin_hash = ?1 WHERE id = ?2",
+        params![hash, profile_id],
+    )?;
+    Ok(())
+}
+
+/// The profiles unlocked in this process and when each was last used, managed as Tauri
+/// state next to [`crate::db::Db`].
+pub struct Sessions {
+    idle_timeout: Duration,
+    last_used: Mutex<HashMap<i64, Instant>>,
+}
+
+impl Default for Sessions {
+    fn default() -> Self {
+        Self::new(IDLE_TIMEOUT)
+    }
+}
+
+impl Sessions {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout,
+            last_used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn last_used(&self) -> MutexGuard<'_, HashMap<i64, Instant>> {
+        self.last_used.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn unlock(&self, conn: &Connection, profile_id: i64, pin: &str) -> Result<()> {
+        check(conn, profile_id, Some(pin))?;
+        self.last_used().insert(profile_id, Instant::now());
+        Ok(())
+    }
+
+    pub fn lock(&self, profile_id: i64) {
+        self.last_used().remove(&profile_id);
+    }
+
+    pub fn lock_all(&self) {
+        self.last_used().clear();
+    }
+
+    /// Whether the profile has a PIN and hasn't been unlocked, or has sat idle too long.
+    pub fn is_locked(&self, conn: &Connection, profile_id: i64) -> Result<bool> {
+        if stored_hash(conn, profile_id)?.is_none() {
+            return Ok(false);
+        }
+        let mut last_used = self.last_used();
+        let fresh = last_used
+            .get(&profile_id)
+            .is_some_and(|at| at.elapsed() < self.idle_timeout);
+        if !fresh {
+            last_used.remove(&profile_id);
+        }
+        Ok(!fresh)
+    }
+
+    /// Fails with [`Error::ProfileLocked`] for a locked profile. Passing the check counts
+    /// as activity and restarts the idle timeout.
+    pub fn require(&self, conn: &Connection, profile_id: i64) -> Result<()> {
+        if self.is_locked(conn, profile_id)? {
+            return Err(Error::ProfileLocked);
+        }
+        if let Some(at) = self.last_used().get_mut(&profile_id) {
+            *at = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// [`Sessions::require`] for every profile, for queries that span all of them.
+    pub fn require_all(&self, conn: &Connection) -> Result<()> {
+        let mut stmt = conn.prepare("SELECT id FROM profiles")?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        self.require_each(conn, ids)
+    }
+
+    /// [`Sessions::require`] for each of `profile_ids`.
+    pub fn require_each(
+        &self,
+        conn: &Connection,
+        profile_ids: impl IntoIterator<Item = i64>,
+    ) -> Result<()> {
+        profile_ids
+            .into_iter()
+            .try_for_each(|id| self.require(conn, id))
+    }
+}
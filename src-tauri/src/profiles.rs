@@ -0,0 +1,128 @@
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::dates;
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub id: i64,
+    pub name: String,
+    /// Whether the profile is protected by a PIN; the hash itself never leaves the backend.
+    #[serde(default)]
+    pub has_pin: bool,
+    /// `YYYY-MM-DD`, for age-based awards such as the [PVSA](crate::pvsa).
+    #[serde(default)]
+    pub birthdate: Option<String>,
+}
+
+const COLUMNS: &str = "id, name, pin_hash IS NOT NULL AS has_pin, birthdate";
+
+impl Profile {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            has_pin: row.get("has_pin")?,
+            birthdate: row.get("birthdate")?,
+        })
+    }
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Profile>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM profiles WHERE deleted_at IS NULL ORDER BY name"
+    ))?;
+    let profiles = stmt
+        .query_map([], Profile::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(profiles)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Profile> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM profiles WHERE id = ?1 AND deleted_at IS NULL"),
+        [id],
+        Profile::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("profile"))
+}
+
+pub fn create(conn: &Connection, name: &str) -> Result<Profile> {
+    let name = validate_name(conn, name, None)?;
+    conn.execute("INSERT INTO profiles (name) VALUES (?1)", [&name])?;
+    get(conn, conn.last_insert_rowid())
+}
+
+pub fn rename(conn: &Connection, id: i64, name: &str) -> Result<Profile> {
+    let name = validate_name(conn, name, Some(id))?;
+    let changed = conn.execute(
+        "UPDATE profiles SET name = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+        params![name, id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("profile"));
+    }
+    get(conn, id)
+}
+
+/// Sets or clears the profile's birthdate.
+pub fn set_birthdate(conn: &Connection, id: i64, birthdate: Option<&str>) -> Result<Profile> {
+    let birthdate = birthdate
+        .map(str::trim)
+        .filter(|b| !b.is_empty())
+        .map(dates::normalize)
+        .transpose()?;
+    let changed = conn.execute(
+        "UPDATE profiles SET birthdate = ?1 WHERE id = ?2 AND deleted_at IS NULL",
+        params![birthdate, id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("profile"));
+    }
+    get(conn, id)
+}
+
+/// Moves the profile, and with it all of its entries, to the [trash](crate::trash). A
+/// running timer is discarded.
+pub fn delete(conn: &mut Connection, id: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+    let changed = tx.execute(
+        "UPDATE profiles SET deleted_at = unixepoch() WHERE id = ?1 AND deleted_at IS NULL",
+        [id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("profile"));
+    }
+    tx.execute(
+        "DELETE FROM timer_sessions WHERE profile_id = ?1 AND ended_at IS NULL",
+        [id],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn validate_name(conn: &Connection, name: &str, id: Option<i64>) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Invalid("profile name is required".into()));
+    }
+    // Names stay taken while a profile is in the trash, so it can always be restored.
+    let taken: Option<bool> = conn
+        .query_row(
+            "SELECT deleted_at IS NOT NULL FROM profiles WHERE name = ?1 AND id IS NOT ?2",
+            params![name, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    match taken {
+        Some(false) => Err(Error::Invalid(format!(
+            "a profile named \"{name}\" already exists"
+        ))),
+        Some(true) => Err(Error::Invalid(format!(
+            "a profile named \"{name}\" is in the trash; restore or purge it first"
+        ))),
+        None => Ok(name.to_string()),
+    }
+}
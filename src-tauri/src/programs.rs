@@ -0,0 +1,374 @@
+//! Service-hour requirement programs, such as a school's "40 hours this year, at two or
+//! more organizations, no more than 10 of them at family-run places". A program is a list
+//! of rules over a date range, written as TOML or JSON:
+//!
+//! ```toml
+//! name = "Honor society service hours"
+//! from = "2024-09-01"
+//! deadline = "2025-05-31"
+//! approved_only = true
+//!
+//! [[rules]]
+//! kind = "min_hours"
+//! hours = 40
+//!
+//! [[rules]]
+//! kind = "min_organizations"
+//! count = 2
+//!
+//! [[rules]]
+//! kind = "max_hours_from"
+//! hours = 10
+//! organizations = ["Smith Family Farm", "Lee's Bakery"]
+//! ```
+//!
+//! A profile enrolls in a program by keeping a copy of it, and its progress is worked out
+//! from its entries whenever it is asked for.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::EntryStatus;
+use crate::dates;
+use crate::entries::{self, EntryFilter};
+use crate::error::{Error, Result};
+use crate::organizations;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// First day that counts, `YYYY-MM-DD`; all earlier entries count when left out.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// Last day that counts, `YYYY-MM-DD`.
+    #[serde(default)]
+    pub deadline: Option<String>,
+    /// Count only [approved](crate::approval) entries.
+    #[serde(default)]
+    pub approved_only: bool,
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Rule {
+    /// At least this many counted hours.
+    MinHours { hours: f64 },
+    /// Time at this many different organizations or more.
+    MinOrganizations { count: u32 },
+    /// No more than this many hours count from the listed organizations together; the
+    /// rest of their time is left out of the counted hours. An organization may be listed
+    /// by one such rule only.
+    MaxHoursFrom {
+        hours: f64,
+        organizations: Vec<String>,
+    },
+    /// At least this many entries.
+    MinEntries { count: u32 },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Enrollment {
+    pub id: i64,
+    pub profile_id: i64,
+    pub program: Program,
+    /// Unix seconds.
+    pub enrolled_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RuleProgress {
+    pub rule: Rule,
+    pub met: bool,
+    /// Where the profile stands, e.g. "12.5 of 40 hours".
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgramProgress {
+    pub enrollment_id: i64,
+    pub name: String,
+    pub description: String,
+    pub from: Option<String>,
+    pub deadline: Option<String>,
+    /// Days from today to the deadline; negative once it has passed.
+    pub days_left: Option<i64>,
+    /// Time that counts towards the program, after any caps.
+    pub counted_minutes: i64,
+    /// The most any `min_hours` rule asks for.
+    pub required_minutes: Option<i64>,
+    pub rules: Vec<RuleProgress>,
+    /// Every rule is met.
+    pub complete: bool,
+}
+
+impl ProgramProgress {
+    pub fn unmet(&self) -> impl Iterator<Item = &RuleProgress> {
+        self.rules.iter().filter(|r| !r.met)
+    }
+}
+
+fn minutes(hours: f64) -> i64 {
+    (hours * 60.0).round() as i64
+}
+
+fn hours(minutes: i64) -> String {
+    format!("{:.2}", minutes as f64 / 60.0)
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+impl Program {
+    /// Reads a program from TOML, or from JSON when `text` is a JSON object.
+    pub fn parse(text: &str) -> Result<Self> {
+        let program: Program = if text.trim_start().starts_with('{') {
+            serde_json::from_str(text)?
+        } else {
+            toml::from_str(text).map_err(|e| Error::Invalid(format!("program file: {e}")))?
+        };
+        program.validate()?;
+        Ok(program)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Invalid("a program needs a name".into()));
+        }
+        if self.rules.is_empty() {
+            return Err(Error::Invalid(format!("{} has no rules", self.name)));
+        }
+        let from = self.from.as_deref().map(dates::parse).transpose()?;
+        let deadline = self.deadline.as_deref().map(dates::parse).transpose()?;
+        if let (Some(from), Some(deadline)) = (from, deadline) {
+            if deadline < from {
+                return Err(Error::Invalid(format!(
+                    "{} ends before it starts",
+                    self.name
+                )));
+            }
+        }
+        let mut capped = HashSet::new();
+        for rule in &self.rules {
+            if let Rule::MaxHoursFrom { organizations, .. } = rule {
+                for name in organizations {
+                    if !capped.insert(organizations::name_key(name)) {
+                        return Err(Error::Invalid(format!(
+                            "{} caps the hours from {name} more than once",
+                            self.name
+                        )));
+                    }
+                }
+            }
+            let valid = match rule {
+                Rule::MinHours { hours } => *hours > 0.0,
+                Rule::MaxHoursFrom {
+                    hours,
+                    organizations,
+                } => *hours >= 0.0 && !organizations.is_empty(),
+                Rule::MinOrganizations { count } | Rule::MinEntries { count } => *count > 0,
+            };
+            if !valid {
+                return Err(Error::Invalid(format!(
+                    "{} has a rule that can't be used: {rule:?}",
+                    self.name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Enrollment {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let program: String = row.get("program")?;
+        Ok(Self {
+            id: row.get("id")?,
+            profile_id: row.get("profile_id")?,
+            program: serde_json::from_str(&program).map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    0,
+                    rusqlite::types::Type::Text,
+                    Box::new(e),
+                )
+            })?,
+            enrolled_at: row.get("enrolled_at")?,
+        })
+    }
+}
+
+/// Enrolls the profile in `program`, or updates its copy of a program of the same name.
+pub fn enroll(conn: &Connection, profile_id: i64, program: &Program) -> Result<Enrollment> {
+    program.validate()?;
+    conn.execute(
+        "INSERT INTO program_enrollments (profile_id, name, program) VALUES (?1, ?2, ?3)
+         ON CONFLICT (profile_id, name) DO UPDATE SET program = excluded.program",
+        params![
+            profile_id,
+            program.name.trim(),
+            serde_json::to_string(program)?
+        ],
+    )?;
+    conn.query_row(
+        "SELECT id, profile_id, program, enrolled_at FROM program_enrollments
+         WHERE profile_id = ?1 AND name = ?2",
+        params![profile_id, program.name.trim()],
+        Enrollment::from_row,
+    )
+    .map_err(Error::from)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Enrollment> {
+    conn.query_row(
+        "SELECT id, profile_id, program, enrolled_at FROM program_enrollments WHERE id = ?1",
+        [id],
+        Enrollment::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("enrollment"))
+}
+
+pub fn list(conn: &Connection, profile_id: i64) -> Result<Vec<Enrollment>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, profile_id, program, enrolled_at FROM program_enrollments
+         WHERE profile_id = ?1 ORDER BY name",
+    )?;
+    let enrollments = stmt
+        .query_map([profile_id], Enrollment::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(enrollments)
+}
+
+pub fn unenroll(conn: &Connection, id: i64) -> Result<()> {
+    let changed = conn.execute("DELETE FROM program_enrollments WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(Error::NotFound("enrollment"));
+    }
+    Ok(())
+}
+
+/// Checks every rule of an enrollment against the profile's entries as of `today`.
+pub fn progress(conn: &Connection, id: i64, today: NaiveDate) -> Result<ProgramProgress> {
+    let Enrollment {
+        profile_id,
+        program,
+        ..
+    } = get(conn, id)?;
+    let filter = EntryFilter {
+        from: program.from.clone(),
+        to: program.deadline.clone(),
+        status: program.approved_only.then_some(EntryStatus::Approved),
+        exclude_rejected: true,
+        ..EntryFilter::profile(profile_id)
+    };
+    let entries = entries::list_matching(conn, &filter)?;
+
+    let mut by_organization: HashMap<String, i64> = HashMap::new();
+    for entry in &entries {
+        *by_organization
+            .entry(organizations::name_key(&entry.place))
+            .or_default() += entry.duration_minutes;
+    }
+    let total: i64 = by_organization.values().sum();
+
+    // Caps come first, since they decide which hours count for everything else. Each
+    // organization's time falls under the first cap that lists it, so programs enrolled
+    // before overlapping caps were refused don't leave it out twice.
+    let mut excess = 0;
+    let mut capped = HashMap::new();
+    let mut claimed = HashSet::new();
+    for (i, rule) in program.rules.iter().enumerate() {
+        if let Rule::MaxHoursFrom {
+            hours,
+            organizations: listed,
+        } = rule
+        {
+            let from_listed: i64 = listed
+                .iter()
+                .map(|name| organizations::name_key(name))
+                .filter(|key| claimed.insert(key.clone()))
+                .filter_map(|key| by_organization.get(&key))
+                .sum();
+            let over = (from_listed - minutes(*hours)).max(0);
+            excess += over;
+            capped.insert(i, (from_listed, over));
+        }
+    }
+    let counted = total - excess;
+
+    let rules = program
+        .rules
+        .iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            let (met, detail) = match rule {
+                Rule::MinHours { hours: required } => (
+                    counted >= minutes(*required),
+                    format!("{} of {} hours", hours(counted), hours(minutes(*required))),
+                ),
+                Rule::MinOrganizations { count } => (
+                    by_organization.len() >= *count as usize,
+                    format!("{} of {count} organizations", by_organization.len()),
+                ),
+                Rule::MaxHoursFrom { hours: cap, .. } => {
+                    let (from_listed, over) = capped[&i];
+                    let detail = match over {
+                        0 => format!("{} of at most {cap} hours", hours(from_listed)),
+                        over => format!(
+                            "{} of at most {cap} hours; {} not counted",
+                            hours(from_listed),
+                            hours(over)
+                        ),
+                    };
+                    (true, detail)
+                }
+                Rule::MinEntries { count } => (
+                    entries.len() >= *count as usize,
+                    format!("{} of {count} entries", entries.len()),
+                ),
+            };
+            RuleProgress {
+                rule: rule.clone(),
+                met,
+                detail,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let days_left = program
+        .deadline
+        .as_deref()
+        .map(dates::parse)
+        .transpose()?
+        .map(|deadline| (deadline - today).num_days());
+    let required_minutes = program
+        .rules
+        .iter()
+        .filter_map(|rule| match rule {
+            Rule::MinHours { hours } => Some(minutes(*hours)),
+            _ => None,
+        })
+        .max();
+    Ok(ProgramProgress {
+        enrollment_id: id,
+        complete: rules.iter().all(|r| r.met),
+        name: program.name,
+        description: program.description,
+        from: program.from,
+        deadline: program.deadline,
+        days_left,
+        counted_minutes: counted,
+        required_minutes,
+        rules,
+    })
+}
@@ -0,0 +1,186 @@
+//! Progress towards the Presidential Volunteer Service Award. The bronze, silver and gold
+//! levels need a number of hours within one 12-month period, and how many depends on the
+//! volunteer's age group, which is taken from their age on the last day of the period.
+
+use chrono::{Datelike, Months, NaiveDate};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::dates;
+use crate::entries::{EntryFilter, DATE_FORMAT};
+use crate::error::{Error, Result};
+use crate::{profiles, totals};
+
+/// The youngest age the award is given at.
+pub const MIN_AGE: u32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeGroup {
+    /// 5 to 10.
+    Kids,
+    /// 11 to 15.
+    Teens,
+    /// 16 to 25.
+    YoungAdults,
+    /// 26 and over.
+    Adults,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+const LEVELS: [Level; 3] = [Level::Bronze, Level::Silver, Level::Gold];
+
+impl AgeGroup {
+    pub fn for_age(age: u32) -> Option<Self> {
+        match age {
+            0..MIN_AGE => None,
+            MIN_AGE..=10 => Some(AgeGroup::Kids),
+            11..=15 => Some(AgeGroup::Teens),
+            16..=25 => Some(AgeGroup::YoungAdults),
+            _ => Some(AgeGroup::Adults),
+        }
+    }
+
+    /// Hours needed for bronze, silver and gold.
+    pub fn thresholds(self) -> [i64; 3] {
+        match self {
+            AgeGroup::Kids => [26, 50, 75],
+            AgeGroup::Teens => [50, 75, 100],
+            AgeGroup::YoungAdults => [100, 175, 250],
+            AgeGroup::Adults => [100, 250, 500],
+        }
+    }
+}
+
+/// The 12-month period to count hours in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Window {
+    /// The 12 months ending on `end`, `YYYY-MM-DD`.
+    Rolling { end: String },
+    /// A period with fixed inclusive bounds, such as a school year, of at most 12 months.
+    Fixed { from: String, to: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Award {
+    /// The highest level the hours reach.
+    pub level: Option<Level>,
+    pub next_level: Option<Level>,
+    /// Minutes still needed for `next_level`.
+    pub minutes_to_next: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Progress {
+    pub from: String,
+    pub to: String,
+    /// Age on the last day of the period.
+    pub age: u32,
+    /// `None` below [`MIN_AGE`].
+    pub age_group: Option<AgeGroup>,
+    pub minutes: i64,
+    #[serde(flatten)]
+    pub award: Award,
+}
+
+/// Whole years from `birthdate` to `day`.
+pub fn age_on(birthdate: NaiveDate, day: NaiveDate) -> u32 {
+    let mut age = day.year() - birthdate.year();
+    if (day.month(), day.day()) < (birthdate.month(), birthdate.day()) {
+        age -= 1;
+    }
+    age.max(0) as u32
+}
+
+/// The level `minutes` reach in an age group, and what is left to the next one.
+pub fn award(group: AgeGroup, minutes: i64) -> Award {
+    let reached = |hours: i64| minutes >= hours * 60;
+    let thresholds = group.thresholds();
+    let level = LEVELS
+        .iter()
+        .zip(thresholds)
+        .rev()
+        .find(|&(_, hours)| reached(hours))
+        .map(|(&level, _)| level);
+    let next = LEVELS
+        .iter()
+        .zip(thresholds)
+        .find(|&(_, hours)| !reached(hours));
+    Award {
+        level,
+        next_level: next.map(|(&level, _)| level),
+        minutes_to_next: next.map(|(_, hours)| hours * 60 - minutes),
+    }
+}
+
+impl Window {
+    /// The first and last day of the window.
+    pub fn bounds(&self) -> Result<(NaiveDate, NaiveDate)> {
+        let year_before = |day: NaiveDate| {
+            day.checked_sub_months(Months::new(12))
+                .and_then(|d| d.succ_opt())
+                .ok_or_else(|| Error::Invalid("date is out of range".into()))
+        };
+        match self {
+            Window::Rolling { end } => {
+                let end = dates::parse(end)?;
+                Ok((year_before(end)?, end))
+            }
+            Window::Fixed { from, to } => {
+                let (from, to) = (dates::parse(from)?, dates::parse(to)?);
+                if to < from {
+                    return Err(Error::Invalid("the period ends before it starts".into()));
+                }
+                if from < year_before(to)? {
+                    return Err(Error::Invalid(
+                        "the award counts hours within at most 12 months".into(),
+                    ));
+                }
+                Ok((from, to))
+            }
+        }
+    }
+}
+
+/// The profile's hours in `window` and the award level they reach.
+pub fn progress(conn: &Connection, profile_id: i64, window: &Window) -> Result<Progress> {
+    let profile = profiles::get(conn, profile_id)?;
+    let birthdate = profile.birthdate.as_deref().ok_or_else(|| {
+        Error::Invalid("set the profile's birthdate to work out its award level".into())
+    })?;
+    let (from, to) = window.bounds()?;
+    let age = age_on(dates::parse(birthdate)?, to);
+    let age_group = AgeGroup::for_age(age);
+
+    let filter = EntryFilter {
+        from: Some(from.format(DATE_FORMAT).to_string()),
+        to: Some(to.format(DATE_FORMAT).to_string()),
+        exclude_rejected: true,
+        ..EntryFilter::profile(profile_id)
+    };
+    let minutes = totals::minutes(conn, &filter)?;
+
+    Ok(Progress {
+        from: filter.from.unwrap_or_default(),
+        to: filter.to.unwrap_or_default(),
+        age,
+        age_group,
+        minutes,
+        award: match age_group {
+            Some(group) => award(group, minutes),
+            None => Award {
+                level: None,
+                next_level: None,
+                minutes_to_next: None,
+            },
+        },
+    })
+}
@@ -0,0 +1,190 @@
+use chrono::NaiveDate;
+use rusqlite::Connection;
+
+use crate::chain;
+use crate::entries::{self, EntryFilter, DATE_FORMAT};
+use crate::error::Result;
+use crate::pdf::{self, LINE_WIDTH};
+use crate::periods::{self, PeriodScheme};
+use crate::profiles;
+use crate::totals::{self, GroupBy};
+
+const DATE_WIDTH: usize = 10;
+const PLACE_WIDTH: usize = 30;
+const HOURS_WIDTH: usize = 6;
+const LABEL_WIDTH: usize = DATE_WIDTH + 2 + PLACE_WIDTH;
+const NOTES_WIDTH: usize = LINE_WIDTH - LABEL_WIDTH - 2 - HOURS_WIDTH - 2;
+
+/// Lays out the hours-verification report for a profile and an inclusive date range. A
+/// range that is exactly one of the profile's reporting periods is named after it, and a
+/// range spanning several is also totalled by period. Tagged entries are also totalled by
+/// tag; an entry with several tags counts towards each.
+pub fn lines(
+    conn: &Connection,
+    profile_id: i64,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<String>> {
+    let profile = profiles::get(conn, profile_id)?;
+    let filter = EntryFilter {
+        from: from.map(str::to_string),
+        to: to.map(str::to_string),
+        ..EntryFilter::profile(profile_id)
+    };
+    let mut entries = entries::list_matching(conn, &filter)?;
+    entries.reverse();
+    let scheme = periods::scheme(conn, profile_id)?;
+    let named = match (from, to) {
+        (Some(from), Some(to)) if scheme != PeriodScheme::Calendar => {
+            NaiveDate::parse_from_str(from, DATE_FORMAT)
+                .ok()
+                .and_then(|day| scheme.period_of(day))
+                .filter(|period| period.from == from && period.to == to)
+        }
+        _ => None,
+    };
+    let range = format!(
+        "{} to {}",
+        from.unwrap_or("earliest"),
+        to.unwrap_or("latest")
+    );
+
+    let rule = "-".repeat(LINE_WIDTH);
+    let mut out = vec![
+        "VOLUNTEER HOURS VERIFICATION".to_string(),
+        String::new(),
+        format!("Volunteer: {}", profile.name),
+        match named {
+            Some(period) => format!("Period:    {} ({range})", period.label),
+            None => format!("Period:    {range}"),
+        },
+        String::new(),
+        format!(
+            "{:<DATE_WIDTH$}  {:<PLACE_WIDTH$}  {:>HOURS_WIDTH$}  Notes",
+            "Date", "Organization", "Hours"
+        ),
+        rule.clone(),
+    ];
+
+    let mut organizations: Vec<(i64, String, i64)> = Vec::new();
+    let mut total = 0;
+    for entry in &entries {
+        let row = format!(
+            "{:<DATE_WIDTH$}  {:<PLACE_WIDTH$}  {:>HOURS_WIDTH$.2}  {}",
+            entry.date,
+            fit(&entry.place, PLACE_WIDTH),
+            entry.hours(),
+            fit(&entry.notes, NOTES_WIDTH)
+        );
+        out.push(row.trim_end().to_string());
+        match organizations
+            .iter_mut()
+            .find(|(id, _, _)| *id == entry.organization_id)
+        {
+            Some((_, _, minutes)) => *minutes += entry.duration_minutes,
+            None => organizations.push((
+                entry.organization_id,
+                entry.place.clone(),
+                entry.duration_minutes,
+            )),
+        }
+        total += entry.duration_minutes;
+    }
+    if entries.is_empty() {
+        out.push("No entries in this period.".to_string());
+    }
+    organizations.sort_by_key(|(_, name, _)| name.to_lowercase());
+
+    let by_period = totals::totals(conn, &filter, GroupBy::Period)?;
+    if by_period.len() > 1 {
+        out.push(String::new());
+        out.push("Hours by period".to_string());
+        out.push(rule.clone());
+        for period in by_period.iter().rev() {
+            out.push(format!(
+                "{:<LABEL_WIDTH$}  {:>HOURS_WIDTH$.2}",
+                fit(&period.label, LABEL_WIDTH),
+                hours(period.minutes)
+            ));
+        }
+    }
+
+    let by_tag = totals::totals(conn, &filter, GroupBy::Tag)?;
+    if by_tag.iter().any(|tag| !tag.key.is_empty()) {
+        out.push(String::new());
+        out.push("Hours by cause".to_string());
+        out.push(rule.clone());
+        for tag in &by_tag {
+            out.push(format!(
+                "{:<LABEL_WIDTH$}  {:>HOURS_WIDTH$.2}",
+                fit(&tag.label, LABEL_WIDTH),
+                hours(tag.minutes)
+            ));
+        }
+    }
+
+    out.push(String::new());
+    out.push("Hours by organization".to_string());
+    out.push(rule.clone());
+    for (_, name, minutes) in &organizations {
+        out.push(format!(
+            "{:<LABEL_WIDTH$}  {:>HOURS_WIDTH$.2}",
+            fit(name, LABEL_WIDTH),
+            hours(*minutes)
+        ));
+    }
+    out.push(rule);
+    out.push(format!(
+        "{:<LABEL_WIDTH$}  {:>HOURS_WIDTH$.2}",
+        "Total hours",
+        hours(total)
+    ));
+
+    // The seal covers the profile's whole log, not only this period.
+    out.push(String::new());
+    match chain::head(conn, profile_id)? {
+        Some(head) => out.extend([
+            format!("Log seal (SHA-256 chain head, record #{}):", head.seq),
+            head.hash,
+        ]),
+        None => out.push("Log seal: no entries recorded yet".to_string()),
+    }
+
+    let blank = "_".repeat(32);
+    out.extend([
+        String::new(),
+        String::new(),
+        format!("Supervisor name:  {blank}"),
+        String::new(),
+        format!("Signature:        {blank}    Date: ____________"),
+        String::new(),
+        format!("Organization:     {blank}"),
+        String::new(),
+        format!("Phone / email:    {blank}"),
+    ]);
+    Ok(out)
+}
+
+pub fn render_pdf(
+    conn: &Connection,
+    profile_id: i64,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<u8>> {
+    Ok(pdf::render(&lines(conn, profile_id, from, to)?))
+}
+
+fn hours(minutes: i64) -> f64 {
+    minutes as f64 / 60.0
+}
+
+/// Collapses whitespace and truncates to `width` characters, marking cuts with `...`.
+fn fit(text: &str, width: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= width {
+        return text;
+    }
+    let mut cut: String = text.chars().take(width - 3).collect();
+    cut.push_str("...");
+    cut
+}
@@ -0,0 +1,243 @@
+//! The append-only history of every entry. Each create, update, delete and restore adds a
+//! revision holding the entry as it stood afterwards, so any earlier version can be shown
+//! or brought back. Triggers keep revisions from being changed, and from being deleted
+//! before their entry is purged. Each revision is also sealed in its profile's
+//! [hash chain](crate::chain).
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::EntryStatus;
+use crate::chain;
+use crate::entries::{self, EntryInput, VolunteerEntry};
+use crate::error::{Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Create,
+    Update,
+    /// Moved to the trash.
+    Delete,
+    /// Brought back from the trash.
+    Restore,
+    /// Set back to an earlier revision.
+    Revert,
+}
+
+impl Action {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Action::Create => "create",
+            Action::Update => "update",
+            Action::Delete => "delete",
+            Action::Restore => "restore",
+            Action::Revert => "revert",
+        }
+    }
+
+    fn parse(action: &str) -> rusqlite::Result<Self> {
+        Ok(match action {
+            "create" => Action::Create,
+            "update" => Action::Update,
+            "delete" => Action::Delete,
+            "restore" => Action::Restore,
+            "revert" => Action::Revert,
+            other => {
+                return Err(rusqlite::Error::InvalidColumnType(
+                    0,
+                    format!("action {other}"),
+                    rusqlite::types::Type::Text,
+                ))
+            }
+        })
+    }
+}
+
+/// An entry as it stood after one change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryRevision {
+    pub id: i64,
+    pub entry_id: i64,
+    pub action: Action,
+    /// Why the change was made, as given by whoever made it.
+    pub reason: String,
+    /// Unix seconds.
+    pub recorded_at: i64,
+    pub profile_id: i64,
+    pub organization_id: i64,
+    pub place: String,
+    pub date: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub duration_minutes: i64,
+    pub notes: String,
+    /// `None` for revisions recorded before statuses were kept.
+    #[serde(default)]
+    pub status: Option<EntryStatus>,
+    #[serde(default)]
+    pub status_reason: String,
+}
+
+const COLUMNS: &str = "id, entry_id, action, reason, recorded_at, profile_id, organization_id, \
+    place, date, start_time, end_time, duration_minutes, notes, status, status_reason";
+
+impl EntryRevision {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            entry_id: row.get("entry_id")?,
+            action: Action::parse(&row.get::<_, String>("action")?)?,
+            reason: row.get("reason")?,
+            recorded_at: row.get("recorded_at")?,
+            profile_id: row.get("profile_id")?,
+            organization_id: row.get("organization_id")?,
+            place: row.get("place")?,
+            date: row.get("date")?,
+            start_time: row.get("start_time")?,
+            end_time: row.get("end_time")?,
+            duration_minutes: row.get("duration_minutes")?,
+            notes: row.get("notes")?,
+            status: row.get("status")?,
+            status_reason: row.get("status_reason")?,
+        })
+    }
+}
+
+/// Appends a revision holding `entry` as it is now.
+pub(crate) fn record(
+    conn: &Connection,
+    entry: &VolunteerEntry,
+    action: Action,
+    reason: &str,
+) -> Result<()> {
+    conn.execute(
+        "INSERT INTO entry_revisions (entry_id, action, reason, profile_id, organization_id,
+         place, date, start_time, end_time, duration_minutes, notes, status, status_reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            entry.id,
+            action.as_str(),
+            reason.trim(),
+            entry.profile_id,
+            entry.organization_id,
+            entry.place,
+            entry.date,
+            entry.start_time,
+            entry.end_time,
+            entry.duration_minutes,
+            entry.notes,
+            entry.status.as_str(),
+            entry.status_reason
+        ],
+    )?;
+    chain::seal(conn, &get(conn, conn.last_insert_rowid())?)
+}
+
+/// Every revision of an entry, oldest first. Entries in the trash still have theirs.
+pub fn list(conn: &Connection, entry_id: i64) -> Result<Vec<EntryRevision>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM entry_revisions WHERE entry_id = ?1 ORDER BY id"
+    ))?;
+    let revisions = stmt
+        .query_map([entry_id], EntryRevision::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if revisions.is_empty() {
+        return Err(Error::NotFound("entry"));
+    }
+    Ok(revisions)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<EntryRevision> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM entry_revisions WHERE id = ?1"),
+        [id],
+        EntryRevision::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("revision"))
+}
+
+/// Sets an entry back to how it stood in one of its revisions, which adds a new revision
+/// rather than dropping the later ones.
+pub fn revert(
+    conn: &Connection,
+    entry_id: i64,
+    revision_id: i64,
+    reason: &str,
+) -> Result<VolunteerEntry> {
+    let revision = get(conn, revision_id)?;
+    if revision.entry_id != entry_id {
+        return Err(Error::NotFound("revision"));
+    }
+    let input = EntryInput {
+        place: revision.place,
+        date: revision.date,
+        start_time: revision.start_time,
+        end_time: revision.end_time,
+        duration_minutes: Some(revision.duration_minutes),
+        notes: revision.notes,
+        reason: String::new(),
+    };
+    let entry = entries::store(conn, entry_id, &input)?;
+    let reason = match reason.trim() {
+        "" => format!("reverted to revision {revision_id}"),
+        reason => format!("reverted to revision {revision_id}: {reason}"),
+    };
+    record(conn, &entry, Action::Revert, &reason)?;
+    Ok(entry)
+}
+
+/// Drops the history of entries that no longer exist, once they are purged, and the
+/// [chains](crate::chain) of purged profiles.
+pub(crate) fn prune(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "DELETE FROM entry_revisions WHERE entry_id NOT IN (SELECT id FROM entries);
+         DELETE FROM entry_chain WHERE profile_id NOT IN (SELECT id FROM profiles);",
+    )?;
+    Ok(())
+}
+
+/// Every revision of every entry, for archives.
+pub(crate) fn all(conn: &Connection) -> Result<Vec<EntryRevision>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM entry_revisions ORDER BY id"
+    ))?;
+    let revisions = stmt
+        .query_map([], EntryRevision::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(revisions)
+}
+
+/// Copies a revision from an archive as it was recorded, under `id` or a fresh one, and
+/// seals the copy unless the archive's chain already does.
+pub(crate) fn insert(conn: &Connection, revision: &EntryRevision, id: Option<i64>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO entry_revisions (id, entry_id, action, reason, recorded_at, profile_id,
+         organization_id, place, date, start_time, end_time, duration_minutes, notes, status,
+         status_reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![
+            id,
+            revision.entry_id,
+            revision.action.as_str(),
+            revision.reason,
+            revision.recorded_at,
+            revision.profile_id,
+            revision.organization_id,
+            revision.place,
+            revision.date,
+            revision.start_time,
+            revision.end_time,
+            revision.duration_minutes,
+            revision.notes,
+            revision.status.map(EntryStatus::as_str),
+            revision.status_reason
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    if !chain::is_sealed(conn, id)? {
+        chain::seal(conn, &get(conn, id)?)?;
+    }
+    Ok(())
+}
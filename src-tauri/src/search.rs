@@ -0,0 +1,87 @@
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::entries::{self, VolunteerEntry};
+use crate::error::{Error, Result};
+
+/// Wrapped around matched terms in [`SearchHit`] snippets. Control characters cannot
+/// appear in typed text, so the webview can split on them without escaping anything.
+pub const HIGHLIGHT_START: &str = "\u{2}";
+pub const HIGHLIGHT_END: &str = "\u{3}";
+
+const DEFAULT_LIMIT: u32 = 50;
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchQuery {
+    pub query: String,
+    pub profile_id: Option<i64>,
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub organization_id: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub entry: VolunteerEntry,
+    /// Higher is a better match.
+    pub score: f64,
+    pub place: String,
+    pub notes: String,
+}
+
+/// Finds entries whose place or notes contain every word of the query (each word also
+/// matches as a prefix), best matches first.
+pub fn search(conn: &Connection, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+    let expression = match_expression(&query.query)
+        .ok_or_else(|| Error::Invalid("search text is required".into()))?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, bm25(entries_fts) AS rank,
+                highlight(entries_fts, 0, ?2, ?3) AS place_highlight,
+                snippet(entries_fts, 1, ?2, ?3, '…', 16) AS notes_snippet
+         FROM entries_fts JOIN entries e ON e.id = entries_fts.rowid
+         WHERE entries_fts MATCH ?1 AND e.deleted_at IS NULL
+           AND e.profile_id IN (SELECT id FROM profiles WHERE deleted_at IS NULL)
+           AND (?4 IS NULL OR e.profile_id = ?4)
+           AND (?5 IS NULL OR e.date >= ?5)
+           AND (?6 IS NULL OR e.date <= ?6)
+           AND (?7 IS NULL OR e.organization_id = ?7)
+         ORDER BY rank
+         LIMIT ?8",
+        entries::columns_of("e")
+    ))?;
+    let hits = stmt
+        .query_map(
+            params![
+                expression,
+                HIGHLIGHT_START,
+                HIGHLIGHT_END,
+                query.profile_id,
+                query.from,
+                query.to,
+                query.organization_id,
+                query.limit.unwrap_or(DEFAULT_LIMIT),
+            ],
+            |row| {
+                Ok(SearchHit {
+                    entry: VolunteerEntry::from_row(row)?,
+                    score: -row.get::<_, f64>("rank")?,
+                    place: row.get("place_highlight")?,
+                    notes: row.get("notes_snippet")?,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(hits)
+}
+
+/// Turns free text into an FTS5 query, quoting each word so punctuation the user types
+/// is never read as query syntax.
+fn match_expression(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
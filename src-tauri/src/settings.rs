@@ -0,0 +1,27 @@
+//! Preferences stored as JSON in the `settings` table, so they travel with the database.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::error::Result;
+
+/// The value saved under `key`, or the default when nothing is saved yet.
+pub fn get<T: DeserializeOwned + Default>(conn: &Connection, key: &str) -> Result<T> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+        .optional()?;
+    match value {
+        Some(value) => Ok(serde_json::from_str(&value)?),
+        None => Ok(T::default()),
+    }
+}
+
+pub fn set<T: Serialize>(conn: &Connection, key: &str, value: &T) -> Result<()> {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        params![key, serde_json::to_string(value)?],
+    )?;
+    Ok(())
+}
@@ -0,0 +1,308 @@
+//! Tags file entries under causes such as tutoring or food service, across the places
+//! they were logged at. An entry may carry any number of tags, and a tag may name the UN
+//! Sustainable Development Goal its cause serves.
+
+use std::collections::BTreeMap;
+
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::EntryStatus;
+use crate::entries::{self, EntryFilter};
+use crate::error::{Error, Result};
+use crate::organizations::name_key;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+    /// Number of the [Sustainable Development Goal](SDGS) the cause serves.
+    pub sdg: Option<u8>,
+}
+
+/// The UN Sustainable Development Goals; goal `n` is at index `n - 1`.
+pub const SDGS: [&str; 17] = [
+    "No Poverty",
+    "Zero Hunger",
+    "Good Health and Well-being",
+    "Quality Education",
+    "Gender Equality",
+    "Clean Water and Sanitation",
+    "Affordable and Clean Energy",
+    "Decent Work and Economic Growth",
+    "Industry, Innovation and Infrastructure",
+    "Reduced Inequalities",
+    "Sustainable Cities and Communities",
+    "Responsible Consumption and Production",
+    "Climate Action",
+    "Life Below Water",
+    "Life on Land",
+    "Peace, Justice and Strong Institutions",
+    "Partnerships for the Goals",
+];
+
+/// Common causes and the goal each serves, added on request by [`install_taxonomy`].
+pub const TAXONOMY: &[(&str, u8)] = &[
+    ("Homelessness", 1),
+    ("Poverty relief", 1),
+    ("Food service", 2),
+    ("Health care", 3),
+    ("Elder care", 3),
+    ("Tutoring", 4),
+    ("Mentoring", 4),
+    ("Literacy", 4),
+    ("Women and girls", 5),
+    ("Clean water", 6),
+    ("Clean energy", 7),
+    ("Job training", 8),
+    ("Digital access", 9),
+    ("Refugee support", 10),
+    ("Housing", 11),
+    ("Disaster relief", 11),
+    ("Recycling", 12),
+    ("Climate action", 13),
+    ("Waterways", 14),
+    ("Environmental", 15),
+    ("Animal welfare", 15),
+    ("Civic engagement", 16),
+    ("Fundraising", 17),
+];
+
+/// The name of goal `number`.
+pub fn sdg_name(number: u8) -> Option<&'static str> {
+    SDGS.get(usize::from(number).checked_sub(1)?).copied()
+}
+
+const COLUMNS: &str = "id, name, sdg";
+
+impl Tag {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            sdg: row.get("sdg")?,
+        })
+    }
+}
+
+pub fn list(conn: &Connection) -> Result<Vec<Tag>> {
+    let mut stmt = conn.prepare(&format!("SELECT {COLUMNS} FROM tags ORDER BY name_key"))?;
+    let tags = stmt
+        .query_map([], Tag::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+pub fn get(conn: &Connection, id: i64) -> Result<Tag> {
+    conn.query_row(
+        &format!("SELECT {COLUMNS} FROM tags WHERE id = ?1"),
+        [id],
+        Tag::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("tag"))
+}
+
+/// Tag names match the way organization names do, ignoring case and spacing.
+pub fn find_by_name(conn: &Connection, name: &str) -> Result<Option<Tag>> {
+    Ok(conn
+        .query_row(
+            &format!("SELECT {COLUMNS} FROM tags WHERE name_key = ?1"),
+            [name_key(name)],
+            Tag::from_row,
+        )
+        .optional()?)
+}
+
+pub fn find_or_create(conn: &Connection, name: &str) -> Result<Tag> {
+    match find_by_name(conn, name)? {
+        Some(tag) => Ok(tag),
+        None => create(conn, name, None),
+    }
+}
+
+pub fn create(conn: &Connection, name: &str, sdg: Option<u8>) -> Result<Tag> {
+    let name = validate_name(conn, name, None)?;
+    validate_sdg(sdg)?;
+    conn.execute(
+        "INSERT INTO tags (name, name_key, sdg) VALUES (?1, ?2, ?3)",
+        params![name, name_key(&name), sdg],
+    )?;
+    get(conn, conn.last_insert_rowid())
+}
+
+pub fn rename(conn: &Connection, id: i64, name: &str) -> Result<Tag> {
+    let name = validate_name(conn, name, Some(id))?;
+    let changed = conn.execute(
+        "UPDATE tags SET name = ?1, name_key = ?2 WHERE id = ?3",
+        params![name, name_key(&name), id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("tag"));
+    }
+    get(conn, id)
+}
+
+/// Sets or clears the goal a tag's cause serves.
+pub fn set_sdg(conn: &Connection, id: i64, sdg: Option<u8>) -> Result<Tag> {
+    validate_sdg(sdg)?;
+    let changed = conn.execute("UPDATE tags SET sdg = ?1 WHERE id = ?2", params![sdg, id])?;
+    if changed == 0 {
+        return Err(Error::NotFound("tag"));
+    }
+    get(conn, id)
+}
+
+/// Deletes a tag, taking it off every entry that has it.
+pub fn delete(conn: &Connection, id: i64) -> Result<()> {
+    let changed = conn.execute("DELETE FROM tags WHERE id = ?1", [id])?;
+    if changed == 0 {
+        return Err(Error::NotFound("tag"));
+    }
+    Ok(())
+}
+
+/// Puts `kept_id` on every entry tagged `merged_id` and removes `merged_id`. The kept tag
+/// takes the merged one's goal if it has none.
+pub fn merge(conn: &mut Connection, kept_id: i64, merged_id: i64) -> Result<Tag> {
+    if kept_id == merged_id {
+        return Err(Error::Invalid("cannot merge a tag into itself".into()));
+    }
+    get(conn, kept_id)?;
+    let merged = get(conn, merged_id)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT OR IGNORE INTO entry_tags (entry_id, tag_id)
+         SELECT entry_id, ?1 FROM entry_tags WHERE tag_id = ?2",
+        params![kept_id, merged_id],
+    )?;
+    tx.execute(
+        "UPDATE tags SET sdg = COALESCE(sdg, ?1) WHERE id = ?2",
+        params![merged.sdg, kept_id],
+    )?;
+    tx.execute("DELETE FROM tags WHERE id = ?1", [merged_id])?;
+    tx.commit()?;
+    get(conn, kept_id)
+}
+
+/// The profiles with entries, trashed or not, that carry any of `tag_ids`, since renaming,
+/// merging or deleting those tags changes how their hours are filed.
+pub fn profiles_using(conn: &Connection, tag_ids: &[i64]) -> Result<Vec<i64>> {
+    let placeholders = vec!["?"; tag_ids.len()].join(", ");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT DISTINCT e.profile_id FROM entries e JOIN entry_tags et ON et.entry_id = e.id
+         WHERE et.tag_id IN ({placeholders}) ORDER BY e.profile_id"
+    ))?;
+    let profiles = stmt
+        .query_map(params_from_iter(tag_ids), |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(profiles)
+}
+
+/// Checks that an entry's tags may change: it is out of the trash and not
+/// [locked](crate::approval).
+fn check_taggable(conn: &Connection, entry_id: i64) -> Result<()> {
+    if entries::get(conn, entry_id)?.status == EntryStatus::Approved {
+        return Err(Error::Invalid("approved entries cannot be retagged".into()));
+    }
+    Ok(())
+}
+
+/// Tags an entry, creating the tag on first use, and returns the entry's tags.
+pub fn tag(conn: &Connection, entry_id: i64, name: &str) -> Result<Vec<Tag>> {
+    check_taggable(conn, entry_id)?;
+    let tag = find_or_create(conn, name)?;
+    conn.execute(
+        "INSERT OR IGNORE INTO entry_tags (entry_id, tag_id) VALUES (?1, ?2)",
+        params![entry_id, tag.id],
+    )?;
+    of_entry(conn, entry_id)
+}
+
+/// Takes a tag off an entry and returns the tags left. The tag itself stays.
+pub fn untag(conn: &Connection, entry_id: i64, tag_id: i64) -> Result<Vec<Tag>> {
+    check_taggable(conn, entry_id)?;
+    conn.execute(
+        "DELETE FROM entry_tags WHERE entry_id = ?1 AND tag_id = ?2",
+        params![entry_id, tag_id],
+    )?;
+    of_entry(conn, entry_id)
+}
+
+pub fn of_entry(conn: &Connection, entry_id: i64) -> Result<Vec<Tag>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.name, t.sdg FROM tags t JOIN entry_tags et ON et.tag_id = t.id
+         WHERE et.entry_id = ?1 ORDER BY t.name_key",
+    )?;
+    let tags = stmt
+        .query_map([entry_id], Tag::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tags)
+}
+
+/// The tags of every matching entry that has some, by entry id.
+pub fn by_entry(conn: &Connection, filter: &EntryFilter) -> Result<BTreeMap<i64, Vec<Tag>>> {
+    let (condition, values) = filter.where_sql("e");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.id AS entry_id, t.id, t.name, t.sdg
+         FROM entries e JOIN entry_tags et ON et.entry_id = e.id JOIN tags t ON t.id = et.tag_id
+         WHERE {condition} ORDER BY t.name_key"
+    ))?;
+    let rows = stmt
+        .query_map(params_from_iter(&values), |row| {
+            Ok((row.get::<_, i64>("entry_id")?, Tag::from_row(row)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut tags: BTreeMap<i64, Vec<Tag>> = BTreeMap::new();
+    for (entry_id, tag) in rows {
+        tags.entry(entry_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Adds the [built-in causes](TAXONOMY) that are missing, and gives the ones already
+/// there their goal unless they have one. Returns every tag.
+pub fn install_taxonomy(conn: &mut Connection) -> Result<Vec<Tag>> {
+    let tx = conn.transaction()?;
+    for &(name, sdg) in TAXONOMY {
+        match find_by_name(&tx, name)? {
+            Some(tag) if tag.sdg.is_none() => {
+                set_sdg(&tx, tag.id, Some(sdg))?;
+            }
+            Some(_) => {}
+            None => {
+                create(&tx, name, Some(sdg))?;
+            }
+        }
+    }
+    tx.commit()?;
+    list(conn)
+}
+
+fn validate_name(conn: &Connection, name: &str, id: Option<i64>) -> Result<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return Err(Error::Invalid("tag name is required".into()));
+    }
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM tags WHERE name_key = ?1 AND id IS NOT ?2)",
+        params![name_key(&name), id],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(Error::Invalid(format!(
+            "a tag named \"{name}\" already exists"
+        )));
+    }
+    Ok(name)
+}
+
+fn validate_sdg(sdg: Option<u8>) -> Result<()> {
+    match sdg {
+        Some(number) if sdg_name(number).is_none() => Err(Error::Invalid(format!(
+            "{number} is not a Sustainable Development Goal (expected 1 to 17)"
+        ))),
+        _ => Ok(()),
+    }
+}
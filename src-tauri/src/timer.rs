@@ -0,0 +1,169 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+
+use crate::dates;
+use crate::entries::{self, EntryInput, VolunteerEntry, DATETIME_FORMAT};
+use crate::error::{Error, Result};
+
+/// A clock-in session. Timestamps are Unix seconds. Sessions live in the database, so a
+/// running timer outlives the window and the process.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimerSession {
+    pub id: i64,
+    pub profile_id: i64,
+    pub place: String,
+    pub notes: String,
+    pub started_at: i64,
+    pub ended_at: Option<i64>,
+    /// Set while the session is on a break.
+    pub paused_at: Option<i64>,
+    pub break_seconds: i64,
+    pub entry_id: Option<i64>,
+}
+
+const COLUMNS: &str =
+    "id, profile_id, place, notes, started_at, ended_at, paused_at, break_seconds, entry_id";
+
+impl TimerSession {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            profile_id: row.get("profile_id")?,
+            place: row.get("place")?,
+            notes: row.get("notes")?,
+            started_at: row.get("started_at")?,
+            ended_at: row.get("ended_at")?,
+            paused_at: row.get("paused_at")?,
+            break_seconds: row.get("break_seconds")?,
+            entry_id: row.get("entry_id")?,
+        })
+    }
+
+    /// Time on the clock as of `now`, not counting breaks.
+    pub fn worked_seconds(&self, now: i64) -> i64 {
+        let end = self.ended_at.or(self.paused_at).unwrap_or(now);
+        (end - self.started_at - self.break_seconds).max(0)
+    }
+}
+
+pub fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+pub fn active(conn: &Connection, profile_id: i64) -> Result<Option<TimerSession>> {
+    Ok(conn
+        .query_row(
+            &format!(
+                "SELECT {COLUMNS} FROM timer_sessions WHERE profile_id = ?1 AND ended_at IS NULL"
+            ),
+            [profile_id],
+            TimerSession::from_row,
+        )
+        .optional()?)
+}
+
+fn require_active(conn: &Connection, profile_id: i64) -> Result<TimerSession> {
+    active(conn, profile_id)?.ok_or(Error::NotFound("running timer"))
+}
+
+pub fn start(
+    conn: &Connection,
+    profile_id: i64,
+    place: &str,
+    notes: &str,
+    now: i64,
+) -> Result<TimerSession> {
+    if active(conn, profile_id)?.is_some() {
+        return Err(Error::Invalid("a timer is already running for this profile".into()));
+    }
+    conn.execute(
+        "INSERT INTO timer_sessions (profile_id, place, notes, started_at) VALUES (?1, ?2, ?3, ?4)",
+        params![profile_id, place.trim(), notes, now],
+    )?;
+    require_active(conn, profile_id)
+}
+
+pub fn pause(conn: &Connection, profile_id: i64, now: i64) -> Result<TimerSession> {
+    let session = require_active(conn, profile_id)?;
+    if session.paused_at.is_some() {
+        return Err(Error::Invalid("the timer is already paused".into()));
+    }
+    conn.execute(
+        "UPDATE timer_sessions SET paused_at = ?1 WHERE id = ?2",
+        params![now, session.id],
+    )?;
+    require_active(conn, profile_id)
+}
+
+pub fn resume(conn: &Connection, profile_id: i64, now: i64) -> Result<TimerSession> {
+    let session = require_active(conn, profile_id)?;
+    let Some(paused_at) = session.paused_at else {
+        return Err(Error::Invalid("the timer is not paused".into()));
+    };
+    conn.execute(
+        "UPDATE timer_sessions SET paused_at = NULL, break_seconds = break_seconds + ?1
+         WHERE id = ?2",
+        params![(now - paused_at).max(0), session.id],
+    )?;
+    require_active(conn, profile_id)
+}
+
+/// Ends the running session and logs it as an entry with the clock-in and clock-out
+/// times, to the minute. `place` and `notes` override what was given at clock-in.
+pub fn stop(
+    conn: &mut Connection,
+    profile_id: i64,
+    place: Option<&str>,
+    notes: Option<&str>,
+    now: i64,
+) -> Result<VolunteerEntry> {
+    let mut session = require_active(conn, profile_id)?;
+    if let Some(paused_at) = session.paused_at.take() {
+        session.break_seconds += (now - paused_at).max(0);
+    }
+    session.ended_at = Some(now);
+    // The end is rounded up, and kept a minute past the start, so the logged span covers
+    // every second on the clock.
+    let started = session.started_at - session.started_at.rem_euclid(60);
+    let ended = (now + 59).max(session.started_at + 60);
+    let ended = ended - ended.rem_euclid(60);
+    let start = local_minute(conn, started)?;
+    let end = local_minute(conn, ended)?;
+    // Entries count whole minutes, so a session shorter than one still logs one.
+    let minutes = ((session.worked_seconds(now) + 30) / 60).clamp(1, (ended - started) / 60);
+
+    let input = EntryInput {
+        place: place.unwrap_or(&session.place).to_string(),
+        date: start.date().to_string(),
+        start_time: Some(start.format(DATETIME_FORMAT).to_string()),
+        end_time: Some(end.format(DATETIME_FORMAT).to_string()),
+        duration_minutes: Some(minutes),
+        notes: notes.unwrap_or(&session.notes).to_string(),
+        reason: "logged with the timer".into(),
+    };
+    let tx = conn.transaction()?;
+    let entry = entries::create(&tx, profile_id, &input)?;
+    tx.execute(
+        "UPDATE timer_sessions SET ended_at = ?1, paused_at = NULL, break_seconds = ?2,
+         entry_id = ?3 WHERE id = ?4",
+        params![now, session.break_seconds, entry.id, session.id],
+    )?;
+    tx.commit()?;
+    Ok(entry)
+}
+
+/// Discards the running session without logging anything.
+pub fn cancel(conn: &Connection, profile_id: i64) -> Result<()> {
+    let session = require_active(conn, profile_id)?;
+    conn.execute("DELETE FROM timer_sessions WHERE id = ?1", [session.id])?;
+    Ok(())
+}
+
+/// Wall-clock time of `timestamp` in the [chosen timezone](crate::dates), truncated to
+/// the minute.
+fn local_minute(conn: &Connection, timestamp: i64) -> Result<NaiveDateTime> {
+    let utc = DateTime::from_timestamp(timestamp - timestamp.rem_euclid(60), 0)
+        .ok_or_else(|| Error::Invalid(format!("timestamp {timestamp} is out of range")))?;
+    Ok(dates::local_time(dates::timezone(conn)?.as_deref(), utc))
+}
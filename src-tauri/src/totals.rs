@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::entries::{EntryFilter, DATE_FORMAT};
+use crate::error::Result;
+use crate::{periods, tags};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    Year,
+    Month,
+    Organization,
+    /// The profile's [reporting periods](crate::periods).
+    Period,
+    /// [Tags](crate::tags). An entry counts towards each of its tags.
+    Tag,
+    /// The Sustainable Development Goals the entry's tags serve, each counted once.
+    Sdg,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Total {
+    /// `YYYY`, `YYYY-MM`, the organization or tag id, the first day of the period, or the
+    /// goal number. Entries outside every term, or without tags, are totalled under an
+    /// empty key.
+    pub key: String,
+    pub label: String,
+    pub minutes: i64,
+    pub entries: i64,
+}
+
+/// Minutes across every matching entry.
+pub fn minutes(conn: &Connection, filter: &EntryFilter) -> Result<i64> {
+    let (condition, values) = filter.where_sql("e");
+    Ok(conn.query_row(
+        &format!("SELECT COALESCE(SUM(e.duration_minutes), 0) FROM entries e WHERE {condition}"),
+        params_from_iter(&values),
+        |row| row.get(0),
+    )?)
+}
+
+/// Sums matching entries per group. Years, months and periods come newest first;
+/// organizations, tags and goals by most time.
+pub fn totals(conn: &Connection, filter: &EntryFilter, group_by: GroupBy) -> Result<Vec<Total>> {
+    let (condition, values) = filter.where_sql("e");
+    let sql = match group_by {
+        GroupBy::Year | GroupBy::Month => {
+            let length = if group_by == GroupBy::Year { 4 } else { 7 };
+            format!(
+                "SELECT substr(e.date, 1, {length}) AS key, substr(e.date, 1, {length}) AS label,
+                        SUM(e.duration_minutes) AS minutes, COUNT(*) AS entries
+                 FROM entries e WHERE {condition}
+                 GROUP BY key ORDER BY key DESC"
+            )
+        }
+        GroupBy::Organization => format!(
+            "SELECT CAST(o.id AS TEXT) AS key, o.name AS label,
+                    SUM(e.duration_minutes) AS minutes, COUNT(*) AS entries
+             FROM entries e JOIN organizations o ON o.id = e.organization_id
+             WHERE {condition}
+             GROUP BY o.id ORDER BY minutes DESC, o.name_key"
+        ),
+        GroupBy::Tag => format!(
+            "SELECT * FROM (
+                 SELECT CAST(t.id AS TEXT) AS key, t.name AS label,
+                        SUM(e.duration_minutes) AS minutes, COUNT(*) AS entries
+                 FROM entries e JOIN entry_tags et ON et.entry_id = e.id
+                 JOIN tags t ON t.id = et.tag_id
+                 WHERE {condition}
+                 GROUP BY t.id
+                 UNION ALL
+                 SELECT '' AS key, 'Untagged' AS label,
+                        SUM(e.duration_minutes) AS minutes, COUNT(*) AS entries
+                 FROM entries e
+                 WHERE {condition}
+                   AND NOT EXISTS(SELECT 1 FROM entry_tags et WHERE et.entry_id = e.id)
+             )
+             WHERE entries > 0
+             ORDER BY key = '', minutes DESC, label"
+        ),
+        // Two tags of an entry may serve the same goal; DISTINCT counts its time once.
+        GroupBy::Sdg => format!(
+            "SELECT CAST(sdg AS TEXT) AS key, CAST(sdg AS TEXT) AS label,
+                    SUM(duration) AS minutes, COUNT(*) AS entries
+             FROM (SELECT DISTINCT e.id, e.duration_minutes AS duration, t.sdg
+                   FROM entries e JOIN entry_tags et ON et.entry_id = e.id
+                   JOIN tags t ON t.id = et.tag_id
+                   WHERE {condition} AND t.sdg IS NOT NULL)
+             GROUP BY sdg ORDER BY minutes DESC, sdg"
+        ),
+        GroupBy::Period => return by_period(conn, filter),
+    };
+    // The untagged half of a tag total repeats the condition, and so its parameters.
+    let values = match group_by {
+        GroupBy::Tag => [values.clone(), values].concat(),
+        _ => values,
+    };
+    let mut stmt = conn.prepare(&sql)?;
+    let mut totals = stmt
+        .query_map(params_from_iter(&values), |row| {
+            Ok(Total {
+                key: row.get("key")?,
+                label: row.get("label")?,
+                minutes: row.get("minutes")?,
+                entries: row.get("entries")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    if group_by == GroupBy::Sdg {
+        for total in &mut totals {
+            if let Some(name) = total.key.parse().ok().and_then(tags::sdg_name) {
+                total.label = format!("{} {name}", total.key);
+            }
+        }
+    }
+    Ok(totals)
+}
+
+/// Periods can't be told apart in SQL when they are terms, so days are totalled there and
+/// sorted into periods here.
+fn by_period(conn: &Connection, filter: &EntryFilter) -> Result<Vec<Total>> {
+    let scheme = periods::scheme(conn, filter.profile_id)?;
+    let (condition, values) = filter.where_sql("e");
+    let mut stmt = conn.prepare(&format!(
+        "SELECT e.date, SUM(e.duration_minutes), COUNT(*) FROM entries e WHERE {condition}
+         GROUP BY e.date"
+    ))?;
+    let days = stmt
+        .query_map(params_from_iter(&values), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut totals: BTreeMap<String, Total> = BTreeMap::new();
+    for (date, minutes, entries) in days {
+        let period = NaiveDate::parse_from_str(&date, DATE_FORMAT)
+            .ok()
+            .and_then(|day| scheme.period_of(day));
+        let (key, label) = match period {
+            Some(period) => (period.from, period.label),
+            None => (String::new(), "Outside any term".to_string()),
+        };
+        let total = totals.entry(key.clone()).or_insert(Total {
+            key,
+            label,
+            minutes: 0,
+            entries: 0,
+        });
+        total.minutes += minutes;
+        total.entries += entries;
+    }
+    Ok(totals.into_values().rev().collect())
+}
+
+/// The distinct years a profile has entries in, newest first.
+pub fn years(conn: &Connection, profile_id: i64) -> Result<Vec<i32>> {
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT CAST(substr(date, 1, 4) AS INTEGER) AS year
+         FROM entries WHERE profile_id = ?1 AND deleted_at IS NULL ORDER BY year DESC",
+    )?;
+    let years = stmt
+        .query_map([profile_id], |row| row.get(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(years)
+}
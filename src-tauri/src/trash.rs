@@ -0,0 +1,174 @@
+//! Deleted entries and profiles keep their rows, marked with `deleted_at`, until they are
+//! restored or purged, either by hand or once they have sat out the retention period.
+
+use rusqlite::{Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::approval::EntryStatus;
+use crate::chain;
+use crate::db;
+use crate::entries::{self, VolunteerEntry};
+use crate::error::{Error, Result};
+use crate::profiles::{self, Profile};
+use crate::revisions::{self, Action};
+use crate::settings;
+
+/// Settings key of the saved [`TrashSettings`].
+pub const SETTINGS_KEY: &str = "trash";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TrashSettings {
+    /// Days a deleted item stays restorable; 0 keeps it until purged by hand.
+    pub retention_days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashedProfile {
+    pub id: i64,
+    pub name: String,
+    pub deleted_at: i64,
+    /// Entries that go with the profile if it is restored or purged.
+    pub entries: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trash {
+    pub profiles: Vec<TrashedProfile>,
+    /// Entries deleted from the profile asked about, most recently deleted first.
+    pub entries: Vec<VolunteerEntry>,
+}
+
+pub fn list(conn: &Connection, profile_id: i64) -> Result<Trash> {
+    let mut stmt = conn.prepare(
+        "SELECT p.id, p.name, p.deleted_at,
+                (SELECT COUNT(*) FROM entries e
+                 WHERE e.profile_id = p.id AND e.deleted_at IS NULL) AS entries
+         FROM profiles p WHERE p.deleted_at IS NOT NULL ORDER BY p.deleted_at DESC",
+    )?;
+    let profiles = stmt
+        .query_map([], |row| {
+            Ok(TrashedProfile {
+                id: row.get("id")?,
+                name: row.get("name")?,
+                deleted_at: row.get("deleted_at")?,
+                entries: row.get("entries")?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM entries e WHERE e.profile_id = ?1 AND e.deleted_at IS NOT NULL
+         ORDER BY e.deleted_at DESC, e.id DESC",
+        entries::columns_of("e")
+    ))?;
+    let entries = stmt
+        .query_map([profile_id], VolunteerEntry::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Trash { profiles, entries })
+}
+
+/// An entry that is in the trash.
+pub fn entry(conn: &Connection, id: i64) -> Result<VolunteerEntry> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM entries e WHERE e.id = ?1 AND e.deleted_at IS NOT NULL",
+            entries::columns_of("e")
+        ),
+        [id],
+        VolunteerEntry::from_row,
+    )
+    .optional()?
+    .ok_or(Error::NotFound("deleted entry"))
+}
+
+pub fn restore_entry(conn: &Connection, id: i64) -> Result<VolunteerEntry> {
+    let changed = conn.execute(
+        "UPDATE entries SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+        [id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("deleted entry"));
+    }
+    let entry = entries::get(conn, id)?;
+    revisions::record(conn, &entry, Action::Restore, "")?;
+    Ok(entry)
+}
+
+/// Brings a profile back with the entries it had when it was deleted.
+pub fn restore_profile(conn: &Connection, id: i64) -> Result<Profile> {
+    let changed = conn.execute(
+        "UPDATE profiles SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+        [id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("deleted profile"));
+    }
+    profiles::get(conn, id)
+}
+
+/// Deletes a trashed entry and its revisions for good. The purge itself is sealed in the
+/// profile's [chain](crate::chain).
+pub fn purge_entry(conn: &Connection, id: i64, now: i64) -> Result<()> {
+    if entry(conn, id)?.status == EntryStatus::Approved {
+        return Err(Error::Invalid("approved entries cannot be purged".into()));
+    }
+    db::atomically(conn, |tx| {
+        chain::seal_purge(tx, id, now)?;
+        tx.execute("DELETE FROM entries WHERE id = ?1", [id])?;
+        revisions::prune(tx)
+    })
+}
+
+/// Deletes a trashed profile and every entry it has for good, with their revisions.
+pub fn purge_profile(conn: &mut Connection, id: i64) -> Result<()> {
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM entries WHERE profile_id = ?1", [id])?;
+    let changed = tx.execute(
+        "DELETE FROM profiles WHERE id = ?1 AND deleted_at IS NOT NULL",
+        [id],
+    )?;
+    if changed == 0 {
+        return Err(Error::NotFound("deleted profile"));
+    }
+    revisions::prune(&tx)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Purges whatever was deleted more than the saved retention period before `now` (Unix
+/// seconds), returning how many entries and profiles went.
+pub fn purge_expired(conn: &mut Connection, now: i64) -> Result<usize> {
+    let TrashSettings { retention_days } = settings::get(conn, SETTINGS_KEY)?;
+    if retention_days == 0 {
+        return Ok(0);
+    }
+    let cutoff = now - i64::from(retention_days) * 24 * 60 * 60;
+    let tx = conn.transaction()?;
+    // Chains of profiles going as a whole are dropped with them instead.
+    let expired = tx
+        .prepare(
+            "SELECT id FROM entries WHERE deleted_at <= ?1
+             AND profile_id NOT IN (SELECT id FROM profiles WHERE deleted_at <= ?1)",
+        )?
+        .query_map([cutoff], |row| row.get::<_, i64>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    for id in expired {
+        chain::seal_purge(&tx, id, now)?;
+    }
+    let entries = tx.execute(
+        "DELETE FROM entries WHERE deleted_at <= ?1
+         OR profile_id IN (SELECT id FROM profiles WHERE deleted_at <= ?1)",
+        [cutoff],
+    )?;
+    let profiles = tx.execute("DELETE FROM profiles WHERE deleted_at <= ?1", [cutoff])?;
+    revisions::prune(&tx)?;
+    tx.commit()?;
+    Ok(entries + profiles)
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::entries::{self, EntryFilter};
+use tauri_app_lib::totals::{self, GroupBy};
+use tauri_app_lib::{chain, db, profiles, revisions, timer, trash};
+
+use common::input;
+
+#[test]
+fn entries_move_through_the_allowed_transitions() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    assert_eq!(entry.status, EntryStatus::Draft);
+
+    assert!(approval::set_status(&conn, entry.id, EntryStatus::Approved, "").is_err());
+    approval::set_status(&conn, entry.id, EntryStatus::Submitted, "").unwrap();
+    assert!(approval::set_status(&conn, entry.id, EntryStatus::Rejected, " ").is_err());
+    let rejected =
+        approval::set_status(&conn, entry.id, EntryStatus::Rejected, "wrong date").unwrap();
+    assert_eq!(rejected.status, EntryStatus::Rejected);
+    assert_eq!(rejected.status_reason, "wrong date");
+
+    entries::update(&conn, entry.id, &input("2024-03-02", 90)).unwrap();
+    approval::set_status(&conn, entry.id, EntryStatus::Submitted, "").unwrap();
+    let approved = approval::set_status(&conn, entry.id, EntryStatus::Approved, "").unwrap();
+    assert_eq!(approved.status_reason, "");
+
+    assert!(entries::update(&conn, entry.id, &input("2024-03-02", 120)).is_err());
+    for status in [EntryStatus::Submitted, EntryStatus::Rejected] {
+        assert!(approval::set_status(&conn, entry.id, status, "no").is_err());
+    }
+    assert_eq!(entries::get(&conn, entry.id).unwrap().duration_minutes, 90);
+}
+
+#[test]
+fn approved_entries_can_be_reopened_with_a_reason() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    // Entries logged before the workflow existed were migrated as approved.
+    conn.execute("UPDATE entries SET status = 'approved'", [])
+        .unwrap();
+
+    assert!(approval::set_status(&conn, entry.id, EntryStatus::Draft, " ").is_err());
+    let reopened =
+        approval::set_status(&conn, entry.id, EntryStatus::Draft, "wrong hours").unwrap();
+    assert_eq!(reopened.status, EntryStatus::Draft);
+    let history = revisions::list(&conn, entry.id).unwrap();
+    assert_eq!(history.last().unwrap().reason, "reopened: wrong hours");
+
+    entries::update(&conn, entry.id, &input("2024-03-02", 90)).unwrap();
+    assert_eq!(entries::get(&conn, entry.id).unwrap().duration_minutes, 90);
+}
+
+#[test]
+fn totals_can_count_approved_hours_only() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let approved = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    entries::create(&conn, alex.id, &input("2024-03-02", 45)).unwrap();
+    approval::set_status(&conn, approved.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, approved.id, EntryStatus::Approved, "").unwrap();
+
+    let all = EntryFilter::profile(alex.id);
+    let approved_only = EntryFilter {
+        status: Some(EntryStatus::Approved),
+        ..all.clone()
+    };
+    assert_eq!(
+        totals::totals(&conn, &all, GroupBy::Year).unwrap()[0].minutes,
+        105
+    );
+    assert_eq!(
+        totals::totals(&conn, &approved_only, GroupBy::Year).unwrap()[0].minutes,
+        60
+    );
+}
+
+#[test]
+fn status_changes_are_recorded_and_approved_entries_stay() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    approval::set_status(&conn, entry.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, entry.id, EntryStatus::Rejected, "wrong date").unwrap();
+    approval::set_status(&conn, entry.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, entry.id, EntryStatus::Approved, "").unwrap();
+
+    let reasons: Vec<_> = revisions::list(&conn, entry.id)
+        .unwrap()
+        .into_iter()
+        .map(|revision| revision.reason)
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            "",
+            "submitted",
+            "rejected: wrong date",
+            "submitted",
+            "approved"
+        ]
+    );
+    let verification = chain::verify(&conn, alex.id, None).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+    assert_eq!(verification.records, 5);
+    let rejected = &revisions::list(&conn, entry.id).unwrap()[2];
+    assert_eq!(rejected.status, Some(EntryStatus::Rejected));
+    assert_eq!(rejected.status_reason, "wrong date");
+
+    assert!(entries::delete(&conn, entry.id, "").is_err());
+    assert!(trash::purge_entry(&conn, entry.id, timer::now()).is_err());
+    assert_eq!(
+        entries::get(&conn, entry.id).unwrap().status,
+        EntryStatus::Approved
+    );
+}
+
+#[test]
+fn approving_outside_the_app_is_caught() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    conn.execute(
+        "UPDATE entries SET status = 'approved' WHERE id = ?1",
+        [entry.id],
+    )
+    .unwrap();
+    assert_eq!(
+        chain::verify(&conn, alex.id, None).unwrap().problems,
+        [format!("entry {} was changed outside the app", entry.id)]
+    );
+}
@@ -0,0 +1,282 @@
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use tauri_app_lib::approval::EntryStatus;
+use tauri_app_lib::archive::{self, RestoreMode};
+use tauri_app_lib::entries::{self, EntryFilter, EntryInput};
+use tauri_app_lib::goals::{self, GoalInput, GoalKind};
+use tauri_app_lib::programs::{self, Program};
+use tauri_app_lib::{attestation, chain, db, organizations, profiles, revisions, tags, timer, trash};
+
+fn log(conn: &Connection, profile: &str, entries: &[(&str, &str, i64)]) {
+    let profile = match profiles::list(conn).unwrap().into_iter().find(|p| p.name == profile) {
+        Some(profile) => profile,
+        None => profiles::create(conn, profile).unwrap(),
+    };
+    for &(place, date, minutes) in entries {
+        let input = EntryInput {
+            place: place.into(),
+            date: date.into(),
+            duration_minutes: Some(minutes),
+            ..Default::default()
+        };
+        entries::create(conn, profile.id, &input).unwrap();
+    }
+}
+
+fn summary(conn: &Connection) -> Vec<(String, String, String, i64)> {
+    let mut rows = Vec::new();
+    for profile in profiles::list(conn).unwrap() {
+        for entry in entries::list(conn, profile.id).unwrap() {
+            rows.push((profile.name.clone(), entry.place, entry.date, entry.duration_minutes));
+        }
+    }
+    rows.sort();
+    rows
+}
+
+/// A log whose one entry is tagged and signed off, with a program and a goal for Alex.
+fn decorated() -> (Connection, i64, i64) {
+    let mut conn = db::open_in_memory().unwrap();
+    log(&conn, "Alex", &[("Library", "2024-06-01", 90)]);
+    let alex = profiles::list(&conn).unwrap().remove(0);
+    let entry = entries::list(&conn, alex.id).unwrap().remove(0);
+    tags::tag(&conn, entry.id, "Literacy").unwrap();
+    let program = Program::parse("name = \"Scouts\"\n[[rules]]\nkind = \"min_hours\"\nhours = 10");
+    programs::enroll(&conn, alex.id, &program.unwrap()).unwrap();
+    let goal = GoalInput {
+        kind: GoalKind::ByDate,
+        minutes: 600,
+        organization_id: None,
+        start_date: Some("2024-01-01".into()),
+        deadline: Some("2024-12-31".into()),
+    };
+    let today = NaiveDate::from_ymd_opt(2024, 6, 2).unwrap();
+    goals::create(&conn, alex.id, &goal, today).unwrap();
+
+    let supervisor = db::open_in_memory().unwrap();
+    let library = organizations::find_or_create(&supervisor, "Library").unwrap();
+    let key = attestation::generate_key(&supervisor, library.id).unwrap();
+    attestation::trust_key(&conn, entry.organization_id, &key.public_key).unwrap();
+    let filter = EntryFilter {
+        organization_id: Some(entry.organization_id),
+        ..EntryFilter::profile(alex.id)
+    };
+    let request = attestation::request(&conn, &filter).unwrap();
+    let signed = attestation::sign(&supervisor, request, "Sam Lee").unwrap();
+    attestation::import(&mut conn, alex.id, &signed).unwrap();
+    (conn, alex.id, entry.id)
+}
+
+#[test]
+fn archive_round_trips_into_an_empty_log() {
+    let source = db::open_in_memory().unwrap();
+    log(&source, "Alex", &[("Food Bank", "2024-03-02", 150), ("Library", "2024-06-01", 90)]);
+    log(&source, "Sam", &[("Food Bank", "2024-04-06", 60)]);
+    let data = archive::export(&source).unwrap();
+
+    let manifest = archive::manifest(&data).unwrap();
+    assert_eq!(manifest.format_version, archive::FORMAT_VERSION);
+    assert_eq!((manifest.profiles, manifest.organizations, manifest.entries), (2, 2, 3));
+
+    let mut target = db::open_in_memory().unwrap();
+    archive::restore(&mut target, &data, RestoreMode::Replace).unwrap();
+    assert_eq!(summary(&target), summary(&source));
+    assert_eq!(organizations::list(&target).unwrap().len(), 2);
+}
+
+#[test]
+fn merge_adds_only_what_is_missing() {
+    let source = db::open_in_memory().unwrap();
+    log(&source, "Alex", &[("Food Bank", "2024-03-02", 150), ("Library", "2024-06-01", 90)]);
+    let data = archive::export(&source).unwrap();
+
+    let mut target = db::open_in_memory().unwrap();
+    log(&target, "Alex", &[("food bank", "2024-03-02", 150), ("Park Cleanup", "2024-05-04", 45)]);
+    let merged = archive::restore(&mut target, &data, RestoreMode::Merge).unwrap();
+
+    assert_eq!((merged.profiles, merged.entries, merged.skipped_entries), (0, 1, 1));
+    let expected: Vec<_> = [
+        ("Library", "2024-06-01", 90),
+        ("Park Cleanup", "2024-05-04", 45),
+        // The log's spelling of the organization wins over the archive's.
+        ("food bank", "2024-03-02", 150),
+    ]
+    .into_iter()
+    .map(|(place, date, minutes)| {
+        ("Alex".to_string(), place.to_string(), date.to_string(), minutes)
+    })
+    .collect();
+    assert_eq!(summary(&target), expected);
+}
+
+#[test]
+fn replace_wipes_the_log_first() {
+    let source = db::open_in_memory().unwrap();
+    log(&source, "Alex", &[("Library", "2024-06-01", 90)]);
+    let data = archive::export(&source).unwrap();
+
+    let mut target = db::open_in_memory().unwrap();
+    log(&target, "Sam", &[("Park Cleanup", "2024-05-04", 45)]);
+    archive::restore(&mut target, &data, RestoreMode::Replace).unwrap();
+    assert_eq!(summary(&target), summary(&source));
+}
+
+#[test]
+fn replace_keeps_the_hash_chain_so_printed_heads_still_verify() {
+    let source = db::open_in_memory().unwrap();
+    log(
+        &source,
+        "Alex",
+        &[("Library", "2024-06-01", 90), ("Park", "2024-06-08", 30)],
+    );
+    let alex = profiles::list(&source).unwrap().remove(0);
+    // Purging the newest entry leaves the chain naming revisions that are gone.
+    let park = entries::list(&source, alex.id).unwrap().remove(0);
+    entries::delete(&source, park.id, "").unwrap();
+    trash::purge_entry(&source, park.id, timer::now()).unwrap();
+    let head = chain::head(&source, alex.id).unwrap().unwrap();
+    let data = archive::export(&source).unwrap();
+    assert_eq!(archive::manifest(&data).unwrap().chain, 4);
+
+    let mut target = db::open_in_memory().unwrap();
+    archive::restore(&mut target, &data, RestoreMode::Replace).unwrap();
+    assert_eq!(chain::head(&target, alex.id).unwrap(), Some(head.clone()));
+    let verification = chain::verify(&target, alex.id, Some(&head.hash)).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+
+    // Entries logged afterwards don't take ids the restored chain still names.
+    log(&target, "Alex", &[("Library", "2024-06-15", 60)]);
+    let verification = chain::verify(&target, alex.id, Some(&head.hash)).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+}
+
+#[test]
+fn tags_sign_offs_enrollments_and_goals_go_along() {
+    let (source, alex, entry) = decorated();
+    let data = archive::export(&source).unwrap();
+    let manifest = archive::manifest(&data).unwrap();
+    assert_eq!(
+        (manifest.tags, manifest.entry_tags, manifest.attestations),
+        (1, 1, 1)
+    );
+    assert_eq!((manifest.enrollments, manifest.goals), (1, 1));
+
+    let mut target = db::open_in_memory().unwrap();
+    log(&target, "Sam", &[("Park Cleanup", "2024-05-04", 45)]);
+    tags::tag(&target, 1, "Outdoors").unwrap();
+    archive::restore(&mut target, &data, RestoreMode::Replace).unwrap();
+    assert_eq!(tags::list(&target).unwrap(), tags::list(&source).unwrap());
+    assert_eq!(tags::of_entry(&target, entry).unwrap()[0].name, "Literacy");
+    let sign_offs = attestation::sign_offs(&target, alex).unwrap();
+    assert_eq!(sign_offs.len(), 1);
+    assert!(sign_offs[0].valid);
+    assert_eq!(programs::list(&target, alex).unwrap().len(), 1);
+    assert_eq!(goals::list(&target, alex).unwrap().len(), 1);
+}
+
+#[test]
+fn merging_twice_tags_logged_entries_and_adds_goals_once() {
+    let (source, _, _) = decorated();
+    let data = archive::export(&source).unwrap();
+
+    let mut target = db::open_in_memory().unwrap();
+    log(&target, "Alex", &[("Library", "2024-06-01", 90)]);
+    let alex = profiles::list(&target).unwrap().remove(0);
+    let entry = entries::list(&target, alex.id).unwrap().remove(0);
+    for _ in 0..2 {
+        let merged = archive::restore(&mut target, &data, RestoreMode::Merge).unwrap();
+        assert_eq!((merged.entries, merged.skipped_entries), (0, 1));
+        // The sign-off names the entry by its id in the other log.
+        assert_eq!(merged.skipped_attestations, 1);
+    }
+    assert_eq!(
+        tags::of_entry(&target, entry.id).unwrap()[0].name,
+        "Literacy"
+    );
+    assert_eq!(programs::list(&target, alex.id).unwrap().len(), 1);
+    assert_eq!(goals::list(&target, alex.id).unwrap().len(), 1);
+    assert!(attestation::sign_offs(&target, alex.id).unwrap().is_empty());
+}
+
+/// `data` with the archived entries' dates replaced by `dates`, in entry order, the way
+/// an app from before dates were checked could have written them.
+fn with_dates(data: &[u8], dates: &[&str]) -> Vec<u8> {
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(data)).unwrap();
+    let mut rewritten = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for i in 0..zip.len() {
+        let file = zip.by_index(i).unwrap();
+        if file.name() != "entries.json" {
+            rewritten.raw_copy_file(file).unwrap();
+            continue;
+        }
+        let mut entries: serde_json::Value = serde_json::from_reader(file).unwrap();
+        for (entry, date) in entries.as_array_mut().unwrap().iter_mut().zip(dates) {
+            entry["date"] = (*date).into();
+        }
+        rewritten
+            .start_file("entries.json", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        serde_json::to_writer(&mut rewritten, &entries).unwrap();
+    }
+    rewritten.finish().unwrap().into_inner()
+}
+
+#[test]
+fn unreadable_dates_in_old_archives_are_restored_rejected() {
+    let source = db::open_in_memory().unwrap();
+    log(
+        &source,
+        "Alex",
+        &[
+            ("Library", "2024-06-01", 90),
+            ("Park", "2024-06-08", 30),
+            ("Pool", "2024-06-15", 45),
+        ],
+    );
+    let data = archive::export(&source).unwrap();
+    let data = with_dates(&data, &["2024/6/1", "June 8", "2024-06-15"]);
+
+    for mode in [RestoreMode::Replace, RestoreMode::Merge] {
+        let mut target = db::open_in_memory().unwrap();
+        let restored = archive::restore(&mut target, &data, mode).unwrap();
+        assert_eq!(restored.entries, 3);
+        assert_eq!(restored.unreadable_entries.len(), 1);
+        let problem = &restored.unreadable_entries[0];
+        assert!(problem.contains("\"June 8\""), "{problem}");
+        let dates: Vec<_> = summary(&target).into_iter().map(|row| row.2).collect();
+        assert_eq!(dates, ["2024-06-01", "June 8", "2024-06-15"]);
+
+        let alex = profiles::list(&target).unwrap().remove(0);
+        let park = entries::list(&target, alex.id)
+            .unwrap()
+            .into_iter()
+            .find(|entry| entry.place == "Park")
+            .unwrap();
+        assert_eq!(park.status, EntryStatus::Rejected);
+        assert!(park.status_reason.contains("\"June 8\""));
+        let last = revisions::list(&target, park.id).unwrap().pop().unwrap();
+        assert_eq!(last.reason, "date could not be read");
+        assert_eq!(last.status, Some(EntryStatus::Rejected));
+        let verification = chain::verify(&target, alex.id, None).unwrap();
+        assert!(verification.intact(), "{:?}", verification.problems);
+    }
+}
+
+#[test]
+fn rejects_archives_from_a_newer_format() {
+    let conn = db::open_in_memory().unwrap();
+    let data = archive::export(&conn).unwrap();
+    let mut zip = zip::ZipArchive::new(std::io::Cursor::new(&data)).unwrap();
+    let mut manifest: serde_json::Value =
+        serde_json::from_reader(zip.by_name("manifest.json").unwrap()).unwrap();
+    manifest["format_version"] = (archive::FORMAT_VERSION + 1).into();
+
+    let mut rewritten = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    rewritten
+        .start_file("manifest.json", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    serde_json::to_writer(&mut rewritten, &manifest).unwrap();
+    let rewritten = rewritten.finish().unwrap().into_inner();
+
+    assert!(archive::manifest(&rewritten).is_err());
+}
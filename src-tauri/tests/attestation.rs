@@ -0,0 +1,143 @@
+mod common;
+
+use rusqlite::Connection;
+use tauri_app_lib::attestation::{self, Attestation};
+use tauri_app_lib::entries::{self, EntryFilter, EntryInput};
+use tauri_app_lib::{db, organizations, profiles};
+
+use common::input;
+
+/// A supervisor's log holding the library's signing key.
+fn supervisor() -> Connection {
+    let conn = db::open_in_memory().unwrap();
+    let library = organizations::find_or_create(&conn, "Library").unwrap();
+    attestation::generate_key(&conn, library.id).unwrap();
+    conn
+}
+
+/// Trusts the supervisor's library key in `conn`, as the volunteer would on being
+/// handed it.
+fn trust(conn: &Connection, supervisor: &Connection) {
+    let library = |conn: &Connection| {
+        organizations::find_by_name(conn, "Library")
+            .unwrap()
+            .unwrap()
+            .id
+    };
+    let key = attestation::organization_key(supervisor, library(supervisor))
+        .unwrap()
+        .unwrap();
+    attestation::trust_key(conn, library(conn), &key.public_key).unwrap();
+}
+
+/// A volunteer's log with two library shifts, and a request to sign them off.
+fn volunteer() -> (Connection, i64, Attestation) {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    let entry = entries::create(&conn, alex.id, &input("2024-03-09", 90)).unwrap();
+    let filter = EntryFilter {
+        organization_id: Some(entry.organization_id),
+        ..EntryFilter::profile(alex.id)
+    };
+    let request = attestation::request(&conn, &filter).unwrap();
+    (conn, alex.id, request)
+}
+
+#[test]
+fn a_signed_attestation_verifies_entries_until_they_change() {
+    let supervisor = supervisor();
+    let (mut conn, profile_id, request) = volunteer();
+    assert_eq!(request.entries.len(), 2);
+    assert!(request.sign_off.is_none());
+
+    let signed = attestation::sign(&supervisor, request, "Sam Lee, branch manager").unwrap();
+    trust(&conn, &supervisor);
+    let summary = attestation::import(&mut conn, profile_id, &signed).unwrap();
+    assert_eq!(summary.verified, 2);
+    assert!(summary.mismatched.is_empty());
+
+    let sign_offs = attestation::sign_offs(&conn, profile_id).unwrap();
+    assert!(sign_offs.iter().all(|s| s.valid));
+    assert_eq!(sign_offs[0].signer, "Sam Lee, branch manager");
+
+    let first = signed.entries[0].entry_id;
+    let notes = EntryInput {
+        notes: "shelving".into(),
+        ..input("2024-03-02", 60)
+    };
+    entries::update(&conn, first, &notes).unwrap();
+    assert!(attestation::sign_offs(&conn, profile_id).unwrap()[0].valid);
+
+    entries::update(&conn, first, &input("2024-03-02", 180)).unwrap();
+    let sign_offs = attestation::sign_offs(&conn, profile_id).unwrap();
+    assert!(!sign_offs[0].valid);
+    assert!(sign_offs[1].valid);
+
+    // Moving the hours to another organization invalidates the sign-off too.
+    let second = signed.entries[1].entry_id;
+    let school = EntryInput {
+        place: "School".into(),
+        ..input("2024-03-09", 90)
+    };
+    entries::update(&conn, second, &school).unwrap();
+    assert!(!attestation::sign_offs(&conn, profile_id).unwrap()[1].valid);
+}
+
+#[test]
+fn tampered_or_untrusted_attestations_are_refused() {
+    let supervisor = supervisor();
+    let (mut conn, profile_id, request) = volunteer();
+    let signed = attestation::sign(&supervisor, request, "Sam").unwrap();
+
+    // No key is known for the library yet, and the file's own key is not taken on trust.
+    assert!(attestation::import(&mut conn, profile_id, &signed).is_err());
+    let library = organizations::find_by_name(&conn, "Library")
+        .unwrap()
+        .unwrap();
+    assert!(attestation::organization_key(&conn, library.id)
+        .unwrap()
+        .is_none());
+
+    trust(&conn, &supervisor);
+    let mut tampered = signed.clone();
+    tampered.entries[0].duration_minutes = 600;
+    assert!(attestation::import(&mut conn, profile_id, &tampered).is_err());
+    let mut tampered = signed.clone();
+    tampered.entries[0].organization_id = Some(library.id + 1);
+    assert!(attestation::import(&mut conn, profile_id, &tampered).is_err());
+
+    attestation::import(&mut conn, profile_id, &signed).unwrap();
+    let impostor = self::supervisor();
+    let (_, _, request) = volunteer();
+    let forged = attestation::sign(&impostor, request, "Someone else").unwrap();
+    assert!(attestation::import(&mut conn, profile_id, &forged).is_err());
+}
+
+#[test]
+fn entries_changed_since_the_request_are_not_verified() {
+    let supervisor = supervisor();
+    let (mut conn, profile_id, request) = volunteer();
+    let changed = request.entries[1].entry_id;
+    entries::update(&conn, changed, &input("2024-03-09", 30)).unwrap();
+
+    let signed = attestation::sign(&supervisor, request, "Sam").unwrap();
+    trust(&conn, &supervisor);
+    let summary = attestation::import(&mut conn, profile_id, &signed).unwrap();
+    assert_eq!(summary.verified, 1);
+    assert_eq!(summary.mismatched, [changed]);
+}
+
+#[test]
+fn a_log_cannot_sign_off_its_own_entries() {
+    let (mut conn, profile_id, request) = volunteer();
+    let library = organizations::find_by_name(&conn, "Library")
+        .unwrap()
+        .unwrap();
+    attestation::generate_key(&conn, library.id).unwrap();
+    let signed = attestation::sign(&conn, request, "Alex, pretending").unwrap();
+    assert!(attestation::import(&mut conn, profile_id, &signed).is_err());
+    assert!(attestation::sign_offs(&conn, profile_id)
+        .unwrap()
+        .is_empty());
+}
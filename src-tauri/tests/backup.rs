@@ -0,0 +1,139 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use rusqlite::Connection;
+use tauri_app_lib::backup::{self, Rotation};
+use tauri_app_lib::error::Error;
+use tauri_app_lib::{db, migrations, profiles};
+
+fn at(time: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M").unwrap()
+}
+
+fn files(dir: &Path) -> Vec<String> {
+    backup::list(dir)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.file)
+        .collect()
+}
+
+fn names(conn: &Connection) -> Vec<String> {
+    profiles::list(conn)
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect()
+}
+
+#[test]
+fn rotation_keeps_the_newest_of_each_day_week_and_month() {
+    let dir = common::scratch_dir("backup-rotate");
+    let taken = [
+        "20240115-120000",
+        "20240215-120000",
+        "20240229-120000",
+        "20240329-120000",
+        "20240330-120000",
+        "20240331-090000",
+        "20240331-180000",
+    ];
+    for time in taken {
+        fs::write(dir.join(format!("volunteer-{time}.db")), "").unwrap();
+    }
+    fs::write(dir.join("notes.txt"), "").unwrap();
+
+    let rotation = Rotation {
+        daily: 2,
+        weekly: 1,
+        monthly: 2,
+    };
+    let removed: Vec<_> = backup::rotate(&dir, &rotation)
+        .unwrap()
+        .into_iter()
+        .map(|b| b.file)
+        .collect();
+    assert_eq!(
+        removed,
+        [
+            "volunteer-20240331-090000.db",
+            "volunteer-20240329-120000.db",
+            "volunteer-20240215-120000.db",
+            "volunteer-20240115-120000.db",
+        ]
+    );
+    assert_eq!(
+        files(&dir),
+        [
+            "volunteer-20240331-180000.db",
+            "volunteer-20240330-120000.db",
+            "volunteer-20240229-120000.db",
+        ]
+    );
+    assert!(dir.join("notes.txt").exists());
+
+    // Even keeping nothing keeps the newest.
+    let nothing = Rotation {
+        daily: 0,
+        weekly: 0,
+        monthly: 0,
+    };
+    assert_eq!(backup::rotate(&dir, &nothing).unwrap().len(), 2);
+    assert_eq!(files(&dir), ["volunteer-20240331-180000.db"]);
+}
+
+#[test]
+fn only_readable_backups_of_a_known_schema_verify() {
+    let dir = common::scratch_dir("backup-verify");
+    let conn = db::open_in_memory().unwrap();
+    profiles::create(&conn, "Alex").unwrap();
+    let good = backup::create(&conn, &dir, None, at("2024-06-01 09:00")).unwrap();
+    assert_eq!(good.file, "volunteer-20240601-090000.db");
+    assert_eq!(good.taken_at, "2024-06-01T09:00:00");
+    backup::verify(&good.path, None).unwrap();
+
+    let garbage = dir.join("volunteer-20240602-090000.db");
+    fs::write(
+        &garbage,
+        "not a database, just some text that is long enough",
+    )
+    .unwrap();
+    assert!(backup::verify(&garbage, None).is_err());
+
+    let newer = backup::create(&conn, &dir, None, at("2024-06-03 09:00")).unwrap();
+    let copy = Connection::open(&newer.path).unwrap();
+    copy.pragma_update(None, "user_version", migrations::latest_version() + 1)
+        .unwrap();
+    drop(copy);
+    let refused = backup::verify(&newer.path, None).unwrap_err();
+    assert!(refused.to_string().contains("newer than this app supports"));
+}
+
+#[test]
+fn restoring_backs_up_the_current_log_first() {
+    let dir = common::scratch_dir("backup-restore");
+    let mut conn = db::open_in_memory().unwrap();
+    profiles::create(&conn, "Alex").unwrap();
+    let taken = backup::create(&conn, &dir, None, at("2024-06-01 09:00")).unwrap();
+    profiles::create(&conn, "Sam").unwrap();
+
+    let missing = backup::restore(
+        &mut conn,
+        &dir,
+        "../volunteer.db",
+        None,
+        at("2024-06-02 09:00"),
+    );
+    assert!(matches!(missing, Err(Error::NotFound("backup"))));
+    backup::restore(&mut conn, &dir, &taken.file, None, at("2024-06-02 09:00")).unwrap();
+    assert_eq!(names(&conn), ["Alex"]);
+
+    let before = backup::find(&dir, "volunteer-20240602-090000.db").unwrap();
+    assert_eq!(
+        names(&db::open(&before.path, None).unwrap()),
+        ["Alex", "Sam"]
+    );
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use rusqlite::Connection;
+use tauri_app_lib::entries;
+use tauri_app_lib::organizations::{self, OrganizationInput};
+use tauri_app_lib::{chain, db, profiles, timer, trash};
+
+use common::input;
+
+fn seeded() -> (Connection, i64, i64) {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let first = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    entries::create(&conn, alex.id, &input("2024-03-09", 90)).unwrap();
+    entries::update(&conn, first.id, &input("2024-03-02", 120)).unwrap();
+    (conn, alex.id, first.id)
+}
+
+#[test]
+fn an_untouched_log_verifies() {
+    let (conn, profile_id, first) = seeded();
+    let verification = chain::verify(&conn, profile_id, None).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+    assert_eq!(verification.records, 3);
+
+    let head = chain::head(&conn, profile_id).unwrap().unwrap();
+    entries::delete(&conn, first, "").unwrap();
+    trash::purge_entry(&conn, first, timer::now()).unwrap();
+    let verification = chain::verify(&conn, profile_id, Some(&head.hash)).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+    assert_eq!(verification.records, 5);
+}
+
+#[test]
+fn backdating_an_entry_is_caught() {
+    let (conn, profile_id, first) = seeded();
+    conn.execute(
+        "UPDATE entries SET date = '2023-12-30' WHERE id = ?1",
+        [first],
+    )
+    .unwrap();
+    let verification = chain::verify(&conn, profile_id, None).unwrap();
+    assert_eq!(
+        verification.problems,
+        [format!("entry {first} was changed outside the app")]
+    );
+}
+
+#[test]
+fn rewritten_or_removed_records_are_caught() {
+    let (conn, profile_id, _) = seeded();
+    let head = chain::head(&conn, profile_id).unwrap().unwrap();
+    // Get around the append-only triggers the way someone editing the file could.
+    conn.execute_batch(
+        "DROP TRIGGER entry_revisions_no_update;
+         DROP TRIGGER entry_chain_no_delete;
+         UPDATE entry_revisions SET duration_minutes = 600 WHERE id = 1;
+         DELETE FROM entry_chain WHERE seq = 3;",
+    )
+    .unwrap();
+
+    let problems = chain::verify(&conn, profile_id, Some(&head.hash))
+        .unwrap()
+        .problems;
+    assert!(problems.contains(&"revision 1 of entry 1 was altered".to_string()));
+    assert!(problems
+        .iter()
+        .any(|p| p.contains("added outside the chain")));
+    assert!(problems.contains(&format!("head {} is not in the chain", head.hash)));
+}
+
+#[test]
+fn revisions_slipped_in_are_not_sealed_by_reopening() {
+    let path = common::scratch_dir("chain-slipped-in").join(db::DB_FILE);
+    let conn = db::open(&path, None).unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("2024-03-02", 60)).unwrap();
+    conn.execute(
+        "INSERT INTO entry_revisions (entry_id, action, recorded_at, profile_id,
+         organization_id, place, date, duration_minutes)
+         SELECT id, 'update', 1, profile_id, organization_id, place, '2023-12-30', 600
+         FROM entries WHERE id = ?1",
+        [entry.id],
+    )
+    .unwrap();
+    drop(conn);
+
+    let conn = db::open(&path, None).unwrap();
+    let problems = chain::verify(&conn, alex.id, None).unwrap().problems;
+    assert_eq!(
+        problems,
+        [format!(
+            "revision 2 of entry {} was added outside the chain",
+            entry.id
+        )]
+    );
+}
+
+#[test]
+fn moving_hours_to_another_organization_is_caught() {
+    let (mut conn, profile_id, first) = seeded();
+    let library = organizations::find_by_name(&conn, "Library")
+        .unwrap()
+        .unwrap();
+    let shelter = organizations::find_or_create(&conn, "Shelter").unwrap();
+    organizations::update(
+        &mut conn,
+        library.id,
+        &OrganizationInput {
+            name: "City Library".into(),
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    entries::delete(&conn, first, "").unwrap();
+    organizations::merge(&mut conn, shelter.id, library.id).unwrap();
+    let verification = chain::verify(&conn, profile_id, None).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+
+    let kept = organizations::find_or_create(&conn, "Animal Rescue").unwrap();
+    conn.execute(
+        "UPDATE entries SET organization_id = ?1, place = ?2 WHERE id = ?3",
+        rusqlite::params![kept.id, kept.name, first],
+    )
+    .unwrap();
+    assert_eq!(
+        chain::verify(&conn, profile_id, None).unwrap().problems,
+        [format!("entry {first} was changed outside the app")]
+    );
+}
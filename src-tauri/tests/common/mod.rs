@@ -0,0 +1,45 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use tauri_app_lib::entries::{self, EntryInput, VolunteerEntry};
+
+/// An empty directory of its own for one test, under the system's temp directory.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vlog-test-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn day(value: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+}
+
+/// `minutes` at the library on `date`.
+pub fn input(date: &str, minutes: i64) -> EntryInput {
+    EntryInput {
+        place: "Library".into(),
+        date: date.into(),
+        duration_minutes: Some(minutes),
+        ..Default::default()
+    }
+}
+
+/// Logs `hours` at `place` on `date`.
+pub fn log(
+    conn: &Connection,
+    profile_id: i64,
+    place: &str,
+    date: &str,
+    hours: i64,
+) -> VolunteerEntry {
+    let input = EntryInput {
+        place: place.into(),
+        ..input(date, hours * 60)
+    };
+    entries::create(conn, profile_id, &input).unwrap()
+}
@@ -0,0 +1,115 @@
+use tauri_app_lib::csv_io::{self, ColumnMapping, RowStatus};
+use tauri_app_lib::entries::{self, EntryFilter, EntryInput};
+use tauri_app_lib::{db, profiles};
+
+#[test]
+fn export_writes_oldest_first_with_quoting() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let park = EntryInput {
+        place: "Park".into(),
+        date: "2024-04-06".into(),
+        duration_minutes: Some(45),
+        ..Default::default()
+    };
+    let library = EntryInput {
+        place: "Library".into(),
+        date: "2024-03-02".into(),
+        start_time: Some("09:00".into()),
+        end_time: Some("11:30".into()),
+        notes: "Shelving, sorting".into(),
+        ..Default::default()
+    };
+    entries::create(&conn, alex.id, &park).unwrap();
+    entries::create(&conn, alex.id, &library).unwrap();
+
+    let csv = csv_io::export(&conn, &EntryFilter::profile(alex.id)).unwrap();
+    assert_eq!(
+        csv,
+        "place,date,start_time,end_time,hours,notes\r\n\
+         Library,2024-03-02,2024-03-02T09:00,2024-03-02T11:30,2.50,\"Shelving, sorting\"\r\n\
+         Park,2024-04-06,,,0.75,\r\n"
+    );
+
+    // An export reads back in as the same entries, all already logged.
+    let report = csv_io::import(&mut conn, alex.id, &csv, &ColumnMapping::default(), false);
+    let report = report.unwrap();
+    assert_eq!(report.imported, 0);
+    assert!(report.rows.iter().all(|r| r.status == RowStatus::Duplicate));
+}
+
+#[test]
+fn columns_are_found_by_the_mapping() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let data = "Organization,Day,Hours Served,Comments\n\
+                Food Bank,2024-03-02,2.5,Sorting\n";
+    assert!(csv_io::import(&mut conn, alex.id, data, &ColumnMapping::default(), true).is_err());
+
+    let mapping = ColumnMapping {
+        place: "organization".into(),
+        date: " DAY ".into(),
+        hours: "hours served".into(),
+        start_time: None,
+        end_time: Some("Finished".into()),
+        notes: Some("comments".into()),
+    };
+    let report = csv_io::import(&mut conn, alex.id, data, &mapping, false).unwrap();
+    assert_eq!(report.imported, 1);
+    let entry = entries::list(&conn, alex.id).unwrap().remove(0);
+    assert_eq!(
+        (
+            entry.place.as_str(),
+            entry.date.as_str(),
+            entry.duration_minutes
+        ),
+        ("Food Bank", "2024-03-02", 150)
+    );
+    assert_eq!(entry.notes, "Sorting");
+}
+
+#[test]
+fn duplicates_and_invalid_rows_are_reported_not_imported() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let logged = EntryInput {
+        place: "Library".into(),
+        date: "2024-03-02".into(),
+        duration_minutes: Some(60),
+        ..Default::default()
+    };
+    entries::create(&conn, alex.id, &logged).unwrap();
+    let data = "place,date,hours,start_time,end_time\n\
+                library ,2024-03-02,1,,\n\
+                Park,2024-03-09,,10:00,12:00\n\
+                PARK,2024-03-09,2,,\n\
+                Park,2024-03-10,lots,,\n\
+                ,2024-03-11,1,,\n\
+                Park,March 12,1,,\n\
+                Park,2024-03-13,,10:00,\n";
+
+    let preview = csv_io::import(&mut conn, alex.id, data, &ColumnMapping::default(), true);
+    let preview = preview.unwrap();
+    assert!(preview.dry_run);
+    assert_eq!(preview.imported, 0);
+    assert_eq!(entries::list(&conn, alex.id).unwrap().len(), 1);
+    let statuses: Vec<_> = preview.rows.iter().map(|r| (r.line, r.status)).collect();
+    assert_eq!(
+        statuses,
+        [
+            (2, RowStatus::Duplicate),
+            (3, RowStatus::New),
+            (4, RowStatus::Duplicate),
+            (5, RowStatus::Invalid),
+            (6, RowStatus::Invalid),
+            (7, RowStatus::Invalid),
+            (8, RowStatus::Invalid),
+        ]
+    );
+    let error = preview.rows[3].error.as_deref();
+    assert_eq!(error, Some("\"lots\" is not a number of hours"));
+
+    let report = csv_io::import(&mut conn, alex.id, data, &ColumnMapping::default(), false);
+    assert_eq!(report.unwrap().imported, 1);
+    assert_eq!(entries::list(&conn, alex.id).unwrap().len(), 2);
+}
@@ -0,0 +1,72 @@
+use chrono::{DateTime, Utc};
+use tauri_app_lib::entries::{self, EntryInput};
+use tauri_app_lib::{dates, db, profiles};
+
+fn utc(value: &str) -> DateTime<Utc> {
+    value.parse().unwrap()
+}
+
+#[test]
+fn dates_are_normalized_or_refused() {
+    assert_eq!(dates::normalize(" 2024-3-5 ").unwrap(), "2024-03-05");
+    assert_eq!(dates::normalize("2024/12/01").unwrap(), "2024-12-01");
+    for bad in [
+        "2024-02-30",
+        "2023-02-29",
+        "24-03-05",
+        "2024-003-05",
+        "2024-03-05T10:00Z",
+        "+2024-03-05",
+        "",
+    ] {
+        assert!(dates::parse(bad).is_err(), "{bad} should be refused");
+    }
+}
+
+#[test]
+fn today_follows_the_chosen_timezone() {
+    let conn = db::open_in_memory().unwrap();
+    assert_eq!(dates::timezone(&conn).unwrap(), None);
+    assert!(dates::set_timezone(&conn, Some("Mars/Olympus_Mons")).is_err());
+    assert_eq!(
+        dates::set_timezone(&conn, Some(" America/Chicago ")).unwrap(),
+        Some("America/Chicago".to_string())
+    );
+
+    // An evening in Chicago is already the next day in UTC.
+    let evening = utc("2024-07-04T03:30:00Z");
+    let zone = dates::timezone(&conn).unwrap();
+    assert_eq!(
+        dates::local_time(zone.as_deref(), evening).to_string(),
+        "2024-07-03 22:30:00"
+    );
+    assert_eq!(
+        dates::local_time(Some("Asia/Tokyo"), evening).to_string(),
+        "2024-07-04 12:30:00"
+    );
+
+    assert_eq!(dates::set_timezone(&conn, None).unwrap(), None);
+    assert_eq!(dates::timezone(&conn).unwrap(), None);
+}
+
+#[test]
+fn entries_are_stored_with_iso_dates() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let input = EntryInput {
+        place: "Library".into(),
+        date: "2024-3-5".into(),
+        duration_minutes: Some(60),
+        ..Default::default()
+    };
+    let entry = entries::create(&conn, alex.id, &input).unwrap();
+    assert_eq!(entry.date, "2024-03-05");
+
+    // Writes that skip validation are refused by the database itself.
+    let sneaked = conn.execute(
+        "UPDATE entries SET date = '2024-02-30' WHERE id = ?1",
+        [entry.id],
+    );
+    assert!(sneaked.is_err());
+    assert_eq!(entries::get(&conn, entry.id).unwrap().date, "2024-03-05");
+}
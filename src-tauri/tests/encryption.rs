@@ -0,0 +1,81 @@
+#![cfg(feature = "encryption")]
+
+mod common;
+
+use tauri_app_lib::db::{self, Db};
+use tauri_app_lib::encryption;
+use tauri_app_lib::error::Error;
+use tauri_app_lib::profiles;
+
+fn names(db: &Db) -> Vec<String> {
+    db.with(|conn| profiles::list(conn))
+        .unwrap()
+        .into_iter()
+        .map(|p| p.name)
+        .collect()
+}
+
+/// A plain database at a fresh path holding one profile, then encrypted with `passphrase`.
+fn encrypted(test: &str, passphrase: &str) -> (Db, std::path::PathBuf) {
+    let path = common::scratch_dir(test).join(db::DB_FILE);
+    assert!(!encryption::is_encrypted(&path).unwrap());
+    let db = Db::open(path.clone()).unwrap();
+    db.with(|conn| profiles::create(conn, "Alex")).unwrap();
+    assert!(!encryption::is_encrypted(&path).unwrap());
+
+    let status = db.set_passphrase(None, Some(passphrase)).unwrap();
+    assert!(status.encrypted && !status.locked);
+    assert!(encryption::is_encrypted(&path).unwrap());
+    (db, path)
+}
+
+#[test]
+fn a_wrong_passphrase_is_refused_clearly() {
+    let (_, path) = encrypted("wrong-passphrase", "correct horse");
+    assert!(matches!(db::open(&path, None), Err(Error::Locked)));
+    let wrong = db::open(&path, Some("battery staple")).unwrap_err();
+    assert!(matches!(wrong, Error::WrongPassphrase));
+    assert_eq!(wrong.to_string(), "wrong passphrase");
+
+    let reopened = Db::open(path).unwrap();
+    assert!(reopened.status().locked);
+    assert!(matches!(reopened.with(|_| Ok(())), Err(Error::Locked)));
+    assert!(reopened.unlock("battery staple").is_err());
+    assert!(!reopened.unlock("correct horse").unwrap().locked);
+    assert_eq!(names(&reopened), ["Alex"]);
+}
+
+#[test]
+fn changing_the_passphrase_rekeys_in_place() {
+    let (db, path) = encrypted("rekey", "first");
+    assert!(db.set_passphrase(Some("wrong"), Some("second")).is_err());
+    // A failed change leaves the database open under the old key.
+    assert_eq!(names(&db), ["Alex"]);
+
+    db.set_passphrase(Some("first"), Some("second")).unwrap();
+    assert!(matches!(
+        db::open(&path, Some("first")),
+        Err(Error::WrongPassphrase)
+    ));
+    assert_eq!(
+        profiles::list(&db::open(&path, Some("second")).unwrap())
+            .unwrap()
+            .len(),
+        1
+    );
+    assert_eq!(names(&db), ["Alex"]);
+}
+
+#[test]
+fn removing_the_passphrase_leaves_a_plain_file() {
+    let (db, path) = encrypted("decrypt", "secret");
+    let status = db.set_passphrase(Some("secret"), None).unwrap();
+    assert!(!status.encrypted && !status.locked);
+    assert!(!encryption::is_encrypted(&path).unwrap());
+    assert_eq!(
+        profiles::list(&db::open(&path, None).unwrap())
+            .unwrap()
+            .len(),
+        1
+    );
+}
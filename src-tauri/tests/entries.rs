@@ -0,0 +1,68 @@
+use rusqlite::Connection;
+use tauri_app_lib::entries::{self, EntryInput, VolunteerEntry};
+use tauri_app_lib::error::Result;
+use tauri_app_lib::{dates, db, profiles};
+
+/// Logs a shift at the library on `date` from `start` to `end`.
+fn shift(conn: &Connection, date: &str, start: &str, end: &str) -> Result<VolunteerEntry> {
+    let alex = match profiles::list(conn).unwrap().pop() {
+        Some(profile) => profile,
+        None => profiles::create(conn, "Alex").unwrap(),
+    };
+    let input = EntryInput {
+        place: "Library".into(),
+        date: date.into(),
+        start_time: Some(start.into()),
+        end_time: Some(end.into()),
+        ..Default::default()
+    };
+    entries::create(conn, alex.id, &input)
+}
+
+fn new_york() -> Connection {
+    let conn = db::open_in_memory().unwrap();
+    dates::set_timezone(&conn, Some("America/New_York")).unwrap();
+    conn
+}
+
+#[test]
+fn a_bare_end_before_the_start_runs_overnight() {
+    let conn = new_york();
+    let overnight = shift(&conn, "2024-06-01", "22:00", "02:30").unwrap();
+    assert_eq!(overnight.end_time.as_deref(), Some("2024-06-02T02:30"));
+    assert_eq!(overnight.duration_minutes, 270);
+
+    let full = shift(&conn, "2024-06-01", "2024-06-01T22:00", "2024-06-02T01:00").unwrap();
+    assert_eq!(full.duration_minutes, 180);
+}
+
+#[test]
+fn ends_at_or_before_the_start_are_refused() {
+    let conn = new_york();
+    for (start, end) in [
+        ("09:00", "09:00"),
+        ("2024-06-01T09:00", "2024-06-01T08:00"),
+        ("09:00", "2024-06-01T08:59"),
+    ] {
+        let refused = shift(&conn, "2024-06-01", start, end).unwrap_err();
+        assert_eq!(refused.to_string(), "end time must be after start time");
+    }
+    assert!(shift(&conn, "2024-06-01", "2024-06-02T09:00", "10:00").is_err());
+}
+
+#[test]
+fn shifts_across_a_clock_change_count_the_time_worked() {
+    let conn = new_york();
+    // Clocks went forward from 02:00 to 03:00 on 10 March 2024.
+    let spring = shift(&conn, "2024-03-10", "01:00", "04:00").unwrap();
+    assert_eq!(spring.duration_minutes, 120);
+    // And back from 02:00 to 01:00 on 3 November, so 01:00-02:00 happened twice.
+    let fall = shift(&conn, "2024-11-03", "00:00", "03:00").unwrap();
+    assert_eq!(fall.duration_minutes, 240);
+    let overnight = shift(&conn, "2024-11-02", "22:00", "02:00").unwrap();
+    assert_eq!(overnight.duration_minutes, 300);
+    // A shift from the first 01:50 to the second 01:10 is twenty minutes, not a day.
+    let repeated = shift(&conn, "2024-11-03", "01:50", "01:10").unwrap();
+    assert_eq!(repeated.end_time.as_deref(), Some("2024-11-03T01:10"));
+    assert_eq!(repeated.duration_minutes, 20);
+}
@@ -0,0 +1,23 @@
+-- Schema and data as written by the webview before the backend owned migrations.
+CREATE TABLE profiles (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE entries (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  profile_id INTEGER NOT NULL,
+  place TEXT NOT NULL,
+  date TEXT NOT NULL,
+  hours REAL NOT NULL,
+  notes TEXT DEFAULT '',
+  FOREIGN KEY (profile_id) REFERENCES profiles(id)
+);
+
+INSERT INTO profiles (id, name) VALUES (1, 'Alex'), (2, 'Sam');
+
+INSERT INTO entries (profile_id, place, date, hours, notes) VALUES
+  (1, 'Food Bank', '2024-03-02', 2.5, 'Sorted cans'),
+  (1, 'food bank ', '2024-11-16', 3, ''),
+  (1, 'Library', '2025-01-11', 1.5, NULL),
+  (2, 'Animal Shelter', '2025-06-07', 4, 'Dog walking');
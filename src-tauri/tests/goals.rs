@@ -0,0 +1,120 @@
+mod common;
+
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::goals::{self, GoalInput, GoalKind};
+use tauri_app_lib::{db, organizations, profiles};
+
+use common::{day, log};
+
+fn goal(kind: GoalKind, hours: i64, deadline: Option<&str>) -> GoalInput {
+    GoalInput {
+        kind,
+        minutes: hours * 60,
+        organization_id: None,
+        start_date: Some("2024-01-01".into()),
+        deadline: deadline.map(str::to_string),
+    }
+}
+
+#[test]
+fn a_dated_goal_reports_pace_and_projection() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let today = day("2024-03-03");
+    let goal = goals::create(
+        &conn,
+        alex.id,
+        &goal(GoalKind::ByDate, 40, Some("2024-03-30")),
+        today,
+    )
+    .unwrap();
+    log(&conn, alex.id, "Library", "2023-12-31", 50);
+    log(&conn, alex.id, "Library", "2024-01-15", 12);
+    // 8 hours over the last 28 days is 2 hours a week.
+    log(&conn, alex.id, "Library", "2024-02-10", 4);
+    log(&conn, alex.id, "Library", "2024-03-01", 4);
+
+    let forecast = goals::forecast(&conn, goal.id, today).unwrap();
+    assert_eq!(forecast.minutes_done, 20 * 60);
+    assert_eq!(forecast.minutes_remaining, 20 * 60);
+    assert_eq!(forecast.recent_minutes_per_week, 2 * 60);
+    // 20 hours in the 28 days left, today included, is 5 hours a week.
+    assert_eq!(forecast.required_minutes_per_week, Some(5 * 60));
+    // At 2 hours a week, 20 hours take ten weeks.
+    assert_eq!(forecast.projected_completion.as_deref(), Some("2024-05-12"));
+    assert_eq!(forecast.on_track, Some(false));
+    assert!(!forecast.complete);
+}
+
+#[test]
+fn monthly_and_organization_goals_count_their_own_hours() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let today = day("2024-03-20");
+    log(&conn, alex.id, "Library", "2024-02-28", 6);
+    log(&conn, alex.id, "Library", "2024-03-02", 3);
+    log(&conn, alex.id, "Food Bank", "2024-03-09", 5);
+    // Rejected hours count towards no goal until they are corrected.
+    let rejected = log(&conn, alex.id, "Library", "2024-03-16", 4);
+    approval::set_status(&conn, rejected.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, rejected.id, EntryStatus::Rejected, "logged twice").unwrap();
+
+    let monthly = goals::create(&conn, alex.id, &goal(GoalKind::PerMonth, 8, None), today).unwrap();
+    let forecast = goals::forecast(&conn, monthly.id, today).unwrap();
+    assert_eq!(
+        (forecast.from.as_str(), forecast.to.as_deref()),
+        ("2024-03-01", Some("2024-03-31"))
+    );
+    assert_eq!(forecast.minutes_done, 8 * 60);
+    assert!(forecast.complete);
+    assert_eq!(forecast.required_minutes_per_week, Some(0));
+
+    let library = organizations::find_by_name(&conn, "library")
+        .unwrap()
+        .unwrap();
+    let input = GoalInput {
+        organization_id: Some(library.id),
+        ..goal(GoalKind::PerOrganization, 20, None)
+    };
+    let at_library = goals::create(&conn, alex.id, &input, today).unwrap();
+    let forecast = goals::forecast(&conn, at_library.id, today).unwrap();
+    assert_eq!(forecast.minutes_done, 9 * 60);
+    assert_eq!(forecast.required_minutes_per_week, None);
+    assert_eq!(forecast.on_track, None);
+}
+
+#[test]
+fn goals_need_what_their_kind_counts_by() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let today = day("2024-03-20");
+    assert!(goals::create(&conn, alex.id, &goal(GoalKind::ByDate, 10, None), today).is_err());
+    assert!(goals::create(
+        &conn,
+        alex.id,
+        &goal(GoalKind::PerOrganization, 10, None),
+        today
+    )
+    .is_err());
+    assert!(goals::create(
+        &conn,
+        alex.id,
+        &goal(GoalKind::PerMonth, 10, Some("2024-12-31")),
+        today
+    )
+    .is_err());
+    assert!(goals::create(
+        &conn,
+        alex.id,
+        &goal(GoalKind::ByDate, 0, Some("2024-12-31")),
+        today
+    )
+    .is_err());
+    assert!(goals::create(
+        &conn,
+        alex.id,
+        &goal(GoalKind::ByDate, 10, Some("2023-12-31")),
+        today
+    )
+    .is_err());
+}
@@ -0,0 +1,193 @@
+use rusqlite::Connection;
+use tauri_app_lib::{chain, migrations};
+
+const LEGACY_V0: &str = include_str!("fixtures/legacy_v0.sql");
+
+fn legacy_db() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(LEGACY_V0).unwrap();
+    conn
+}
+
+fn count(conn: &Connection, table: &str) -> i64 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get(0))
+        .unwrap()
+}
+
+#[test]
+fn migrations_are_ordered() {
+    let versions: Vec<u32> = migrations::MIGRATIONS.iter().map(|m| m.version).collect();
+    let expected: Vec<u32> = (1..=versions.len() as u32).collect();
+    assert_eq!(versions, expected);
+}
+
+#[test]
+fn fresh_database_reaches_latest() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    assert_eq!(migrations::run(&mut conn).unwrap(), 0);
+    assert_eq!(
+        migrations::current_version(&conn).unwrap(),
+        migrations::latest_version()
+    );
+}
+
+#[test]
+fn legacy_snapshot_upgrades_without_losing_rows() {
+    let mut conn = legacy_db();
+    migrations::run(&mut conn).unwrap();
+    assert_eq!(
+        migrations::current_version(&conn).unwrap(),
+        migrations::latest_version()
+    );
+    assert_eq!(count(&conn, "profiles"), 2);
+    assert_eq!(count(&conn, "entries"), 4);
+}
+
+#[test]
+fn legacy_places_fold_into_organizations() {
+    let mut conn = legacy_db();
+    migrations::run(&mut conn).unwrap();
+    let names: Vec<String> = conn
+        .prepare("SELECT name FROM organizations ORDER BY id")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(names, ["Food Bank", "Library", "Animal Shelter"]);
+
+    let food_bank_entries: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM entries e JOIN organizations o ON o.id = e.organization_id
+             WHERE o.name = 'Food Bank' AND e.place = 'Food Bank'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(food_bank_entries, 2);
+}
+
+#[test]
+fn legacy_hours_become_whole_minutes() {
+    let mut conn = legacy_db();
+    migrations::run(&mut conn).unwrap();
+    let minutes: Vec<i64> = conn
+        .prepare("SELECT duration_minutes FROM entries ORDER BY id")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(minutes, [150, 180, 90, 240]);
+    let untimed: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM entries WHERE start_time IS NULL AND end_time IS NULL",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(untimed, 4);
+}
+
+#[test]
+fn legacy_hours_that_make_no_minute_are_rejected_not_invented() {
+    let mut conn = legacy_db();
+    conn.execute_batch(
+        "INSERT INTO entries (profile_id, place, date, hours) VALUES
+           (2, 'Park', '2025-06-08', 0),
+           (2, 'Park', '2025-06-09', -1.5);",
+    )
+    .unwrap();
+    migrations::run(&mut conn).unwrap();
+    let rows: Vec<(String, String)> = conn
+        .prepare("SELECT status, status_reason FROM entries ORDER BY id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert!(rows[..4].iter().all(|(status, _)| status == "approved"));
+    assert_eq!(rows[4].0, "rejected");
+    assert!(rows[4].1.contains("\"0.0\""), "{}", rows[4].1);
+    assert_eq!(rows[5].0, "rejected");
+    assert!(rows[5].1.contains("\"-1.5\""), "{}", rows[5].1);
+
+    let rejected: Vec<i64> = conn
+        .prepare(
+            "SELECT entry_id FROM entry_revisions WHERE reason = 'hours were not positive'
+             AND status = 'rejected' ORDER BY id",
+        )
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(rejected, [5, 6]);
+    let verification = chain::verify(&conn, 2, None).unwrap();
+    assert!(verification.intact(), "{:?}", verification.problems);
+}
+
+#[test]
+fn rerunning_is_a_no_op() {
+    let mut conn = legacy_db();
+    migrations::run(&mut conn).unwrap();
+    let latest = migrations::latest_version();
+    assert_eq!(migrations::run(&mut conn).unwrap(), latest);
+    assert_eq!(migrations::current_version(&conn).unwrap(), latest);
+}
+
+#[test]
+fn newer_schema_is_refused() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    let future = migrations::latest_version() + 1;
+    conn.pragma_update(None, "user_version", future).unwrap();
+    assert!(migrations::run(&mut conn).is_err());
+}
+
+#[test]
+fn legacy_dates_are_repaired_or_flagged_with_a_revision() {
+    let mut conn = legacy_db();
+    conn.execute_batch(
+        "INSERT INTO entries (profile_id, place, date, hours) VALUES
+           (2, 'Park', '2025/6/8', 1),
+           (2, 'Park', 'June 9', 1);",
+    )
+    .unwrap();
+    migrations::run(&mut conn).unwrap();
+    let rows: Vec<(String, String, String)> = conn
+        .prepare("SELECT date, status, status_reason FROM entries WHERE id > 4 ORDER BY id")
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        rows[0],
+        ("2025-06-08".into(), "approved".into(), String::new())
+    );
+    assert_eq!(rows[1].0, "June 9");
+    assert_eq!(rows[1].1, "rejected");
+    assert!(rows[1].2.contains("\"June 9\""), "{}", rows[1].2);
+
+    let reasons: Vec<(i64, String)> = conn
+        .prepare(
+            "SELECT entry_id, reason FROM entry_revisions WHERE reason LIKE 'date %' ORDER BY id",
+        )
+        .unwrap()
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        reasons,
+        [
+            (5, "date written as YYYY-MM-DD".to_string()),
+            (6, "date could not be read".to_string())
+        ]
+    );
+    // Revisions the migrations recorded are sealed with them.
+    for profile_id in [1, 2] {
+        let verification = chain::verify(&conn, profile_id, None).unwrap();
+        assert!(verification.intact(), "{:?}", verification.problems);
+    }
+}
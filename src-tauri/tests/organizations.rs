@@ -0,0 +1,166 @@
+mod common;
+
+use chrono::NaiveDate;
+use rusqlite::Connection;
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::entries::{self, EntryFilter};
+use tauri_app_lib::goals::{self, GoalInput, GoalKind};
+use tauri_app_lib::history::{Change, History};
+use tauri_app_lib::organizations::{self, OrganizationInput};
+use tauri_app_lib::totals::{self, GroupBy};
+use tauri_app_lib::{db, profiles, revisions};
+
+use common::log;
+
+fn hours_by_organization(conn: &Connection, profile_id: i64) -> Vec<(String, i64)> {
+    totals::totals(
+        conn,
+        &EntryFilter::profile(profile_id),
+        GroupBy::Organization,
+    )
+    .unwrap()
+    .into_iter()
+    .map(|t| (t.label, t.minutes / 60))
+    .collect()
+}
+
+#[test]
+fn merging_moves_entries_totals_and_goals_to_the_kept_organization() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    log(&conn, alex.id, "Food Bank", "2024-03-02", 3);
+    log(&conn, alex.id, "City Food Bank", "2024-03-02", 2);
+    log(&conn, alex.id, "Library", "2024-03-02", 1);
+    let kept = organizations::find_by_name(&conn, "Food Bank")
+        .unwrap()
+        .unwrap();
+    let merged = organizations::find_by_name(&conn, "City Food Bank")
+        .unwrap()
+        .unwrap();
+    let details = OrganizationInput {
+        name: merged.name.clone(),
+        contact_name: "Pat Kim".into(),
+        notes: "Loading dock at the back".into(),
+        ..Default::default()
+    };
+    organizations::update(&mut conn, merged.id, &details).unwrap();
+    let goal = GoalInput {
+        kind: GoalKind::PerOrganization,
+        minutes: 600,
+        organization_id: Some(merged.id),
+        start_date: Some("2024-01-01".into()),
+        deadline: None,
+    };
+    let today = NaiveDate::from_ymd_opt(2024, 3, 9).unwrap();
+    let goal = goals::create(&conn, alex.id, &goal, today).unwrap();
+
+    let sam = profiles::create(&conn, "Sam").unwrap();
+    log(&conn, sam.id, "Library", "2024-03-02", 1);
+    // Only Alex has hours at either food bank, so only Alex's session must be unlocked.
+    let touched = organizations::profiles_using(&conn, &[kept.id, merged.id]).unwrap();
+    assert_eq!(touched, [alex.id]);
+
+    assert!(organizations::merge(&mut conn, kept.id, kept.id).is_err());
+    let result = organizations::merge(&mut conn, kept.id, merged.id)
+        .unwrap()
+        .organization;
+    assert_eq!(result.id, kept.id);
+    assert_eq!(result.contact_name, "Pat Kim");
+    assert_eq!(result.notes, "Loading dock at the back");
+    assert!(organizations::get(&conn, merged.id).is_err());
+    assert_eq!(organizations::list(&conn).unwrap().len(), 2);
+
+    assert_eq!(
+        hours_by_organization(&conn, alex.id),
+        [("Food Bank".to_string(), 5), ("Library".to_string(), 1)]
+    );
+    let moved = entries::list(&conn, alex.id).unwrap();
+    assert_eq!(moved.iter().filter(|e| e.place == "Food Bank").count(), 2);
+    let forecast = goals::forecast(&conn, goal.id, today).unwrap();
+    assert_eq!(forecast.goal.organization_id, Some(kept.id));
+    assert_eq!(forecast.minutes_done, 5 * 60);
+}
+
+#[test]
+fn renames_reach_the_entries_and_used_organizations_stay() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    log(&conn, alex.id, "library", "2024-03-02", 1);
+    let library = organizations::find_by_name(&conn, " LIBRARY ")
+        .unwrap()
+        .unwrap();
+    let renamed = OrganizationInput {
+        name: "Central  Library".into(),
+        ..Default::default()
+    };
+    organizations::update(&mut conn, library.id, &renamed).unwrap();
+    assert_eq!(
+        entries::list(&conn, alex.id).unwrap()[0].place,
+        "Central Library"
+    );
+
+    assert!(organizations::delete(&mut conn, library.id).is_err());
+    let park = organizations::find_or_create(&conn, "Park").unwrap();
+    organizations::delete(&mut conn, park.id).unwrap();
+    assert_eq!(organizations::list(&conn).unwrap().len(), 1);
+}
+
+#[test]
+fn merges_can_be_undone_and_leave_approved_entries_alone() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let approved = log(&conn, alex.id, "Food Bank", "2024-03-02", 3);
+    let moved = log(&conn, alex.id, "City Food Bank", "2024-03-02", 2);
+    let kept = organizations::find_by_name(&conn, "City Food Bank")
+        .unwrap()
+        .unwrap();
+    let food_bank = organizations::find_by_name(&conn, "Food Bank")
+        .unwrap()
+        .unwrap();
+    approval::set_status(&conn, approved.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, approved.id, EntryStatus::Approved, "").unwrap();
+    assert!(organizations::merge(&mut conn, kept.id, food_bank.id).is_err());
+    let renamed = OrganizationInput {
+        name: "Pantry".into(),
+        ..Default::default()
+    };
+    assert!(organizations::update(&mut conn, food_bank.id, &renamed).is_err());
+    assert_eq!(entries::get(&conn, approved.id).unwrap().place, "Food Bank");
+
+    let history = History::default();
+    let merge = organizations::merge(&mut conn, food_bank.id, kept.id).unwrap();
+    assert_eq!(merge.entry_ids, [moved.id]);
+    history.record(Change::MergeOrganizations { merge });
+    let allow = |_: &Connection, _: &Change| Ok(());
+    history.undo(&mut conn, allow).unwrap();
+    assert_eq!(
+        hours_by_organization(&conn, alex.id),
+        [
+            ("Food Bank".to_string(), 3),
+            ("City Food Bank".to_string(), 2)
+        ]
+    );
+    assert_eq!(
+        organizations::get(&conn, kept.id).unwrap().name,
+        "City Food Bank"
+    );
+    let reasons: Vec<_> = revisions::list(&conn, moved.id)
+        .unwrap()
+        .into_iter()
+        .map(|revision| revision.reason)
+        .collect();
+    assert_eq!(
+        reasons,
+        [
+            "",
+            "organization City Food Bank merged into Food Bank",
+            "undo"
+        ]
+    );
+
+    history.redo(&mut conn, allow).unwrap();
+    assert_eq!(
+        hours_by_organization(&conn, alex.id),
+        [("Food Bank".to_string(), 5)]
+    );
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use tauri_app_lib::entries::EntryFilter;
+use tauri_app_lib::periods::{self, PeriodScheme, Term};
+use tauri_app_lib::totals::{self, GroupBy};
+use tauri_app_lib::{db, profiles, report};
+
+use common::{day, log};
+
+fn term(name: &str, from: &str, to: &str) -> Term {
+    Term {
+        name: name.into(),
+        from: from.into(),
+        to: to.into(),
+    }
+}
+
+#[test]
+fn years_can_start_in_any_month() {
+    let school = PeriodScheme::SchoolYear { start_month: 8 };
+    let period = school.period_of(day("2025-03-14")).unwrap();
+    assert_eq!(
+        (
+            period.label.as_str(),
+            period.from.as_str(),
+            period.to.as_str()
+        ),
+        ("2024-25", "2024-08-01", "2025-07-31")
+    );
+    assert_eq!(
+        school.period_of(day("2025-08-01")).unwrap().label,
+        "2025-26"
+    );
+
+    let fiscal = PeriodScheme::FiscalYear { start_month: 10 };
+    let period = fiscal.period_of(day("2024-10-01")).unwrap();
+    assert_eq!(
+        (period.label.as_str(), period.to.as_str()),
+        ("FY2025", "2025-09-30")
+    );
+    assert_eq!(
+        PeriodScheme::Calendar
+            .period_of(day("2024-02-29"))
+            .unwrap()
+            .label,
+        "2024"
+    );
+}
+
+#[test]
+fn totals_and_reports_follow_the_profile_periods() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    log(&conn, alex.id, "Library", "2024-07-31", 2);
+    log(&conn, alex.id, "Library", "2024-08-01", 3);
+    log(&conn, alex.id, "Library", "2025-01-10", 4);
+    let filter = EntryFilter::profile(alex.id);
+
+    let by_year = totals::totals(&conn, &filter, GroupBy::Period).unwrap();
+    assert_eq!(by_year.len(), 2);
+
+    periods::set_scheme(&conn, alex.id, &PeriodScheme::SchoolYear { start_month: 8 }).unwrap();
+    let by_school_year = totals::totals(&conn, &filter, GroupBy::Period).unwrap();
+    let summary: Vec<_> = by_school_year
+        .iter()
+        .map(|t| (t.label.as_str(), t.minutes / 60))
+        .collect();
+    assert_eq!(summary, [("2024-25", 7), ("2023-24", 2)]);
+    let labels: Vec<_> = periods::list(&conn, alex.id)
+        .unwrap()
+        .into_iter()
+        .map(|p| p.label)
+        .collect();
+    assert_eq!(labels, ["2024-25", "2023-24"]);
+
+    let period = periods::find(&conn, alex.id, "2024-25").unwrap();
+    assert_eq!(period, periods::find(&conn, alex.id, "2024-12-25").unwrap());
+    let lines = report::lines(&conn, alex.id, Some(&period.from), Some(&period.to)).unwrap();
+    assert_eq!(lines[3], "Period:    2024-25 (2024-08-01 to 2025-07-31)");
+    let lines = report::lines(&conn, alex.id, None, None).unwrap();
+    assert!(lines.iter().any(|line| line == "Hours by period"));
+}
+
+#[test]
+fn terms_are_checked_and_may_leave_gaps() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let overlapping = PeriodScheme::Terms {
+        terms: vec![
+            term("Fall", "2024-09-01", "2024-12-20"),
+            term("Spring", "2024-12-15", "2025-05-31"),
+        ],
+    };
+    assert!(periods::set_scheme(&conn, alex.id, &overlapping).is_err());
+    assert!(periods::set_scheme(&conn, alex.id, &PeriodScheme::Terms { terms: vec![] }).is_err());
+    assert!(periods::set_scheme(
+        &conn,
+        alex.id,
+        &PeriodScheme::FiscalYear { start_month: 13 }
+    )
+    .is_err());
+
+    let terms = PeriodScheme::Terms {
+        terms: vec![
+            term(" Spring ", "2025-01-06", "2025-05-31"),
+            term("Fall", "2024-09-01", "2024-12-20"),
+        ],
+    };
+    let saved = periods::set_scheme(&conn, alex.id, &terms).unwrap();
+    assert_eq!(
+        saved,
+        PeriodScheme::Terms {
+            terms: vec![
+                term("Fall", "2024-09-01", "2024-12-20"),
+                term("Spring", "2025-01-06", "2025-05-31"),
+            ],
+        }
+    );
+    assert_eq!(periods::scheme(&conn, alex.id).unwrap(), saved);
+
+    log(&conn, alex.id, "Library", "2024-10-01", 5);
+    log(&conn, alex.id, "Library", "2024-12-28", 1);
+    let by_term = totals::totals(&conn, &EntryFilter::profile(alex.id), GroupBy::Period).unwrap();
+    let summary: Vec<_> = by_term
+        .iter()
+        .map(|t| (t.key.as_str(), t.minutes / 60))
+        .collect();
+    assert_eq!(summary, [("2024-09-01", 5), ("", 1)]);
+    // Every term can be chosen, even one without entries yet.
+    assert_eq!(periods::list(&conn, alex.id).unwrap().len(), 2);
+}
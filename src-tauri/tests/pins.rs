@@ -0,0 +1,102 @@
+use std::thread::sleep;
+use std::time::Duration;
+
+use rusqlite::Connection;
+use tauri_app_lib::error::Error;
+use tauri_app_lib::pins::{self, Sessions, FREE_ATTEMPTS};
+use tauri_app_lib::{db, profiles};
+
+/// A log with Alex behind the PIN 1234 and Sam without one.
+fn seeded() -> (Connection, i64, i64) {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let sam = profiles::create(&conn, "Sam").unwrap();
+    pins::set(&conn, alex.id, None, Some("1234")).unwrap();
+    (conn, alex.id, sam.id)
+}
+
+/// Moves the last wrong PIN `seconds` into the past.
+fn wait(conn: &Connection, seconds: i64) {
+    conn.execute(
+        "UPDATE profiles SET pin_failed_at = pin_failed_at - ?1",
+        [seconds],
+    )
+    .unwrap();
+}
+
+#[test]
+fn sessions_lock_again_once_idle() {
+    let (conn, alex, sam) = seeded();
+    let sessions = Sessions::new(Duration::from_millis(200));
+    assert!(matches!(
+        sessions.require(&conn, alex),
+        Err(Error::ProfileLocked)
+    ));
+    sessions.require(&conn, sam).unwrap();
+    assert!(matches!(
+        sessions.unlock(&conn, alex, "4321"),
+        Err(Error::WrongPin)
+    ));
+
+    sessions.unlock(&conn, alex, "1234").unwrap();
+    sessions.require_all(&conn).unwrap();
+    sleep(Duration::from_millis(300));
+    assert!(sessions.is_locked(&conn, alex).unwrap());
+    assert!(sessions.require_each(&conn, [sam, alex]).is_err());
+
+    sessions.unlock(&conn, alex, "1234").unwrap();
+    sessions.lock_all();
+    assert!(sessions.is_locked(&conn, alex).unwrap());
+    assert!(!sessions.is_locked(&conn, sam).unwrap());
+}
+
+#[test]
+fn wrong_pins_make_each_try_wait_longer() {
+    let (conn, alex, _) = seeded();
+    for _ in 0..FREE_ATTEMPTS {
+        assert!(matches!(
+            pins::check(&conn, alex, Some("0000")),
+            Err(Error::WrongPin)
+        ));
+    }
+    // Even the right PIN waits, so guesses can't be told apart from it.
+    assert!(matches!(
+        pins::check(&conn, alex, Some("1234")),
+        Err(Error::PinBackoff(1..=30))
+    ));
+    wait(&conn, 30);
+    assert!(matches!(
+        pins::check(&conn, alex, Some("0000")),
+        Err(Error::WrongPin)
+    ));
+    wait(&conn, 30);
+    assert!(matches!(
+        pins::check(&conn, alex, Some("1234")),
+        Err(Error::PinBackoff(1..=30))
+    ));
+
+    wait(&conn, 30);
+    pins::check(&conn, alex, Some("1234")).unwrap();
+    // The right PIN starts the count over.
+    assert!(matches!(
+        pins::check(&conn, alex, Some("0000")),
+        Err(Error::WrongPin)
+    ));
+    pins::check(&conn, alex, Some("1234")).unwrap();
+}
+
+#[test]
+fn changing_a_pin_needs_the_current_one() {
+    let (conn, alex, sam) = seeded();
+    assert!(pins::set(&conn, alex, Some("9999"), Some("5678")).is_err());
+    assert!(pins::set(&conn, sam, None, Some("12")).is_err());
+    pins::set(&conn, alex, Some("1234"), Some("5678")).unwrap();
+    assert!(matches!(
+        pins::check(&conn, alex, None),
+        Err(Error::ProfileLocked)
+    ));
+    pins::check(&conn, alex, Some("5678")).unwrap();
+
+    pins::set(&conn, alex, Some("5678"), None).unwrap();
+    pins::check(&conn, alex, None).unwrap();
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use chrono::NaiveDate;
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::programs::{self, Program, Rule};
+use tauri_app_lib::{db, profiles};
+
+use common::log;
+
+const SCHOOL_YEAR: &str = r#"
+name = "Honor society"
+from = "2024-09-01"
+deadline = "2025-05-31"
+
+[[rules]]
+kind = "min_hours"
+hours = 40
+
+[[rules]]
+kind = "min_organizations"
+count = 2
+
+[[rules]]
+kind = "max_hours_from"
+hours = 10
+organizations = ["Family Farm"]
+"#;
+
+fn today() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2025, 5, 1).unwrap()
+}
+
+#[test]
+fn programs_read_from_toml_or_json() {
+    let from_toml = Program::parse(SCHOOL_YEAR).unwrap();
+    let json = serde_json::to_string(&from_toml).unwrap();
+    assert_eq!(Program::parse(&json).unwrap(), from_toml);
+    assert_eq!(from_toml.rules[0], Rule::MinHours { hours: 40.0 });
+
+    assert!(Program::parse("name = \"Empty\"\nrules = []").is_err());
+    assert!(Program::parse("name = \"Bad\"\n[[rules]]\nkind = \"min_hours\"\nhours = 0").is_err());
+    assert!(Program::parse("name = \"Odd\"\n[[rules]]\nkind = \"most_smiles\"").is_err());
+}
+
+#[test]
+fn progress_reports_unmet_rules_and_capped_hours() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let enrollment =
+        programs::enroll(&conn, alex.id, &Program::parse(SCHOOL_YEAR).unwrap()).unwrap();
+    log(&conn, alex.id, "Family Farm", "2024-10-05", 16);
+    log(&conn, alex.id, "Family Farm", "2024-08-30", 20);
+
+    let progress = programs::progress(&conn, enrollment.id, today()).unwrap();
+    assert_eq!(progress.counted_minutes, 10 * 60);
+    assert_eq!(progress.required_minutes, Some(40 * 60));
+    assert_eq!(progress.days_left, Some(30));
+    let unmet: Vec<_> = progress.unmet().map(|r| r.detail.as_str()).collect();
+    assert_eq!(unmet, ["10 of 40 hours", "1 of 2 organizations"]);
+    assert_eq!(
+        progress.rules[2].detail,
+        "16 of at most 10 hours; 6 not counted"
+    );
+
+    log(&conn, alex.id, "Library", "2025-03-01", 30);
+    let progress = programs::progress(&conn, enrollment.id, today()).unwrap();
+    assert_eq!(progress.counted_minutes, 40 * 60);
+    assert!(progress.complete);
+}
+
+#[test]
+fn overlapping_caps_leave_hours_out_once() {
+    let overlapping = "name = \"Twice\"
+[[rules]]
+kind = \"max_hours_from\"
+hours = 2
+organizations = [\"Family Farm\"]
+[[rules]]
+kind = \"max_hours_from\"
+hours = 1
+organizations = [\"family  farm\", \"Bakery\"]";
+    assert!(Program::parse(overlapping).is_err());
+
+    // A copy enrolled before overlaps were refused still counts sensibly.
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let program = Program {
+        rules: vec![
+            Rule::MaxHoursFrom {
+                hours: 2.0,
+                organizations: vec!["Family Farm".into()],
+            },
+            Rule::MaxHoursFrom {
+                hours: 1.0,
+                organizations: vec!["Family Farm".into(), "Bakery".into()],
+            },
+        ],
+        ..Program::parse(SCHOOL_YEAR).unwrap()
+    };
+    let refused = programs::enroll(&conn, alex.id, &program).unwrap_err();
+    assert!(refused.to_string().contains("more than once"));
+    conn.execute(
+        "INSERT INTO program_enrollments (profile_id, name, program) VALUES (?1, 'Old', ?2)",
+        rusqlite::params![alex.id, serde_json::to_string(&program).unwrap()],
+    )
+    .unwrap();
+    let enrollment = programs::list(&conn, alex.id).unwrap().remove(0);
+    log(&conn, alex.id, "Family Farm", "2024-10-05", 5);
+    log(&conn, alex.id, "Bakery", "2024-10-12", 3);
+
+    let progress = programs::progress(&conn, enrollment.id, today()).unwrap();
+    // Two farm hours and one bakery hour count.
+    assert_eq!(progress.counted_minutes, 3 * 60);
+}
+
+#[test]
+fn approved_only_programs_skip_unapproved_entries() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let program = Program {
+        approved_only: true,
+        ..Program::parse(SCHOOL_YEAR).unwrap()
+    };
+    let enrollment = programs::enroll(&conn, alex.id, &program).unwrap();
+    let approved = log(&conn, alex.id, "Library", "2024-11-02", 5);
+    log(&conn, alex.id, "Library", "2024-11-09", 7);
+    approval::set_status(&conn, approved.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, approved.id, EntryStatus::Approved, "").unwrap();
+
+    let progress = programs::progress(&conn, enrollment.id, today()).unwrap();
+    assert_eq!(progress.counted_minutes, 5 * 60);
+
+    // Enrolling again under the same name updates the profile's copy.
+    programs::enroll(&conn, alex.id, &Program::parse(SCHOOL_YEAR).unwrap()).unwrap();
+    assert_eq!(programs::list(&conn, alex.id).unwrap().len(), 1);
+    let progress = programs::progress(&conn, enrollment.id, today()).unwrap();
+    assert_eq!(progress.counted_minutes, 12 * 60);
+}
@@ -0,0 +1,142 @@
+use chrono::NaiveDate;
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::entries::{self, EntryInput};
+use tauri_app_lib::pvsa::{self, AgeGroup, Award, Level, Window};
+use tauri_app_lib::{db, profiles};
+
+fn day(value: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+}
+
+fn hours(hours: i64) -> i64 {
+    hours * 60
+}
+
+#[test]
+fn ages_map_to_their_groups() {
+    assert_eq!(AgeGroup::for_age(4), None);
+    assert_eq!(AgeGroup::for_age(5), Some(AgeGroup::Kids));
+    assert_eq!(AgeGroup::for_age(10), Some(AgeGroup::Kids));
+    assert_eq!(AgeGroup::for_age(11), Some(AgeGroup::Teens));
+    assert_eq!(AgeGroup::for_age(15), Some(AgeGroup::Teens));
+    assert_eq!(AgeGroup::for_age(16), Some(AgeGroup::YoungAdults));
+    assert_eq!(AgeGroup::for_age(25), Some(AgeGroup::YoungAdults));
+    assert_eq!(AgeGroup::for_age(26), Some(AgeGroup::Adults));
+    assert_eq!(AgeGroup::for_age(80), Some(AgeGroup::Adults));
+}
+
+#[test]
+fn age_counts_whole_years_to_the_birthday() {
+    assert_eq!(pvsa::age_on(day("2010-06-15"), day("2024-06-14")), 13);
+    assert_eq!(pvsa::age_on(day("2010-06-15"), day("2024-06-15")), 14);
+    assert_eq!(pvsa::age_on(day("2008-02-29"), day("2024-02-28")), 15);
+    assert_eq!(pvsa::age_on(day("2008-02-29"), day("2024-02-29")), 16);
+}
+
+#[test]
+fn every_age_group_has_its_own_levels() {
+    let cases = [
+        (AgeGroup::Kids, [26, 50, 75]),
+        (AgeGroup::Teens, [50, 75, 100]),
+        (AgeGroup::YoungAdults, [100, 175, 250]),
+        (AgeGroup::Adults, [100, 250, 500]),
+    ];
+    for (group, [bronze, silver, gold]) in cases {
+        assert_eq!(
+            pvsa::award(group, hours(bronze) - 1),
+            Award {
+                level: None,
+                next_level: Some(Level::Bronze),
+                minutes_to_next: Some(1),
+            },
+            "{group:?}"
+        );
+        assert_eq!(
+            pvsa::award(group, hours(bronze)),
+            Award {
+                level: Some(Level::Bronze),
+                next_level: Some(Level::Silver),
+                minutes_to_next: Some(hours(silver - bronze)),
+            },
+            "{group:?}"
+        );
+        assert_eq!(
+            pvsa::award(group, hours(silver)).level,
+            Some(Level::Silver),
+            "{group:?}"
+        );
+        assert_eq!(
+            pvsa::award(group, hours(gold) - 30).minutes_to_next,
+            Some(30),
+            "{group:?}"
+        );
+        assert_eq!(
+            pvsa::award(group, hours(gold) + 600),
+            Award {
+                level: Some(Level::Gold),
+                next_level: None,
+                minutes_to_next: None,
+            },
+            "{group:?}"
+        );
+    }
+}
+
+#[test]
+fn windows_span_at_most_twelve_months() {
+    let rolling = Window::Rolling {
+        end: "2024-06-30".into(),
+    };
+    assert_eq!(
+        rolling.bounds().unwrap(),
+        (day("2023-07-01"), day("2024-06-30"))
+    );
+    let school_year = Window::Fixed {
+        from: "2023-09-01".into(),
+        to: "2024-08-31".into(),
+    };
+    assert!(school_year.bounds().is_ok());
+    let too_long = Window::Fixed {
+        from: "2023-08-31".into(),
+        to: "2024-08-31".into(),
+    };
+    assert!(too_long.bounds().is_err());
+}
+
+#[test]
+fn progress_counts_hours_in_the_window_at_the_age_then() {
+    let conn = db::open_in_memory().unwrap();
+    let sam = profiles::create(&conn, "Sam").unwrap();
+    let window = Window::Rolling {
+        end: "2024-06-30".into(),
+    };
+    assert!(pvsa::progress(&conn, sam.id, &window).is_err());
+    profiles::set_birthdate(&conn, sam.id, Some("2008-07-15")).unwrap();
+
+    for (date, minutes) in [
+        ("2023-06-30", hours(40)),
+        ("2023-07-01", hours(30)),
+        ("2024-06-30", hours(50)),
+        ("2024-06-29", hours(20)),
+    ] {
+        let input = EntryInput {
+            place: "Library".into(),
+            date: date.into(),
+            duration_minutes: Some(minutes),
+            ..Default::default()
+        };
+        let entry = entries::create(&conn, sam.id, &input).unwrap();
+        // Rejected hours don't count towards an award until they are corrected.
+        if date == "2024-06-29" {
+            approval::set_status(&conn, entry.id, EntryStatus::Submitted, "").unwrap();
+            approval::set_status(&conn, entry.id, EntryStatus::Rejected, "no such shift").unwrap();
+        }
+    }
+
+    let progress = pvsa::progress(&conn, sam.id, &window).unwrap();
+    assert_eq!(progress.age, 15);
+    assert_eq!(progress.age_group, Some(AgeGroup::Teens));
+    assert_eq!(progress.minutes, hours(80));
+    assert_eq!(progress.award.level, Some(Level::Silver));
+    assert_eq!(progress.award.minutes_to_next, Some(hours(20)));
+}
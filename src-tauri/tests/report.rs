@@ -0,0 +1,53 @@
+use tauri_app_lib::entries::{self, EntryInput};
+use tauri_app_lib::{chain, db, profiles, report};
+
+const GOLDEN_2024: &str = include_str!("fixtures/report_2024.txt");
+
+fn seeded() -> (rusqlite::Connection, i64) {
+    let conn = db::open_in_memory().unwrap();
+    let profile = profiles::create(&conn, "Alex").unwrap();
+    for (place, date, minutes, notes) in [
+        ("Park Cleanup", "2023-12-31", 120, ""),
+        ("Food Bank", "2024-03-02", 150, "Sorted cans"),
+        (
+            "Library",
+            "2024-06-01",
+            90,
+            "Read to kids at story time, then reshelved the whole picture-book section",
+        ),
+        ("food bank ", "2024-11-16", 180, ""),
+        ("Library", "2025-01-11", 60, ""),
+    ] {
+        let input = EntryInput {
+            place: place.into(),
+            date: date.into(),
+            duration_minutes: Some(minutes),
+            notes: notes.into(),
+            ..Default::default()
+        };
+        entries::create(&conn, profile.id, &input).unwrap();
+    }
+    (conn, profile.id)
+}
+
+#[test]
+fn report_text_matches_golden() {
+    let (conn, profile_id) = seeded();
+    let lines = report::lines(&conn, profile_id, Some("2024-01-01"), Some("2024-12-31")).unwrap();
+    // The seal covers when each entry was recorded, so it differs from run to run.
+    let head = chain::head(&conn, profile_id).unwrap().unwrap();
+    let golden = GOLDEN_2024.replace("{chain_head}", &head.hash);
+    let expected: Vec<&str> = golden.lines().collect();
+    assert_eq!(lines, expected);
+}
+
+#[test]
+fn pdf_contains_report_text() {
+    let (conn, profile_id) = seeded();
+    let pdf = report::render_pdf(&conn, profile_id, Some("2024-01-01"), Some("2024-12-31")).unwrap();
+    let text = String::from_utf8_lossy(&pdf);
+    assert!(text.starts_with("%PDF-1.4"));
+    assert!(text.trim_end().ends_with("%%EOF"));
+    assert!(text.contains("(Volunteer: Alex) Tj"));
+    assert!(text.contains("(Total hours                                   7.00) Tj"));
+}
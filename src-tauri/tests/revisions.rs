@@ -0,0 +1,123 @@
+use rusqlite::Connection;
+use tauri_app_lib::entries::{self, EntryInput};
+use tauri_app_lib::revisions::{self, Action};
+use tauri_app_lib::{chain, db, organizations, profiles, timer, trash};
+
+fn input(place: &str, minutes: i64, reason: &str) -> EntryInput {
+    EntryInput {
+        place: place.into(),
+        date: "2024-03-02".into(),
+        duration_minutes: Some(minutes),
+        reason: reason.into(),
+        ..Default::default()
+    }
+}
+
+fn actions(conn: &Connection, entry_id: i64) -> Vec<(Action, String)> {
+    revisions::list(conn, entry_id)
+        .unwrap()
+        .into_iter()
+        .map(|r| (r.action, r.reason))
+        .collect()
+}
+
+#[test]
+fn every_change_adds_a_revision() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("Library", 60, "")).unwrap();
+    entries::update(&conn, entry.id, &input("Library", 90, "stayed late")).unwrap();
+    entries::delete(&conn, entry.id, "logged twice").unwrap();
+    trash::restore_entry(&conn, entry.id).unwrap();
+
+    assert_eq!(
+        actions(&conn, entry.id),
+        [
+            (Action::Create, String::new()),
+            (Action::Update, "stayed late".into()),
+            (Action::Delete, "logged twice".into()),
+            (Action::Restore, String::new()),
+        ]
+    );
+    let minutes: Vec<i64> = revisions::list(&conn, entry.id)
+        .unwrap()
+        .iter()
+        .map(|r| r.duration_minutes)
+        .collect();
+    assert_eq!(minutes, [60, 90, 90, 90]);
+}
+
+#[test]
+fn revert_brings_back_an_earlier_revision() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("Library", 60, "")).unwrap();
+    entries::update(&conn, entry.id, &input("Food Bank", 120, "")).unwrap();
+    let first = revisions::list(&conn, entry.id).unwrap()[0].id;
+
+    let reverted = revisions::revert(&conn, entry.id, first, "wrong shift").unwrap();
+    assert_eq!(
+        (reverted.place.as_str(), reverted.duration_minutes),
+        ("Library", 60)
+    );
+    let history = revisions::list(&conn, entry.id).unwrap();
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[2].action, Action::Revert);
+    assert_eq!(
+        history[2].reason,
+        format!("reverted to revision {first}: wrong shift")
+    );
+
+    let other = entries::create(&conn, alex.id, &input("Park", 30, "")).unwrap();
+    assert!(revisions::revert(&conn, other.id, first, "").is_err());
+}
+
+#[test]
+fn revisions_are_append_only_until_purged() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("Library", 60, "")).unwrap();
+
+    assert!(conn
+        .execute("UPDATE entry_revisions SET duration_minutes = 600", [])
+        .is_err());
+    assert!(conn.execute("DELETE FROM entry_revisions", []).is_err());
+
+    entries::delete(&conn, entry.id, "").unwrap();
+    trash::purge_entry(&conn, entry.id, timer::now()).unwrap();
+    assert!(revisions::list(&conn, entry.id).is_err());
+}
+
+#[test]
+fn a_change_whose_revision_fails_is_not_made() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = entries::create(&conn, alex.id, &input("Library", 60, "")).unwrap();
+    conn.execute_batch(
+        "CREATE TEMP TRIGGER refuse_revisions BEFORE INSERT ON entry_revisions BEGIN
+            SELECT RAISE(ABORT, 'refused');
+        END;",
+    )
+    .unwrap();
+
+    assert!(entries::create(&conn, alex.id, &input("School", 30, "")).is_err());
+    assert_eq!(entries::list(&conn, alex.id).unwrap().len(), 1);
+    assert!(organizations::find_by_name(&conn, "School")
+        .unwrap()
+        .is_none());
+    assert!(entries::update(&conn, entry.id, &input("Library", 90, "")).is_err());
+    assert_eq!(entries::get(&conn, entry.id).unwrap().duration_minutes, 60);
+    assert!(entries::delete(&conn, entry.id, "").is_err());
+    assert!(entries::get(&conn, entry.id).is_ok());
+
+    conn.execute("DROP TRIGGER refuse_revisions", []).unwrap();
+    entries::delete(&conn, entry.id, "").unwrap();
+    conn.execute_batch(
+        "CREATE TEMP TRIGGER refuse_purges BEFORE DELETE ON entries BEGIN
+            SELECT RAISE(ABORT, 'refused');
+        END;",
+    )
+    .unwrap();
+    assert!(trash::purge_entry(&conn, entry.id, timer::now()).is_err());
+    assert!(chain::verify(&conn, alex.id, None).unwrap().intact());
+}
@@ -0,0 +1,127 @@
+use rusqlite::Connection;
+use tauri_app_lib::entries::{self, EntryInput, VolunteerEntry};
+use tauri_app_lib::search::{self, SearchQuery, HIGHLIGHT_END, HIGHLIGHT_START};
+use tauri_app_lib::{db, profiles, timer, trash};
+
+fn log(conn: &Connection, profile_id: i64, place: &str, date: &str, notes: &str) -> VolunteerEntry {
+    let input = EntryInput {
+        place: place.into(),
+        date: date.into(),
+        duration_minutes: Some(60),
+        notes: notes.into(),
+        ..Default::default()
+    };
+    entries::create(conn, profile_id, &input).unwrap()
+}
+
+fn found(conn: &Connection, query: &SearchQuery) -> Vec<i64> {
+    let mut ids: Vec<_> = search::search(conn, query)
+        .unwrap()
+        .into_iter()
+        .map(|hit| hit.entry.id)
+        .collect();
+    ids.sort();
+    ids
+}
+
+fn text(query: &str) -> SearchQuery {
+    SearchQuery {
+        query: query.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn every_word_matches_as_a_prefix() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let sam = profiles::create(&conn, "Sam").unwrap();
+    let library = log(
+        &conn,
+        alex.id,
+        "Library",
+        "2024-03-02",
+        "Tutoring after school",
+    );
+    let cafe = log(
+        &conn,
+        alex.id,
+        "Café Lumière",
+        "2024-04-06",
+        "Serving lunch",
+    );
+    let shelter = log(&conn, sam.id, "Shelter", "2024-05-04", "Tutored adults");
+
+    assert_eq!(found(&conn, &text("tutor")), [library.id, shelter.id]);
+    assert_eq!(found(&conn, &text("tutor school")), [library.id]);
+    assert_eq!(found(&conn, &text("CAFE lumiere")), [cafe.id]);
+    // Typed quotes and operators are just text.
+    assert_eq!(found(&conn, &text("\"lunch")), [cafe.id]);
+    assert!(found(&conn, &text("lunch OR tutor")).is_empty());
+    assert!(search::search(&conn, &text("  ")).is_err());
+
+    let narrowed = SearchQuery {
+        profile_id: Some(alex.id),
+        to: Some("2024-03-31".into()),
+        ..text("tutor")
+    };
+    assert_eq!(found(&conn, &narrowed), [library.id]);
+    let limited = SearchQuery {
+        limit: Some(1),
+        ..text("tutor")
+    };
+    assert_eq!(found(&conn, &limited).len(), 1);
+}
+
+#[test]
+fn edits_and_purges_keep_the_index_in_step() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let entry = log(&conn, alex.id, "Library", "2024-03-02", "Shelving books");
+    assert_eq!(found(&conn, &text("shelving")), [entry.id]);
+
+    let edited = EntryInput {
+        place: "Food Bank".into(),
+        date: entry.date.clone(),
+        duration_minutes: Some(60),
+        notes: "Sorting cans".into(),
+        ..Default::default()
+    };
+    entries::update(&conn, entry.id, &edited).unwrap();
+    assert!(found(&conn, &text("shelving")).is_empty());
+    assert!(found(&conn, &text("library")).is_empty());
+    assert_eq!(found(&conn, &text("food cans")), [entry.id]);
+
+    entries::delete(&conn, entry.id, "").unwrap();
+    assert!(found(&conn, &text("cans")).is_empty());
+    trash::purge_entry(&conn, entry.id, timer::now()).unwrap();
+    let indexed: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM entries_fts WHERE entries_fts MATCH 'cans'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(indexed, 0);
+}
+
+#[test]
+fn hits_mark_the_matched_words() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let filler = "and then we sorted more of the donated winter coats by size".repeat(3);
+    let notes = format!("{filler} before tutoring the late group");
+    log(&conn, alex.id, "Community Library", "2024-03-02", &notes);
+
+    let hits = search::search(&conn, &text("tutor library")).unwrap();
+    let hit = &hits[0];
+    assert!(hit.score > 0.0);
+    assert_eq!(
+        hit.place,
+        format!("Community {HIGHLIGHT_START}Library{HIGHLIGHT_END}")
+    );
+    assert!(hit.notes.starts_with('…'), "{}", hit.notes);
+    assert!(hit.notes.contains(&format!(
+        "{HIGHLIGHT_START}tutoring{HIGHLIGHT_END} the late group"
+    )));
+}
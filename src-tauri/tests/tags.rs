@@ -0,0 +1,134 @@
+mod common;
+
+use rusqlite::Connection;
+use tauri_app_lib::approval::{self, EntryStatus};
+use tauri_app_lib::entries::{self, EntryFilter};
+use tauri_app_lib::tags;
+use tauri_app_lib::totals::{self, GroupBy};
+use tauri_app_lib::{db, profiles, report};
+
+use common::log;
+
+fn summary(conn: &Connection, filter: &EntryFilter, group_by: GroupBy) -> Vec<(String, i64)> {
+    totals::totals(conn, filter, group_by)
+        .unwrap()
+        .into_iter()
+        .map(|t| (t.label, t.minutes / 60))
+        .collect()
+}
+
+#[test]
+fn hours_are_totalled_and_filtered_by_tag() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let library = log(&conn, alex.id, "Library", "2024-03-02", 3);
+    let shelter = log(&conn, alex.id, "Shelter", "2024-03-02", 2);
+    log(&conn, alex.id, "Park", "2024-03-02", 1);
+
+    tags::tag(&conn, library.id, "Tutoring").unwrap();
+    assert_eq!(tags::tag(&conn, library.id, " tutoring ").unwrap().len(), 1);
+    tags::tag(&conn, shelter.id, "Food service").unwrap();
+    let both = tags::tag(&conn, shelter.id, "Tutoring").unwrap();
+    assert_eq!(both.len(), 2);
+
+    let filter = EntryFilter::profile(alex.id);
+    assert_eq!(
+        summary(&conn, &filter, GroupBy::Tag),
+        [
+            ("Tutoring".to_string(), 5),
+            ("Food service".to_string(), 2),
+            ("Untagged".to_string(), 1)
+        ]
+    );
+
+    let tutoring = tags::find_by_name(&conn, "TUTORING").unwrap().unwrap();
+    let tutored = EntryFilter {
+        tag_id: Some(tutoring.id),
+        ..filter.clone()
+    };
+    assert_eq!(entries::list_matching(&conn, &tutored).unwrap().len(), 2);
+    assert_eq!(tags::by_entry(&conn, &filter).unwrap().len(), 2);
+
+    tags::untag(&conn, shelter.id, tutoring.id).unwrap();
+    assert_eq!(entries::list_matching(&conn, &tutored).unwrap().len(), 1);
+
+    // Approved entries are locked, and trashed ones are out of reach.
+    approval::set_status(&conn, library.id, EntryStatus::Submitted, "").unwrap();
+    approval::set_status(&conn, library.id, EntryStatus::Approved, "").unwrap();
+    assert!(tags::tag(&conn, library.id, "Reading").is_err());
+    assert!(tags::untag(&conn, library.id, tutoring.id).is_err());
+    entries::delete(&conn, shelter.id, "").unwrap();
+    assert!(tags::tag(&conn, shelter.id, "Reading").is_err());
+    assert!(tags::find_by_name(&conn, "Reading").unwrap().is_none());
+    let lines = report::lines(&conn, alex.id, None, None).unwrap();
+    assert!(lines.iter().any(|line| line == "Hours by cause"));
+}
+
+#[test]
+fn tags_can_be_renamed_and_merged() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let first = log(&conn, alex.id, "Library", "2024-03-02", 1);
+    let second = log(&conn, alex.id, "School", "2024-03-02", 1);
+    tags::tag(&conn, first.id, "Tutoring").unwrap();
+    tags::tag(&conn, first.id, "Homework help").unwrap();
+    tags::tag(&conn, second.id, "Homework help").unwrap();
+    let tutoring = tags::find_by_name(&conn, "Tutoring").unwrap().unwrap();
+    let homework = tags::find_by_name(&conn, "Homework help").unwrap().unwrap();
+    let sam = profiles::create(&conn, "Sam").unwrap();
+    let untagged = tags::create(&conn, "Gardening", None).unwrap();
+    // Changing either tag touches Alex's hours, so Alex's session must be unlocked.
+    let touched = tags::profiles_using(&conn, &[tutoring.id, homework.id]).unwrap();
+    assert_eq!(touched, [alex.id]);
+    assert!(!touched.contains(&sam.id));
+    assert!(tags::profiles_using(&conn, &[untagged.id])
+        .unwrap()
+        .is_empty());
+
+    assert!(tags::rename(&conn, homework.id, "tutoring").is_err());
+    assert!(tags::rename(&conn, homework.id, "  ").is_err());
+    let renamed = tags::rename(&conn, homework.id, "Homework  Club").unwrap();
+    assert_eq!(renamed.name, "Homework Club");
+    tags::set_sdg(&conn, homework.id, Some(4)).unwrap();
+
+    assert!(tags::merge(&mut conn, tutoring.id, tutoring.id).is_err());
+    let kept = tags::merge(&mut conn, tutoring.id, homework.id).unwrap();
+    assert_eq!((kept.name.as_str(), kept.sdg), ("Tutoring", Some(4)));
+    assert_eq!(tags::list(&conn).unwrap(), [untagged, kept.clone()]);
+    // The entry that had both tags keeps one.
+    assert_eq!(
+        tags::of_entry(&conn, first.id).unwrap(),
+        std::slice::from_ref(&kept)
+    );
+    assert_eq!(tags::of_entry(&conn, second.id).unwrap(), [kept]);
+}
+
+#[test]
+fn the_built_in_taxonomy_totals_hours_by_goal() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let library = log(&conn, alex.id, "Library", "2024-03-02", 3);
+    let kitchen = log(&conn, alex.id, "Soup Kitchen", "2024-03-02", 2);
+    tags::tag(&conn, library.id, "tutoring").unwrap();
+    tags::tag(&conn, library.id, "Mentoring").unwrap();
+    tags::tag(&conn, kitchen.id, "Food service").unwrap();
+    let before = tags::find_by_name(&conn, "tutoring").unwrap().unwrap();
+    assert_eq!(before.sdg, None);
+
+    let installed = tags::install_taxonomy(&mut conn).unwrap();
+    assert_eq!(installed.len(), tags::TAXONOMY.len());
+    assert_eq!(tags::install_taxonomy(&mut conn).unwrap(), installed);
+    let after = tags::get(&conn, before.id).unwrap();
+    assert_eq!((after.name.as_str(), after.sdg), ("tutoring", Some(4)));
+
+    // Tutoring and mentoring both serve goal 4; the library hours count there once.
+    assert_eq!(
+        summary(&conn, &EntryFilter::profile(alex.id), GroupBy::Sdg),
+        [
+            ("4 Quality Education".to_string(), 3),
+            ("2 Zero Hunger".to_string(), 2)
+        ]
+    );
+    assert!(tags::set_sdg(&conn, after.id, Some(18)).is_err());
+    assert!(tags::set_sdg(&conn, after.id, Some(0)).is_err());
+}
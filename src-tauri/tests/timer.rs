@@ -0,0 +1,65 @@
+use rusqlite::Connection;
+use tauri_app_lib::error::Error;
+use tauri_app_lib::{dates, db, profiles, timer};
+
+/// 2024-06-01 10:00:00 UTC.
+const TEN: i64 = 1_717_236_000;
+
+fn seeded() -> (Connection, i64) {
+    let conn = db::open_in_memory().unwrap();
+    dates::set_timezone(&conn, Some("UTC")).unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    (conn, alex.id)
+}
+
+#[test]
+fn breaks_are_left_out_of_the_logged_minutes() {
+    let (mut conn, alex) = seeded();
+    timer::start(&conn, alex, " Library ", "Shelving", TEN).unwrap();
+    timer::pause(&conn, alex, TEN + 600).unwrap();
+    let paused = timer::active(&conn, alex).unwrap().unwrap();
+    assert_eq!(paused.worked_seconds(TEN + 800), 600);
+    timer::resume(&conn, alex, TEN + 900).unwrap();
+
+    let entry = timer::stop(&mut conn, alex, None, None, TEN + 3630).unwrap();
+    assert_eq!(entry.place, "Library");
+    assert_eq!(entry.start_time.as_deref(), Some("2024-06-01T10:00"));
+    assert_eq!(entry.end_time.as_deref(), Some("2024-06-01T11:01"));
+    assert_eq!(entry.duration_minutes, 56);
+    assert!(timer::active(&conn, alex).unwrap().is_none());
+}
+
+#[test]
+fn a_session_under_a_minute_logs_one() {
+    let (mut conn, alex) = seeded();
+    timer::start(&conn, alex, "Library", "", TEN + 20).unwrap();
+    let entry = timer::stop(&mut conn, alex, Some("Park"), Some("Quick"), TEN + 40).unwrap();
+    assert_eq!(
+        (entry.place.as_str(), entry.notes.as_str()),
+        ("Park", "Quick")
+    );
+    assert_eq!(entry.end_time.as_deref(), Some("2024-06-01T10:01"));
+    assert_eq!(entry.duration_minutes, 1);
+
+    timer::start(&conn, alex, "Library", "", TEN).unwrap();
+    let instant = timer::stop(&mut conn, alex, None, None, TEN).unwrap();
+    assert_eq!(instant.duration_minutes, 1);
+}
+
+#[test]
+fn one_timer_runs_at_a_time_and_can_be_cancelled() {
+    let (mut conn, alex) = seeded();
+    assert!(matches!(
+        timer::stop(&mut conn, alex, None, None, TEN),
+        Err(Error::NotFound("running timer"))
+    ));
+    timer::start(&conn, alex, "Library", "", TEN).unwrap();
+    assert!(timer::start(&conn, alex, "Park", "", TEN + 60).is_err());
+    assert!(timer::resume(&conn, alex, TEN + 60).is_err());
+    timer::pause(&conn, alex, TEN + 60).unwrap();
+    assert!(timer::pause(&conn, alex, TEN + 120).is_err());
+
+    timer::cancel(&conn, alex).unwrap();
+    assert!(timer::active(&conn, alex).unwrap().is_none());
+    assert!(timer::cancel(&conn, alex).is_err());
+}
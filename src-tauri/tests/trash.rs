@@ -0,0 +1,148 @@
+use rusqlite::Connection;
+use tauri_app_lib::entries::{self, EntryInput, VolunteerEntry};
+use tauri_app_lib::history::{Change, History};
+use tauri_app_lib::search::{self, SearchQuery};
+use tauri_app_lib::trash::{self, TrashSettings};
+use tauri_app_lib::{db, organizations, profiles, settings, timer};
+
+fn entry(conn: &Connection, profile_id: i64, place: &str) -> VolunteerEntry {
+    let input = EntryInput {
+        place: place.into(),
+        date: "2024-03-02".into(),
+        duration_minutes: Some(60),
+        ..Default::default()
+    };
+    entries::create(conn, profile_id, &input).unwrap()
+}
+
+fn places(conn: &Connection, profile_id: i64) -> Vec<String> {
+    let mut places: Vec<_> = entries::list(conn, profile_id)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.place)
+        .collect();
+    places.sort();
+    places
+}
+
+#[test]
+fn deleted_entries_wait_in_the_trash() {
+    let conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let kept = entry(&conn, alex.id, "Library");
+    let deleted = entry(&conn, alex.id, "Food Bank");
+    entries::delete(&conn, deleted.id, "").unwrap();
+
+    assert_eq!(places(&conn, alex.id), ["Library"]);
+    assert!(entries::get(&conn, deleted.id).is_err());
+    let listed = trash::list(&conn, alex.id).unwrap();
+    assert_eq!(listed.entries.len(), 1);
+    assert_eq!(listed.entries[0].id, deleted.id);
+
+    trash::restore_entry(&conn, deleted.id).unwrap();
+    assert_eq!(places(&conn, alex.id), ["Food Bank", "Library"]);
+    // Only trashed entries can be purged.
+    assert!(trash::purge_entry(&conn, kept.id, timer::now()).is_err());
+}
+
+#[test]
+fn profiles_come_back_with_their_entries() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    entry(&conn, alex.id, "Library");
+    profiles::delete(&mut conn, alex.id).unwrap();
+
+    assert!(profiles::list(&conn).unwrap().is_empty());
+    assert!(profiles::create(&conn, "Alex").is_err());
+    let listed = trash::list(&conn, alex.id).unwrap();
+    assert_eq!(listed.profiles.len(), 1);
+    assert_eq!(listed.profiles[0].entries, 1);
+
+    trash::restore_profile(&conn, alex.id).unwrap();
+    assert_eq!(places(&conn, alex.id), ["Library"]);
+}
+
+#[test]
+fn trashed_items_stay_out_of_search_and_organization_checks() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let sam = profiles::create(&conn, "Sam").unwrap();
+    entry(&conn, alex.id, "Library");
+    entry(&conn, sam.id, "Library");
+    let shelter = entry(&conn, sam.id, "Animal Shelter");
+    entries::delete(&conn, shelter.id, "").unwrap();
+    profiles::delete(&mut conn, sam.id).unwrap();
+
+    let query = SearchQuery {
+        query: "library".into(),
+        ..Default::default()
+    };
+    let hits = search::search(&conn, &query).unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].entry.profile_id, alex.id);
+
+    // Only a trashed entry uses the shelter, so it can go, and the entry with it.
+    organizations::delete(&mut conn, shelter.organization_id).unwrap();
+    assert!(trash::entry(&conn, shelter.id).is_err());
+    let library = organizations::find_by_name(&conn, "Library")
+        .unwrap()
+        .unwrap();
+    assert!(organizations::delete(&mut conn, library.id).is_err());
+}
+
+#[test]
+fn expired_items_are_purged() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let old = entry(&conn, alex.id, "Library");
+    entries::delete(&conn, old.id, "").unwrap();
+    let deleted_at = trash::entry(&conn, old.id).unwrap().deleted_at.unwrap();
+
+    let day = 24 * 60 * 60;
+    assert_eq!(
+        trash::purge_expired(&mut conn, deleted_at + 29 * day).unwrap(),
+        0
+    );
+    assert_eq!(
+        trash::purge_expired(&mut conn, deleted_at + 31 * day).unwrap(),
+        1
+    );
+    assert!(trash::entry(&conn, old.id).is_err());
+
+    let young = entry(&conn, alex.id, "Food Bank");
+    entries::delete(&conn, young.id, "").unwrap();
+    settings::set(
+        &conn,
+        trash::SETTINGS_KEY,
+        &TrashSettings { retention_days: 0 },
+    )
+    .unwrap();
+    assert_eq!(
+        trash::purge_expired(&mut conn, deleted_at + 999 * day).unwrap(),
+        0
+    );
+}
+
+#[test]
+fn undo_and_redo_walk_the_history() {
+    let mut conn = db::open_in_memory().unwrap();
+    let alex = profiles::create(&conn, "Alex").unwrap();
+    let history = History::default();
+    let created = entry(&conn, alex.id, "Library");
+    history.record(Change::CreateEntry {
+        entry: created.clone(),
+    });
+    entries::delete(&conn, created.id, "").unwrap();
+    history.record(Change::DeleteEntry { entry: created });
+
+    let allow = |_: &Connection, _: &Change| Ok(());
+    history.undo(&mut conn, allow).unwrap();
+    assert_eq!(places(&conn, alex.id), ["Library"]);
+    history.undo(&mut conn, allow).unwrap();
+    assert!(places(&conn, alex.id).is_empty());
+    assert!(history.undo(&mut conn, allow).unwrap().is_none());
+
+    history.redo(&mut conn, allow).unwrap();
+    assert_eq!(places(&conn, alex.id), ["Library"]);
+    assert!(history.status().redo.is_some());
+}